@@ -55,7 +55,8 @@ fn derive_to_wire<'a>(rdata: &RdataStruct<'a>) -> Result<TokenStream> {
         let name = field.name;
         let to_wire_func = Ident::new(&format!("{}_to_wire", field.codec), field.name.span());
         match field.codec.as_ref() {
-            "name" | "name_uncompressed" | "text" | "byte_binary" | "binary" => {
+            "name" | "name_uncompressed" | "text" | "byte_binary" | "binary" | "short_binary"
+            | "type_bitmap" => {
                 quote! {
                     #to_wire_func(render, &self.#name)?;
                 }
@@ -100,7 +101,8 @@ fn derive_to_str<'a>(rdata: &RdataStruct<'a>) -> Result<TokenStream> {
         let name = field.name;
         let to_str_func = Ident::new(&format!("{}_to_str", field.display), field.name.span());
         match field.codec.as_ref() {
-            "name" | "name_uncompressed" | "text" | "byte_binary" | "binary" => {
+            "name" | "name_uncompressed" | "text" | "byte_binary" | "binary" | "short_binary"
+            | "type_bitmap" => {
                 if i != field_count - 1 {
                     quote! {
                         #to_str_func(f, &self.#name)?;
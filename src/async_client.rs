@@ -0,0 +1,164 @@
+use crate::client::{is_response_of, render_query};
+use crate::edns::MIN_UDP_SIZE;
+use crate::error::Error;
+use crate::header_flag::HeaderFlag;
+use crate::message::MAX_MESSAGE_LEN;
+use crate::question::Question;
+use crate::request::Request;
+use crate::response::Response;
+use crate::util::tcp::{read_message_async, write_message_async};
+use crate::util::udp::bind_for_async;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+const DEFAULT_UDP_SIZE: u16 = 1232;
+
+type Pending = Arc<Mutex<HashMap<u16, (Question, oneshot::Sender<Response>)>>>;
+
+//queries to one server share one udp socket, responses are dispatched to
+//the waiting query by message id
+pub struct AsyncClient {
+    server: SocketAddr,
+    socket: Arc<UdpSocket>,
+    pending: Pending,
+    receiver: JoinHandle<()>,
+    timeout: Duration,
+    udp_size: u16,
+    dnssec_ok: bool,
+}
+
+impl AsyncClient {
+    pub async fn connect(server: SocketAddr) -> Result<Self> {
+        let socket = bind_for_async(server).await?;
+        socket.connect(server).await?;
+        let socket = Arc::new(socket);
+        let pending = Pending::default();
+        let receiver = tokio::spawn(receive_responses(socket.clone(), pending.clone()));
+        Ok(AsyncClient {
+            server,
+            socket,
+            pending,
+            receiver,
+            timeout: DEFAULT_TIMEOUT,
+            udp_size: DEFAULT_UDP_SIZE,
+            dnssec_ok: false,
+        })
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn udp_size(mut self, udp_size: u16) -> Self {
+        self.udp_size = udp_size;
+        self
+    }
+
+    pub fn dnssec_ok(mut self, dnssec_ok: bool) -> Self {
+        self.dnssec_ok = dnssec_ok;
+        self
+    }
+
+    //id of the request is changed if it's used by another in-flight query,
+    //the response always carries the original id
+    pub async fn query(&self, req: &Request) -> Result<Response> {
+        let mut req = req.clone();
+        let id = req.header.id;
+        let (sender, receiver) = oneshot::channel();
+        {
+            let mut pending = self.pending.lock().unwrap();
+            while pending.contains_key(&req.header.id) {
+                req.header.id = rand::random::<u16>();
+            }
+            pending.insert(req.header.id, (req.question.clone(), sender));
+        }
+
+        let result = self.query_udp(&req, receiver).await;
+        self.pending.lock().unwrap().remove(&req.header.id);
+        let mut resp = result?;
+        if resp.header.is_flag_set(HeaderFlag::Truncation) {
+            resp = self.query_tcp(&req).await?;
+        }
+        resp.header.id = id;
+        Ok(resp)
+    }
+
+    async fn query_udp(
+        &self,
+        req: &Request,
+        receiver: oneshot::Receiver<Response>,
+    ) -> Result<Response> {
+        self.socket.send(&self.render_query(req)?).await?;
+        match timeout(self.timeout, receiver).await {
+            Ok(Ok(resp)) => Ok(resp),
+            Ok(Err(_)) => bail!("receiver of {} stopped", self.server),
+            Err(_) => bail!(Error::Timeout),
+        }
+    }
+
+    async fn query_tcp(&self, req: &Request) -> Result<Response> {
+        let query = self.render_query(req)?;
+        let raw = match timeout(self.timeout, async {
+            let mut stream = TcpStream::connect(self.server).await?;
+            write_message_async(&mut stream, &query).await?;
+            read_message_async(&mut stream).await
+        })
+        .await
+        {
+            Ok(raw) => raw?,
+            Err(_) => bail!(Error::Timeout),
+        };
+        let resp = Response::from_wire(&raw)?;
+        if !is_response_of(&resp, req) {
+            bail!("response from {} doesn't match query", self.server);
+        }
+        Ok(resp)
+    }
+
+    fn render_query(&self, req: &Request) -> Result<Vec<u8>> {
+        let udp_size = if self.udp_size > MIN_UDP_SIZE {
+            Some(self.udp_size)
+        } else {
+            None
+        };
+        render_query(req, udp_size, self.dnssec_ok)
+    }
+}
+
+impl Drop for AsyncClient {
+    fn drop(&mut self) {
+        self.receiver.abort();
+    }
+}
+
+async fn receive_responses(socket: Arc<UdpSocket>, pending: Pending) {
+    let mut buf = vec![0; MAX_MESSAGE_LEN];
+    loop {
+        //error like icmp port unreachable shouldn't stop other queries
+        let len = match socket.recv(&mut buf).await {
+            Ok(len) => len,
+            Err(_) => continue,
+        };
+        let resp = match Response::from_wire(&buf[..len]) {
+            Ok(resp) => resp,
+            Err(_) => continue,
+        };
+        let mut pending = pending.lock().unwrap();
+        let matched = pending.get(&resp.header.id).is_some_and(|(question, _)| {
+            resp.question == *question && resp.header.is_flag_set(HeaderFlag::QueryRespone)
+        });
+        if matched {
+            let (_, sender) = pending.remove(&resp.header.id).unwrap();
+            let _ = sender.send(resp);
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use crate::edns::{Edns, MIN_UDP_SIZE};
+use crate::message::{Message, MAX_MESSAGE_LEN};
+use crate::message_render::MessageRender;
+use crate::notify::{NotifyRequest, NotifyResponse};
+use crate::opcode::Opcode;
+use crate::request::Request;
+use crate::response::Response;
+use crate::rr_type::RRType;
+use crate::util::tcp::{read_message_async, write_message_async};
+use anyhow::Result;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+const DEFAULT_TCP_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_PIPELINED_RESPONSES: usize = 32;
+
+//no response is sent back when handler returns none
+pub trait Handler: Send + Sync + 'static {
+    //edns is the one carried by the query, response should have edns only
+    //when the query has it
+    fn handle(
+        &self,
+        req: Request,
+        edns: Option<Edns>,
+        src: SocketAddr,
+    ) -> impl Future<Output = Option<Response>> + Send;
+
+    fn handle_notify(
+        &self,
+        _req: NotifyRequest,
+        _src: SocketAddr,
+    ) -> impl Future<Output = Option<NotifyResponse>> + Send {
+        async { None }
+    }
+
+    //axfr or ixfr over tcp, return the rendered messages which are sent
+    //back in order, raw is the request on wire to verify its tsig
+    fn handle_transfer(
+        &self,
+        _req: Message,
+        _raw: &[u8],
+        _src: SocketAddr,
+    ) -> impl Future<Output = Option<Vec<Vec<u8>>>> + Send {
+        async { None }
+    }
+}
+
+//each query is handled in its own task, queries pipelined over one tcp
+//connection are answered in the order they are handled, rfc7766 6.2.1.1
+pub struct Server<H> {
+    handler: Arc<H>,
+    tcp_idle_timeout: Duration,
+}
+
+impl<H: Handler> Server<H> {
+    pub fn new(handler: H) -> Self {
+        Server {
+            handler: Arc::new(handler),
+            tcp_idle_timeout: DEFAULT_TCP_IDLE_TIMEOUT,
+        }
+    }
+
+    pub fn tcp_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.tcp_idle_timeout = idle_timeout;
+        self
+    }
+
+    pub async fn serve_udp(&self, socket: UdpSocket) -> Result<()> {
+        let socket = Arc::new(socket);
+        let mut buf = vec![0; MAX_MESSAGE_LEN];
+        loop {
+            let (len, src) = socket.recv_from(&mut buf).await?;
+            let raw = buf[..len].to_vec();
+            let handler = self.handler.clone();
+            let socket = socket.clone();
+            tokio::spawn(async move {
+                for resp in handle_query(&*handler, &raw, src, true).await {
+                    let _ = socket.send_to(&resp, src).await;
+                }
+            });
+        }
+    }
+
+    pub async fn serve_tcp(&self, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, src) = listener.accept().await?;
+            tokio::spawn(serve_connection(
+                self.handler.clone(),
+                stream,
+                src,
+                self.tcp_idle_timeout,
+            ));
+        }
+    }
+
+    pub async fn serve(&self, socket: UdpSocket, listener: TcpListener) -> Result<()> {
+        tokio::try_join!(self.serve_udp(socket), self.serve_tcp(listener))?;
+        Ok(())
+    }
+}
+
+async fn serve_connection<H: Handler>(
+    handler: Arc<H>,
+    stream: TcpStream,
+    src: SocketAddr,
+    idle_timeout: Duration,
+) {
+    let (mut reader, mut writer) = stream.into_split();
+    //messages of one response are written together
+    let (sender, mut receiver) = mpsc::channel::<Vec<Vec<u8>>>(MAX_PIPELINED_RESPONSES);
+    let writing = tokio::spawn(async move {
+        while let Some(messages) = receiver.recv().await {
+            for message in messages {
+                if write_message_async(&mut writer, &message).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    while let Ok(Ok(raw)) = timeout(idle_timeout, read_message_async(&mut reader)).await {
+        let handler = handler.clone();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            let messages = handle_query(&*handler, &raw, src, false).await;
+            if !messages.is_empty() {
+                let _ = sender.send(messages).await;
+            }
+        });
+    }
+    //responses of the queries being handled are still sent
+    drop(sender);
+    let _ = writing.await;
+}
+
+async fn handle_query<H: Handler>(
+    handler: &H,
+    raw: &[u8],
+    src: SocketAddr,
+    udp: bool,
+) -> Vec<Vec<u8>> {
+    let query = match Message::from_wire(raw) {
+        Ok(query) => query,
+        Err(_) => return Vec::new(),
+    };
+    let messages = match query.header.opcode {
+        Opcode::Notify => match NotifyRequest::from_wire(raw) {
+            Ok(req) => handler
+                .handle_notify(req, src)
+                .await
+                .and_then(|resp| render_notify(&resp).ok())
+                .map(|resp| vec![resp]),
+            Err(_) => None,
+        },
+        Opcode::Query
+            if !udp
+                && query.question().is_some_and(|question| {
+                    matches!(question.typ, RRType::AXFR | RRType::IXFR)
+                }) =>
+        {
+            handler.handle_transfer(query, raw, src).await
+        }
+        _ => {
+            let edns = query.get_edns();
+            let max_len = if udp {
+                edns.as_ref()
+                    .map_or(MIN_UDP_SIZE, |edns| edns.udp_size.max(MIN_UDP_SIZE))
+                    as usize
+            } else {
+                MAX_MESSAGE_LEN
+            };
+            match Request::from_wire(raw) {
+                Ok(req) => handler
+                    .handle(req, edns, src)
+                    .await
+                    .and_then(|resp| render_response(&resp, max_len).ok())
+                    .map(|resp| vec![resp]),
+                Err(_) => None,
+            }
+        }
+    };
+    messages.unwrap_or_default()
+}
+
+//response which exceeds the max length is truncated by to_wire
+fn render_response(resp: &Response, max_len: usize) -> Result<Vec<u8>> {
+    let mut buf = vec![0; max_len];
+    let mut render = MessageRender::new(&mut buf);
+    let len = resp.to_wire(&mut render)?;
+    Ok(buf[..len].to_vec())
+}
+
+fn render_notify(resp: &NotifyResponse) -> Result<Vec<u8>> {
+    let mut buf = [0; 512];
+    let mut render = MessageRender::new(&mut buf);
+    let len = resp.to_wire(&mut render)?;
+    Ok(buf[..len].to_vec())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::async_client::AsyncClient;
+    use crate::client::render_query;
+    use crate::header_flag::HeaderFlag;
+    use crate::name::Name;
+    use crate::response::SectionType;
+    use crate::response_builder::ResponseBuilder;
+    use crate::rr_type::RRType;
+    use crate::util::fixture::big_rrset;
+    use std::collections::HashSet;
+
+    //answer a.example.com. with one address, the other names with a big
+    //rrset which has to be sent over tcp
+    struct TestHandler;
+
+    impl Handler for TestHandler {
+        async fn handle(
+            &self,
+            req: Request,
+            _edns: Option<Edns>,
+            _src: SocketAddr,
+        ) -> Option<Response> {
+            let name = req.question.name.clone();
+            let count = if name.eq(&Name::new("a.example.com.").unwrap()) {
+                1
+            } else {
+                //delay to make responses of pipelined queries out of order
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                100
+            };
+            let mut resp = Response::with_question(name.clone(), req.question.typ);
+            resp.header.id = req.header.id;
+            let mut builder = ResponseBuilder::new(&mut resp);
+            builder.set_flag(HeaderFlag::QueryRespone);
+            builder.add_rrset(SectionType::Answer, big_rrset(&name.to_string(), count));
+            builder.done();
+            Some(resp)
+        }
+    }
+
+    async fn start_server() -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let listener = TcpListener::bind(addr).await.unwrap();
+        tokio::spawn(async move {
+            Server::new(TestHandler)
+                .serve(socket, listener)
+                .await
+                .unwrap();
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_queries() {
+        let addr = start_server().await;
+        let client = Arc::new(AsyncClient::connect(addr).await.unwrap().udp_size(512));
+        let mut queries = Vec::new();
+        for name in ["a.example.com.", "b.example.com.", "a.example.com."] {
+            let client = client.clone();
+            //same id for all the queries
+            let mut req = Request::new(Name::new(name).unwrap(), RRType::A);
+            req.header.id = 1000;
+            queries.push(tokio::spawn(async move {
+                let resp = client.query(&req).await.unwrap();
+                assert_eq!(resp.header.id, req.header.id);
+                assert_eq!(resp.question, req.question);
+                resp.section(SectionType::Answer).unwrap()[0].rdatas.len()
+            }));
+        }
+        let mut counts = Vec::new();
+        for query in queries {
+            counts.push(query.await.unwrap());
+        }
+        //the big response is truncated and retried over tcp
+        assert_eq!(counts, vec![1, 100, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_pipelining() {
+        let addr = start_server().await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut ids = HashSet::new();
+        for (id, name) in ["b.example.com.", "a.example.com."].iter().enumerate() {
+            let mut req = Request::new(Name::new(name).unwrap(), RRType::A);
+            req.header.id = id as u16;
+            ids.insert(req.header.id);
+            write_message_async(&mut stream, &render_query(&req, None, false).unwrap())
+                .await
+                .unwrap();
+        }
+        //the slow query is answered last
+        let mut answered = Vec::new();
+        for _ in 0..2 {
+            let raw = read_message_async(&mut stream).await.unwrap();
+            let resp = Response::from_wire(&raw).unwrap();
+            assert!(ids.remove(&resp.header.id));
+            answered.push(resp.question.name.to_string());
+        }
+        assert_eq!(answered, vec!["a.example.com.", "b.example.com."]);
+    }
+
+    #[test]
+    fn test_render_response() {
+        let mut resp = Response::with_question(Name::new("a.example.com.").unwrap(), RRType::A);
+        let mut builder = ResponseBuilder::new(&mut resp);
+        builder.add_rrset(SectionType::Answer, big_rrset("a.example.com.", 100));
+        builder.done();
+        let raw = render_response(&resp, MAX_MESSAGE_LEN).unwrap();
+        assert_eq!(Response::from_wire(&raw).unwrap(), resp);
+        let raw = render_response(&resp, 512).unwrap();
+        let truncated = Response::from_wire(&raw).unwrap();
+        assert!(truncated.header.is_flag_set(HeaderFlag::Truncation));
+        assert_eq!(truncated.header.an_count, 0);
+    }
+}
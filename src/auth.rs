@@ -0,0 +1,510 @@
+use crate::async_server::Handler;
+use crate::axfr::AxfrSender;
+use crate::edns::Edns;
+use crate::error::Error;
+use crate::header_flag::HeaderFlag;
+use crate::ixfr::{IxfrSender, Journal};
+use crate::message::Message;
+use crate::message_render::MessageRender;
+use crate::name::Name;
+use crate::notify::{NotifyRequest, NotifyResponse};
+use crate::rcode::Rcode;
+use crate::rdata::RData;
+use crate::request::Request;
+use crate::response::{Response, Section, SectionType};
+use crate::response_builder::ResponseBuilder;
+use crate::rr_type::RRType;
+use crate::tsig::{TsigAlgorithm, TsigError, TsigKey, TsigSigner};
+use crate::zone::Zone;
+use crate::zonefile::parse_zone;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//zones served by one server, query goes to the zone nearest to qname
+#[derive(Clone, Default)]
+pub struct ZoneTable {
+    zones: HashMap<Name, Zone>,
+}
+
+impl ZoneTable {
+    pub fn new() -> Self {
+        ZoneTable {
+            zones: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.zones.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    pub fn add_zone(&mut self, zone: Zone) -> Result<()> {
+        if zone.soa().is_none() {
+            bail!("zone {} has no soa", zone.origin());
+        }
+        if self.zones.contains_key(zone.origin()) {
+            bail!("zone {} is duplicate", zone.origin());
+        }
+        self.zones.insert(zone.origin().clone(), zone);
+        Ok(())
+    }
+
+    pub fn load_zone<P: AsRef<Path>>(&mut self, origin: Name, path: P) -> Result<()> {
+        let content = fs::read_to_string(path)?;
+        let rrsets = parse_zone(&content, &origin)?;
+        self.add_zone(Zone::from_rrsets(origin, rrsets)?)
+    }
+
+    pub fn get_zone(&self, origin: &Name) -> Option<&Zone> {
+        self.zones.get(origin)
+    }
+
+    pub fn find_zone(&self, name: &Name) -> Option<&Zone> {
+        (0..name.label_count())
+            .filter_map(|level| name.parent(level).ok())
+            .find_map(|parent| self.zones.get(&parent))
+    }
+}
+
+//how a transfer request is authorized by its tsig
+enum TransferAuth<'a> {
+    Unsigned,
+    //key and mac of the verified request
+    Signed(&'a TsigKey, Vec<u8>),
+    //error response is signed by it
+    Failed(Box<TsigSigner>),
+}
+
+//answer queries from the zone table, which could be replaced while
+//serving, transfers are only served over tcp to the allowed addresses or
+//requests signed by the transfer keys
+#[derive(Clone)]
+pub struct AuthHandler {
+    zones: Arc<RwLock<Arc<ZoneTable>>>,
+    transfer_addrs: Vec<IpAddr>,
+    transfer_keys: Vec<TsigKey>,
+    authority_ns: bool,
+    minimal_responses: bool,
+}
+
+impl AuthHandler {
+    pub fn new(zones: ZoneTable) -> Self {
+        AuthHandler {
+            zones: Arc::new(RwLock::new(Arc::new(zones))),
+            transfer_addrs: Vec::new(),
+            transfer_keys: Vec::new(),
+            authority_ns: false,
+            minimal_responses: false,
+        }
+    }
+
+    //add ns of the zone to authority section of positive answer
+    pub fn authority_ns(mut self, authority_ns: bool) -> Self {
+        self.authority_ns = authority_ns;
+        self
+    }
+
+    //strip optional authority and additional data from responses
+    pub fn minimal_responses(mut self, minimal_responses: bool) -> Self {
+        self.minimal_responses = minimal_responses;
+        self
+    }
+
+    pub fn allow_transfer(mut self, addrs: Vec<IpAddr>) -> Self {
+        self.transfer_addrs = addrs;
+        self
+    }
+
+    //signed transfer request is answered with signed messages
+    pub fn transfer_keys(mut self, keys: Vec<TsigKey>) -> Self {
+        self.transfer_keys = keys;
+        self
+    }
+
+    pub fn zones(&self) -> Arc<ZoneTable> {
+        self.zones.read().unwrap().clone()
+    }
+
+    pub fn set_zones(&self, zones: ZoneTable) {
+        *self.zones.write().unwrap() = Arc::new(zones);
+    }
+
+    pub fn query(&self, req: &Request, edns: Option<&Edns>) -> Response {
+        let zones = self.zones();
+        match zones.find_zone(&req.question.name) {
+            Some(_) if matches!(req.question.typ, RRType::AXFR | RRType::IXFR) => {
+                error_response(req, Rcode::NotImp)
+            }
+            Some(zone) => {
+                let mut resp = zone.query(req, edns);
+                let authoritative = resp.header.is_flag_set(HeaderFlag::AuthAnswer);
+                let mut builder = ResponseBuilder::new(&mut resp);
+                if self.authority_ns && authoritative {
+                    if let Some(ns) = zone.get_rrset(zone.origin(), RRType::NS) {
+                        builder
+                            .add_authority_ns(ns.clone())
+                            .add_additional(|name, typ| zone.additional(name, typ, false));
+                    }
+                }
+                if self.minimal_responses {
+                    builder.minimize(edns.is_some_and(|edns| edns.dnssec_aware));
+                }
+                builder.done();
+                resp
+            }
+            None => error_response(req, Rcode::Refused),
+        }
+    }
+
+    pub fn notify(&self, req: NotifyRequest) -> NotifyResponse {
+        let known = self.zones().get_zone(&req.question.name).is_some();
+        let mut resp = NotifyResponse::new(req);
+        if !known {
+            resp.header.rcode = Rcode::NotAuth;
+        }
+        resp
+    }
+
+    //zone transfer without journal, ixfr is answered with the whole zone,
+    //refused or failed transfer is answered with one error response
+    pub fn transfer(&self, req: Message, raw: &[u8], src: IpAddr) -> Result<Vec<Vec<u8>>> {
+        let query = match req.question() {
+            Some(question) => Request {
+                header: req.header.clone(),
+                question: question.clone(),
+            },
+            None => bail!("transfer request has no question"),
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let (key, mac) = match self.transfer_auth(&req, raw, now) {
+            TransferAuth::Signed(key, mac) => (Some(key), mac),
+            TransferAuth::Unsigned if self.transfer_addrs.contains(&src) => (None, Vec::new()),
+            TransferAuth::Unsigned => return transfer_error(&query, Rcode::Refused, None),
+            TransferAuth::Failed(signer) => {
+                return transfer_error(&query, Rcode::NotAuth, Some(*signer))
+            }
+        };
+        let signer = key.map(|key| TsigSigner::new(key.clone(), Some(&mac), now));
+        let zones = self.zones();
+        let zone = match zones.get_zone(&query.question.name) {
+            Some(zone) => zone,
+            None => return transfer_error(&query, Rcode::NotAuth, signer),
+        };
+        let journal = Journal::new();
+        let messages = if query.question.typ == RRType::IXFR {
+            let mut sender = IxfrSender::new(zone, &journal);
+            if let Some(key) = key {
+                sender = sender.tsig(key, &mac);
+            }
+            sender.messages(&req, now)
+        } else {
+            let mut sender = AxfrSender::new(zone);
+            if let Some(key) = key {
+                sender = sender.tsig(key, &mac);
+            }
+            sender.messages(&query, now)
+        };
+        messages.or_else(|_| transfer_error(&query, Rcode::ServFail, signer))
+    }
+
+    //error response of failed verification is signed with the tsig error,
+    //rfc8945 5.2
+    fn transfer_auth(&self, req: &Message, raw: &[u8], now: u64) -> TransferAuth<'_> {
+        let (key_name, algorithm, request_mac) = match req
+            .section(SectionType::Additional)
+            .and_then(|rrsets| rrsets.last())
+        {
+            Some(rrset) if rrset.typ == RRType::TSIG => match rrset.rdatas.first() {
+                Some(RData::TSig(tsig)) => {
+                    (rrset.name.clone(), tsig.algorithm.clone(), tsig.mac.clone())
+                }
+                _ => return TransferAuth::Unsigned,
+            },
+            _ => return TransferAuth::Unsigned,
+        };
+        let key = self
+            .transfer_keys
+            .iter()
+            .find(|key| key.name.eq(&key_name) && key.algorithm.to_name().eq(&algorithm));
+        let key = match key {
+            Some(key) => key,
+            None => {
+                let algorithm =
+                    TsigAlgorithm::from_name(&algorithm).unwrap_or(TsigAlgorithm::HmacSha256);
+                let unknown = TsigKey::new(key_name, algorithm, &[]);
+                let signer = TsigSigner::new(unknown, None, now).error(TsigError::BadKey);
+                return TransferAuth::Failed(Box::new(signer));
+            }
+        };
+        match key.verify(raw, None, now) {
+            Ok(tsig) => TransferAuth::Signed(key, tsig.mac),
+            Err(e) => {
+                let error = match e.downcast_ref::<Error>() {
+                    Some(Error::Tsig(error)) => *error,
+                    _ => TsigError::BadSig,
+                };
+                let signer = TsigSigner::new(key.clone(), Some(&request_mac), now).error(error);
+                TransferAuth::Failed(Box::new(signer))
+            }
+        }
+    }
+}
+
+impl Handler for AuthHandler {
+    async fn handle(&self, req: Request, edns: Option<Edns>, _src: SocketAddr) -> Option<Response> {
+        Some(self.query(&req, edns.as_ref()))
+    }
+
+    async fn handle_notify(&self, req: NotifyRequest, _src: SocketAddr) -> Option<NotifyResponse> {
+        Some(self.notify(req))
+    }
+
+    async fn handle_transfer(
+        &self,
+        req: Message,
+        raw: &[u8],
+        src: SocketAddr,
+    ) -> Option<Vec<Vec<u8>>> {
+        self.transfer(req, raw, src.ip()).ok()
+    }
+}
+
+fn transfer_error(req: &Request, rcode: Rcode, signer: Option<TsigSigner>) -> Result<Vec<Vec<u8>>> {
+    let mut buf = [0; 512];
+    let mut render = MessageRender::new(&mut buf);
+    if let Some(signer) = signer {
+        render.set_tsig(signer);
+    }
+    let len = error_response(req, rcode).to_wire(&mut render)?;
+    Ok(vec![buf[..len].to_vec()])
+}
+
+fn error_response(req: &Request, rcode: Rcode) -> Response {
+    let mut resp = Response {
+        header: req.header.clone(),
+        question: req.question.clone(),
+        sections: [Section(None), Section(None), Section(None)],
+    };
+    ResponseBuilder::new(&mut resp)
+        .make_response()
+        .clear_flag(HeaderFlag::AuthAnswer)
+        .rcode(rcode)
+        .done();
+    resp
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::async_server::Server;
+    use crate::axfr::AxfrReceiver;
+    use crate::client::Client;
+    use crate::notify::Notifier;
+    use crate::response::SectionType;
+    use crate::util::fixture::{big_rrset, build_zone, name, tsig_key, ZONE};
+    use std::time::Duration;
+    use tokio::net::{TcpListener, UdpSocket};
+
+    fn build_zones() -> ZoneTable {
+        let mut zone = build_zone("example.com.", ZONE);
+        zone.add_rrset(big_rrset("big.example.com.", 100)).unwrap();
+        let mut zones = ZoneTable::new();
+        zones.add_zone(zone.clone()).unwrap();
+        assert!(zones.add_zone(zone).is_err());
+        zones
+    }
+
+    #[test]
+    fn test_zone_table() {
+        let zones = build_zones();
+        let zone = zones
+            .find_zone(&Name::new("a.b.example.com.").unwrap())
+            .unwrap();
+        assert_eq!(zone.origin(), &Name::new("example.com.").unwrap());
+        assert!(zones.find_zone(&Name::new("com.").unwrap()).is_none());
+        assert!(zones
+            .get_zone(&Name::new("www.example.com.").unwrap())
+            .is_none());
+
+        let handler = AuthHandler::new(zones);
+        let resp = handler.query(
+            &Request::new(Name::new("www.example.net.").unwrap(), RRType::A),
+            None,
+        );
+        assert_eq!(resp.header.rcode, Rcode::Refused);
+        let resp = handler.query(
+            &Request::new(Name::new("example.com.").unwrap(), RRType::AXFR),
+            None,
+        );
+        assert_eq!(resp.header.rcode, Rcode::NotImp);
+        handler.set_zones(ZoneTable::new());
+        let resp = handler.query(
+            &Request::new(Name::new("www.example.com.").unwrap(), RRType::A),
+            None,
+        );
+        assert_eq!(resp.header.rcode, Rcode::Refused);
+    }
+
+    #[test]
+    fn test_authority_ns_and_minimal() {
+        let req = Request::new(name("www.example.com."), RRType::A);
+        let resp = AuthHandler::new(build_zones()).query(&req, None);
+        assert_eq!(resp.header.ns_count, 0);
+
+        let handler = AuthHandler::new(build_zones()).authority_ns(true);
+        let resp = handler.query(&req, None);
+        assert_eq!(resp.header.ns_count, 2);
+        assert_eq!(resp.header.ar_count, 2);
+
+        let handler = handler.minimal_responses(true);
+        let resp = handler.query(&req, None);
+        assert_eq!(resp.header.an_count, 2);
+        assert_eq!(resp.header.ns_count, 0);
+        assert_eq!(resp.header.ar_count, 0);
+    }
+
+    fn transfer(
+        handler: &AuthHandler,
+        zone: &str,
+        key: Option<TsigKey>,
+        src: &str,
+    ) -> Vec<Message> {
+        let req = Message::with_query(name(zone), RRType::AXFR);
+        let mut buf = [0; 512];
+        let mut render = MessageRender::new(&mut buf);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        if let Some(key) = key {
+            render.set_tsig(TsigSigner::new(key, None, now));
+        }
+        let len = req.to_wire(&mut render).unwrap();
+        let req = Message::from_wire(&buf[..len]).unwrap();
+        handler
+            .transfer(req, &buf[..len], src.parse().unwrap())
+            .unwrap()
+            .iter()
+            .map(|message| Message::from_wire(message).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_transfer_acl() {
+        let handler = AuthHandler::new(build_zones())
+            .allow_transfer(vec!["127.0.0.1".parse().unwrap()])
+            .transfer_keys(vec![tsig_key()]);
+        let messages = transfer(&handler, "example.com.", None, "127.0.0.1");
+        assert_eq!(messages[0].header.rcode, Rcode::NoError);
+        assert!(messages[0].section(SectionType::Answer).is_some());
+
+        for (zone, key, src, rcode) in [
+            ("example.com.", None, "127.0.0.2", Rcode::Refused),
+            ("example.net.", None, "127.0.0.1", Rcode::NotAuth),
+            (
+                "example.com.",
+                Some(TsigKey::new(
+                    name("other-key."),
+                    TsigAlgorithm::HmacSha256,
+                    b"other",
+                )),
+                "127.0.0.1",
+                Rcode::NotAuth,
+            ),
+            (
+                "example.com.",
+                Some(TsigKey::new(
+                    name("tsig-key."),
+                    TsigAlgorithm::HmacSha256,
+                    b"other",
+                )),
+                "127.0.0.1",
+                Rcode::NotAuth,
+            ),
+        ] {
+            let messages = transfer(&handler, zone, key, src);
+            assert_eq!(messages.len(), 1);
+            assert_eq!(messages[0].header.rcode, rcode);
+            assert!(messages[0].section(SectionType::Answer).is_none());
+        }
+
+        //signed request is allowed from any address
+        let messages = transfer(&handler, "example.com.", Some(tsig_key()), "127.0.0.2");
+        assert_eq!(messages[0].header.rcode, Rcode::NoError);
+        assert!(messages[0].section(SectionType::Answer).is_some());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_auth_server() {
+        let zones = build_zones();
+        let zone = zones
+            .get_zone(&Name::new("example.com.").unwrap())
+            .unwrap()
+            .clone();
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let listener = TcpListener::bind(addr).await.unwrap();
+        tokio::spawn(async move {
+            Server::new(AuthHandler::new(zones).transfer_keys(vec![tsig_key()]))
+                .serve(socket, listener)
+                .await
+                .unwrap();
+        });
+
+        let received = tokio::task::spawn_blocking(move || {
+            let client = Client::new(vec![addr])
+                .udp_size(512)
+                .timeout(Duration::from_secs(1));
+            let resp = client
+                .query(&Request::new(
+                    Name::new("www.example.com.").unwrap(),
+                    RRType::A,
+                ))
+                .unwrap();
+            assert!(resp.header.is_flag_set(HeaderFlag::AuthAnswer));
+            assert_eq!(resp.section_rrset_count(SectionType::Answer), 1);
+            //truncated over udp and retried over tcp
+            let resp = client
+                .query(&Request::new(
+                    Name::new("big.example.com.").unwrap(),
+                    RRType::A,
+                ))
+                .unwrap();
+            assert_eq!(
+                resp.section(SectionType::Answer).unwrap()[0].rdatas.len(),
+                100
+            );
+
+            let notifier = Notifier::new(Name::new("example.com.").unwrap(), vec![addr])
+                .timeout(Duration::from_secs(1));
+            assert!(notifier.notify().is_empty());
+
+            //unsigned transfer is refused
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let mut stream = std::net::TcpStream::connect(addr).unwrap();
+            assert!(AxfrReceiver::new(Name::new("example.com.").unwrap())
+                .receive(&mut stream, now)
+                .is_err());
+            let key = tsig_key();
+            let mut stream = std::net::TcpStream::connect(addr).unwrap();
+            AxfrReceiver::new(Name::new("example.com.").unwrap())
+                .tsig(&key)
+                .receive(&mut stream, now)
+                .unwrap()
+        })
+        .await
+        .unwrap();
+        assert_eq!(received.rrsets(), zone.rrsets());
+    }
+}
@@ -0,0 +1,450 @@
+use crate::header::Header;
+use crate::header_flag::HeaderFlag;
+use crate::message::{Message, MAX_MESSAGE_LEN};
+use crate::message_render::MessageRender;
+use crate::name::Name;
+use crate::question::Question;
+use crate::rcode::Rcode;
+use crate::rdata::RData;
+use crate::request::Request;
+use crate::response::SectionType;
+use crate::rr_type::RRType;
+use crate::rrset::RRset;
+use crate::tsig::{TsigKey, TsigSigner, TsigStreamVerifier};
+use crate::util::tcp::{read_message, write_message};
+use crate::zone::Zone;
+use anyhow::{bail, ensure, Result};
+use std::io::{Read, Write};
+
+//make each message of a transfer fit in common tcp windows
+pub(crate) const DEFAULT_MESSAGE_LEN: usize = 16384;
+
+//split zone into messages of axfr response, soa is the first and the last
+//record, each message has its own compression table, rfc5936 2.2
+pub struct AxfrSender<'a> {
+    zone: &'a Zone,
+    max_len: usize,
+    tsig: Option<(&'a TsigKey, Vec<u8>)>,
+}
+
+impl<'a> AxfrSender<'a> {
+    pub fn new(zone: &'a Zone) -> Self {
+        AxfrSender {
+            zone,
+            max_len: DEFAULT_MESSAGE_LEN,
+            tsig: None,
+        }
+    }
+
+    //message is closed once it exceeds max_len, one rr larger than it is
+    //sent in its own message
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len.min(MAX_MESSAGE_LEN);
+        self
+    }
+
+    //every message is signed, request_mac is the mac of the verified request
+    pub fn tsig(mut self, key: &'a TsigKey, request_mac: &[u8]) -> Self {
+        self.tsig = Some((key, request_mac.to_vec()));
+        self
+    }
+
+    pub fn messages(&self, req: &Request, now: u64) -> Result<Vec<Vec<u8>>> {
+        ensure!(
+            req.question.typ == RRType::AXFR && req.question.name.eq(self.zone.origin()),
+            "request isn't axfr of zone {}",
+            self.zone.origin()
+        );
+        let soa = match self.zone.soa() {
+            Some(soa) => soa,
+            None => bail!("zone {} has no soa", self.zone.origin()),
+        };
+        let records = self.zone.rrsets().into_iter().chain(std::iter::once(soa));
+        render_transfer(
+            req.header.id,
+            &req.question,
+            records,
+            self.max_len,
+            self.tsig.as_ref(),
+            now,
+        )
+    }
+
+    pub fn send<W: Write>(&self, stream: &mut W, req: &Request, now: u64) -> Result<()> {
+        for message in self.messages(req, now)? {
+            write_message(stream, &message)?;
+        }
+        Ok(())
+    }
+}
+
+//render records into messages of a transfer response, a new message is
+//started when the current one exceeds max_len, which includes the tsig rr
+pub(crate) fn render_transfer<'b>(
+    id: u16,
+    question: &Question,
+    records: impl Iterator<Item = &'b RRset>,
+    max_len: usize,
+    tsig: Option<&(&TsigKey, Vec<u8>)>,
+    now: u64,
+) -> Result<Vec<Vec<u8>>> {
+    let mut header = Header {
+        id,
+        qd_count: 1,
+        ..Default::default()
+    };
+    header
+        .set_flag(HeaderFlag::QueryRespone, true)
+        .set_flag(HeaderFlag::AuthAnswer, true);
+    let mut buf = vec![0; MAX_MESSAGE_LEN];
+    let mut render = MessageRender::new(&mut buf);
+    if let Some((key, mac)) = tsig {
+        render.set_tsig(TsigSigner::new((*key).clone(), Some(mac), now));
+    }
+    let tsig_len = render.tsig().map_or(0, |signer| signer.tsig_len());
+    let hard_limit = MAX_MESSAGE_LEN - tsig_len;
+    let max_len = max_len.min(MAX_MESSAGE_LEN).saturating_sub(tsig_len);
+    let mut messages = Vec::new();
+    let mut count = 0;
+    header.to_wire(&mut render)?;
+    question.to_wire(&mut render)?;
+    for rrset in records {
+        for rr in split_rrset(rrset) {
+            let pos = render.len();
+            if rr.to_wire(&mut render).is_ok() && render.len() <= max_len {
+                count += 1;
+                continue;
+            }
+            render.truncate(pos)?;
+            if count > 0 {
+                finish_message(&mut render, count)?;
+                messages.push(render.data().to_vec());
+                render.reset();
+                header.to_wire(&mut render)?;
+                question.to_wire(&mut render)?;
+            }
+            //rr larger than max_len is sent alone
+            ensure!(
+                rr.to_wire(&mut render).is_ok() && render.len() <= hard_limit,
+                "rr of {} is too large",
+                rr.name
+            );
+            count = 1;
+        }
+    }
+    finish_message(&mut render, count)?;
+    messages.push(render.data().to_vec());
+    Ok(messages)
+}
+
+fn finish_message(render: &mut MessageRender, count: u16) -> Result<()> {
+    //answer count is the fourth field of header
+    render.write_u16_at(6, count)?;
+    render.sign()
+}
+
+pub(crate) fn split_rrset(rrset: &RRset) -> impl Iterator<Item = RRset> + '_ {
+    rrset.rdatas.iter().map(move |rdata| RRset {
+        name: rrset.name.clone(),
+        typ: rrset.typ,
+        class: rrset.class,
+        ttl: rrset.ttl,
+        rdatas: vec![rdata.clone()],
+    })
+}
+
+//request axfr of a zone and rebuild the zone from the response stream
+pub struct AxfrReceiver<'a> {
+    zone: Name,
+    tsig: Option<&'a TsigKey>,
+}
+
+impl<'a> AxfrReceiver<'a> {
+    pub fn new(zone: Name) -> Self {
+        AxfrReceiver { zone, tsig: None }
+    }
+
+    pub fn tsig(mut self, key: &'a TsigKey) -> Self {
+        self.tsig = Some(key);
+        self
+    }
+
+    pub fn receive<S: Read + Write>(&self, stream: &mut S, now: u64) -> Result<Zone> {
+        let mut req = Message::with_query(self.zone.clone(), RRType::AXFR);
+        req.header.set_flag(HeaderFlag::RecursionDesired, false);
+        let mut transfer = Transfer::new(self.zone.clone(), req.header.id);
+        request_transfer(stream, &req, self.tsig, now, |msg| {
+            transfer.consume(msg)?;
+            Ok(transfer.done)
+        })?;
+        Zone::from_rrsets(self.zone.clone(), transfer.rrsets)
+    }
+}
+
+//send the request and feed each message of the response to consume until
+//it returns true, every message is verified if key is specified
+pub(crate) fn request_transfer<S, F>(
+    stream: &mut S,
+    req: &Message,
+    tsig: Option<&TsigKey>,
+    now: u64,
+    mut consume: F,
+) -> Result<()>
+where
+    S: Read + Write,
+    F: FnMut(Message) -> Result<bool>,
+{
+    let mut buf = [0; 512];
+    let mut render = MessageRender::new(&mut buf);
+    if let Some(key) = tsig {
+        render.set_tsig(TsigSigner::new(key.clone(), None, now));
+    }
+    req.to_wire(&mut render)?;
+    let request_mac = render
+        .tsig()
+        .map_or(Vec::new(), |signer| signer.mac().to_vec());
+    write_message(stream, render.data())?;
+
+    let mut verifier = tsig.map(|key| TsigStreamVerifier::new(key, &request_mac));
+    loop {
+        let raw = read_message(stream)?;
+        if let Some(ref mut verifier) = verifier {
+            verifier.verify(&raw, now)?;
+        }
+        if consume(Message::from_wire(&raw)?)? {
+            break;
+        }
+    }
+    match verifier {
+        Some(verifier) => verifier.finish(),
+        None => Ok(()),
+    }
+}
+
+//records received so far, the transfer is done when the soa shows again
+struct Transfer {
+    zone: Name,
+    id: u16,
+    serial: Option<u32>,
+    rrsets: Vec<RRset>,
+    done: bool,
+}
+
+impl Transfer {
+    fn new(zone: Name, id: u16) -> Self {
+        Transfer {
+            zone,
+            id,
+            serial: None,
+            rrsets: Vec::new(),
+            done: false,
+        }
+    }
+
+    fn consume(&mut self, mut msg: Message) -> Result<()> {
+        ensure!(
+            msg.header.id == self.id,
+            "response id doesn't match request"
+        );
+        ensure!(
+            msg.header.rcode == Rcode::NoError,
+            "axfr failed with {}",
+            msg.header.rcode
+        );
+        for question in &msg.questions {
+            ensure!(
+                question.typ == RRType::AXFR && question.name.eq(&self.zone),
+                "question of response doesn't match request"
+            );
+        }
+
+        let answer = msg.take_section(SectionType::Answer).unwrap_or_default();
+        ensure!(!answer.is_empty(), "axfr response has no record");
+        for rr in answer.iter().flat_map(split_rrset) {
+            ensure!(!self.done, "record {} after the last soa", rr.name);
+            if rr.typ != RRType::SOA {
+                ensure!(self.serial.is_some(), "first record isn't soa");
+                self.rrsets.push(rr);
+                continue;
+            }
+
+            ensure!(
+                rr.name.eq(&self.zone),
+                "soa of {} isn't at zone apex",
+                rr.name
+            );
+            let serial = match rr.rdatas[0] {
+                RData::SOA(ref soa) => soa.serial,
+                _ => unreachable!(),
+            };
+            match self.serial {
+                None => {
+                    self.serial = Some(serial);
+                    self.rrsets.push(rr);
+                }
+                Some(first) => {
+                    ensure!(
+                        first == serial,
+                        "serial changed from {} to {} during transfer",
+                        first,
+                        serial
+                    );
+                    self.done = true;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::fixture::{big_rrset, build_zone, tsig_key, ZONE};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    fn big_zone() -> Zone {
+        let mut zone = build_zone("example.com.", ZONE);
+        zone.add_rrset(big_rrset("big.example.com.", 200)).unwrap();
+        zone
+    }
+
+    fn same_zone(a: &Zone, b: &Zone) {
+        assert_eq!(a.rrsets(), b.rrsets());
+    }
+
+    #[test]
+    fn test_axfr_messages() {
+        let zone = big_zone();
+        let req = Request::new(zone.origin().clone(), RRType::AXFR);
+        let messages = AxfrSender::new(&zone)
+            .max_len(512)
+            .messages(&req, 0)
+            .unwrap();
+        assert!(messages.len() > 5);
+        assert!(messages.iter().all(|message| message.len() <= 512));
+
+        let mut transfer = Transfer::new(zone.origin().clone(), req.header.id);
+        for message in &messages {
+            assert!(!transfer.done);
+            transfer
+                .consume(Message::from_wire(message).unwrap())
+                .unwrap();
+        }
+        assert!(transfer.done);
+        same_zone(
+            &Zone::from_rrsets(zone.origin().clone(), transfer.rrsets).unwrap(),
+            &zone,
+        );
+
+        //tsig rr is counted in max_len
+        let key = tsig_key();
+        let signed = AxfrSender::new(&zone)
+            .max_len(512)
+            .tsig(&key, &[])
+            .messages(&req, 0)
+            .unwrap();
+        assert!(signed.len() > messages.len());
+        assert!(signed.iter().all(|message| message.len() <= 512));
+
+        //every rr is larger than max_len, including the leading soa
+        let messages = AxfrSender::new(&zone)
+            .max_len(40)
+            .messages(&req, 0)
+            .unwrap();
+        let count = zone
+            .rrsets()
+            .iter()
+            .map(|rrset| rrset.rdatas.len())
+            .sum::<usize>();
+        assert_eq!(messages.len(), count + 1);
+
+        let other = Request::new(Name::new("example.net.").unwrap(), RRType::AXFR);
+        assert!(AxfrSender::new(&zone).messages(&other, 0).is_err());
+        let soa = Request::new(zone.origin().clone(), RRType::SOA);
+        assert!(AxfrSender::new(&zone).messages(&soa, 0).is_err());
+    }
+
+    #[test]
+    fn test_invalid_axfr_stream() {
+        let zone = big_zone();
+        let req = Request::new(zone.origin().clone(), RRType::AXFR);
+        let messages = AxfrSender::new(&zone)
+            .max_len(512)
+            .messages(&req, 0)
+            .unwrap();
+        let consume_all = |messages: &[Vec<u8>]| {
+            let mut transfer = Transfer::new(zone.origin().clone(), req.header.id);
+            messages
+                .iter()
+                .try_for_each(|message| transfer.consume(Message::from_wire(message)?))
+        };
+        //missing the first message which has the leading soa
+        assert!(consume_all(&messages[1..]).is_err());
+        //records after the trailing soa
+        let mut repeated = messages.clone();
+        repeated.push(messages[1].clone());
+        assert!(consume_all(&repeated).is_err());
+
+        //serial changed in the middle of the transfer
+        let mut last = Message::from_wire(messages.last().unwrap()).unwrap();
+        let answer = last.section_mut(SectionType::Answer).unwrap();
+        let soa = answer.last_mut().unwrap();
+        if let RData::SOA(ref mut soa) = soa.rdatas[0] {
+            soa.serial += 1;
+        }
+        last.recalculate_header();
+        let mut buf = [0; 2048];
+        let mut render = MessageRender::new(&mut buf);
+        let len = last.to_wire(&mut render).unwrap();
+        let mut changed = messages[..messages.len() - 1].to_vec();
+        changed.push(buf[..len].to_vec());
+        let err = consume_all(&changed).unwrap_err();
+        assert!(err.to_string().contains("serial changed"));
+
+        let mut transfer = Transfer::new(zone.origin().clone(), req.header.id.wrapping_add(1));
+        assert!(transfer
+            .consume(Message::from_wire(&messages[0]).unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_axfr_over_tcp() {
+        let zone = big_zone();
+        let key = tsig_key();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server_zone = zone.clone();
+        let server = thread::spawn(move || {
+            let key = tsig_key();
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                let raw = read_message(&mut stream).unwrap();
+                let req = Request::from_wire(&raw).unwrap();
+                let mut sender = AxfrSender::new(&server_zone).max_len(2048);
+                //the second request is signed
+                let mac;
+                if req.header.ar_count > 0 {
+                    mac = key.verify(&raw, None, 1600000000).unwrap().mac;
+                    sender = sender.tsig(&key, &mac);
+                }
+                sender.send(&mut stream, &req, 1600000000).unwrap();
+            }
+        });
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let received = AxfrReceiver::new(zone.origin().clone())
+            .receive(&mut stream, 1600000000)
+            .unwrap();
+        same_zone(&received, &zone);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let received = AxfrReceiver::new(zone.origin().clone())
+            .tsig(&key)
+            .receive(&mut stream, 1600000000)
+            .unwrap();
+        same_zone(&received, &zone);
+        server.join().unwrap();
+    }
+}
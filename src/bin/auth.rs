@@ -0,0 +1,132 @@
+use std::net::{IpAddr, SocketAddr};
+
+extern crate clap;
+extern crate r53;
+
+use anyhow::{anyhow, bail, Result};
+use clap::{App, Arg};
+use r53::auth::{AuthHandler, ZoneTable};
+use r53::{Name, Server, TsigKey};
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::signal::unix::{signal, SignalKind};
+
+//zone is specified as origin:path
+fn load_zones(zones: &[String]) -> Result<ZoneTable> {
+    let mut table = ZoneTable::new();
+    for zone in zones {
+        let (origin, path) = match zone.find(':') {
+            Some(pos) => (&zone[..pos], &zone[pos + 1..]),
+            None => bail!("zone {} isn't in format origin:path", zone),
+        };
+        table
+            .load_zone(Name::new(origin)?, path)
+            .map_err(|e| anyhow!("load zone {} failed: {}", origin, e))?;
+    }
+    Ok(table)
+}
+
+//key is specified as name:algorithm:secret, secret is in base64
+fn parse_key(key: &str) -> Result<TsigKey> {
+    let fields = key.splitn(3, ':').collect::<Vec<_>>();
+    if fields.len() != 3 {
+        bail!("key {} isn't in format name:algorithm:secret", key);
+    }
+    TsigKey::from_base64(Name::new(fields[0])?, fields[1].parse()?, fields[2])
+}
+
+async fn run(
+    addr: SocketAddr,
+    zones: Vec<String>,
+    transfer_addrs: Vec<IpAddr>,
+    transfer_keys: Vec<TsigKey>,
+) -> Result<()> {
+    let handler = AuthHandler::new(load_zones(&zones)?)
+        .allow_transfer(transfer_addrs)
+        .transfer_keys(transfer_keys);
+    let socket = UdpSocket::bind(addr).await?;
+    let listener = TcpListener::bind(addr).await?;
+    let reloader = handler.clone();
+    let mut hangup = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            match load_zones(&zones) {
+                Ok(table) => {
+                    eprintln!("reload {} zones", table.len());
+                    reloader.set_zones(table);
+                }
+                Err(e) => eprintln!("reload failed, keep serving old zones: {}", e),
+            }
+        }
+    });
+
+    eprintln!("serve {} zones on {}", handler.zones().len(), addr);
+    Server::new(handler).serve(socket, listener).await
+}
+
+fn main() -> Result<()> {
+    let matches = App::new("r53-auth")
+        .arg(
+            Arg::with_name("listen")
+                .help("address to listen on")
+                .short("l")
+                .long("listen")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("zone")
+                .help("zone to serve as origin:path, reloaded on SIGHUP")
+                .short("z")
+                .long("zone")
+                .value_name("ZONE")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("allow-transfer")
+                .help("address allowed to transfer zones without tsig")
+                .long("allow-transfer")
+                .value_name("ADDR")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("transfer-key")
+                .help("tsig key to sign transfers as name:algorithm:secret")
+                .long("transfer-key")
+                .value_name("KEY")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .get_matches();
+
+    let addr = matches.value_of("listen").unwrap_or("0.0.0.0:53");
+    let addr = addr
+        .parse::<SocketAddr>()
+        .map_err(|_| anyhow!("listen address {} is invalid", addr))?;
+    let zones = matches
+        .values_of("zone")
+        .unwrap()
+        .map(|zone| zone.to_string())
+        .collect::<Vec<_>>();
+    let transfer_addrs = matches
+        .values_of("allow-transfer")
+        .map_or(Ok(Vec::new()), |addrs| {
+            addrs.map(|addr| addr.parse::<IpAddr>()).collect()
+        })
+        .map_err(|e| anyhow!("transfer address is invalid: {}", e))?;
+    let transfer_keys = matches
+        .values_of("transfer-key")
+        .map_or(Ok(Vec::new()), |keys| keys.map(parse_key).collect())
+        .map_err(|e| anyhow!("transfer key is invalid: {}", e))?;
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    runtime
+        .block_on(run(addr, zones, transfer_addrs, transfer_keys))
+        .map_err(|e| anyhow!("auth server failed: {}", e))
+}
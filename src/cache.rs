@@ -0,0 +1,823 @@
+use crate::domaintree::tree::{DomainTree, FindResultFlag};
+use crate::edns::Edns;
+use crate::header_flag::HeaderFlag;
+use crate::message::MAX_MESSAGE_LEN;
+use crate::message_render::MessageRender;
+use crate::name::Name;
+use crate::rcode::Rcode;
+use crate::rdata::RData;
+use crate::request::Request;
+use crate::response::{Response, Section, SectionType, MAX_CNAME_CHAIN};
+use crate::response_builder::ResponseBuilder;
+use crate::rr_class::RRClass;
+use crate::rr_type::RRType;
+use crate::rrset::{RRTtl, RRset};
+use crate::zone::cname_target;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//rfc8767 recommends to cap ttl to 7 days and return stale data with ttl 30
+const MAX_TTL: u32 = 7 * 24 * 3600;
+const STALE_TTL: u32 = 30;
+const DEFAULT_MAX_SIZE: usize = 64 * 1024 * 1024;
+//size of name and fields of an rr on wire
+const RR_HEADER_LEN: usize = 10;
+
+pub trait Clock {
+    //seconds since unix epoch
+    fn now(&self) -> u64;
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+    }
+}
+
+//data with higher trust replaces the lower one, rfc2181 5.4.1
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Trust {
+    Additional,
+    Glue,
+    Authority,
+    Answer,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheAnswer {
+    RRset(RRset),
+    //soa of the zone which proves the nonexistence
+    NXDomain(RRset),
+    NoData(RRset),
+}
+
+impl CacheAnswer {
+    fn set_ttl(&mut self, ttl: u32) {
+        match self {
+            CacheAnswer::RRset(rrset)
+            | CacheAnswer::NXDomain(rrset)
+            | CacheAnswer::NoData(rrset) => rrset.ttl = RRTtl(ttl),
+        }
+    }
+}
+
+struct Entry {
+    answer: CacheAnswer,
+    trust: Trust,
+    expire: u64,
+    tick: u64,
+    size: usize,
+}
+
+//nxdomain is cached with type any since it covers all types of the name
+type Key = (RRType, RRClass);
+type Entries = HashMap<Key, Entry>;
+
+pub struct Cache<C = SystemClock> {
+    clock: C,
+    tree: DomainTree<Entries>,
+    //least recently used entry has the smallest tick
+    lru: BTreeMap<u64, (Name, Key)>,
+    tick: u64,
+    size: usize,
+    max_size: usize,
+    max_stale: u32,
+    max_ttl: u32,
+    //buffer to measure the wire size of rdatas
+    buf: Vec<u8>,
+}
+
+impl Cache<SystemClock> {
+    pub fn new() -> Self {
+        Cache::with_clock(SystemClock)
+    }
+}
+
+impl Default for Cache<SystemClock> {
+    fn default() -> Self {
+        Cache::new()
+    }
+}
+
+impl<C: Clock> Cache<C> {
+    pub fn with_clock(clock: C) -> Self {
+        Cache {
+            clock,
+            tree: DomainTree::new(),
+            lru: BTreeMap::new(),
+            tick: 0,
+            size: 0,
+            max_size: DEFAULT_MAX_SIZE,
+            max_stale: 0,
+            max_ttl: MAX_TTL,
+            buf: vec![0; MAX_MESSAGE_LEN],
+        }
+    }
+
+    //approximate bytes of cached data, least recently used data is evicted
+    //when it's exceeded
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    //seconds expired data is kept to be served by get_stale
+    pub fn max_stale(mut self, max_stale: u32) -> Self {
+        self.max_stale = max_stale;
+        self
+    }
+
+    //ttl of cached data and synthesized responses never exceeds it
+    pub fn max_ttl(mut self, max_ttl: u32) -> Self {
+        self.max_ttl = max_ttl;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.lru.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lru.is_empty()
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn insert_rrset(&mut self, rrset: RRset, trust: Trust) {
+        let key = (rrset.typ, rrset.class);
+        let name = rrset.name.clone();
+        let ttl = rrset.ttl.0;
+        self.insert(name, key, CacheAnswer::RRset(rrset), ttl, trust);
+    }
+
+    //negative answer lives for the smaller one of soa ttl and minimum,
+    //rfc2308 5
+    pub fn insert_nxdomain(&mut self, name: Name, class: RRClass, soa: RRset) {
+        if let Some(ttl) = negative_ttl(&soa) {
+            let answer = CacheAnswer::NXDomain(soa);
+            self.insert(name, (RRType::ANY, class), answer, ttl, Trust::Answer);
+        }
+    }
+
+    pub fn insert_nodata(&mut self, name: Name, typ: RRType, class: RRClass, soa: RRset) {
+        if let Some(ttl) = negative_ttl(&soa) {
+            let answer = CacheAnswer::NoData(soa);
+            self.insert(name, (typ, class), answer, ttl, Trust::Answer);
+        }
+    }
+
+    //cache the records and the negative answer in response from servers of
+    //zone, records out of the zone are dropped, answer is only accepted
+    //along the cname and dname chain of qname, rfc2181 5.4.1
+    pub fn insert_response(&mut self, resp: &Response, zone: &Name) {
+        let authoritative = resp.header.is_flag_set(HeaderFlag::AuthAnswer);
+        let answer_trust = if authoritative {
+            Trust::Answer
+        } else {
+            Trust::Authority
+        };
+        let in_zone = |rrset: &&RRset| !rrset.rdatas.is_empty() && rrset.name.is_subdomain(zone);
+        let mut qname = resp.question.name.clone();
+        let qtype = resp.question.typ;
+        let mut answered = false;
+        for rrset in resp
+            .section(SectionType::Answer)
+            .into_iter()
+            .flatten()
+            .filter(in_zone)
+        {
+            if rrset.name.eq(&qname) {
+                if rrset.typ == qtype {
+                    answered = true;
+                } else if rrset.typ == RRType::CNAME {
+                    if let Some(target) = cname_target(rrset) {
+                        qname = target.clone();
+                    }
+                }
+            } else if rrset.typ != RRType::DNAME || !qname.is_subdomain(&rrset.name) {
+                continue;
+            }
+            self.insert_rrset(rrset.clone(), answer_trust);
+        }
+
+        let authority = resp
+            .section(SectionType::Authority)
+            .into_iter()
+            .flatten()
+            .filter(in_zone);
+        let soa = authority
+            .clone()
+            .find(|rrset| rrset.typ == RRType::SOA && qname.is_subdomain(&rrset.name));
+        let mut ns_names = Vec::new();
+        for rrset in authority {
+            match rrset.typ {
+                RRType::SOA => {}
+                //ns above the cut of qname isn't for this answer
+                RRType::NS if !qname.is_subdomain(&rrset.name) => {}
+                RRType::NS => {
+                    ns_names.extend(rrset.rdatas.iter().filter_map(|rdata| match rdata {
+                        RData::NS(ns) => Some(ns.name.clone()),
+                        _ => None,
+                    }));
+                    let trust = if authoritative {
+                        Trust::Authority
+                    } else {
+                        Trust::Glue
+                    };
+                    self.insert_rrset(rrset.clone(), trust);
+                }
+                _ => self.insert_rrset(rrset.clone(), Trust::Authority),
+            }
+        }
+        if let (Some(soa), false) = (soa, answered) {
+            match resp.header.rcode {
+                Rcode::NXDomain => self.insert_nxdomain(qname, resp.question.class, soa.clone()),
+                Rcode::NoError => {
+                    self.insert_nodata(qname, qtype, resp.question.class, soa.clone())
+                }
+                _ => {}
+            }
+        }
+
+        for rrset in resp
+            .section(SectionType::Additional)
+            .into_iter()
+            .flatten()
+            .filter(in_zone)
+        {
+            let trust = match rrset.typ {
+                RRType::OPT | RRType::TSIG => continue,
+                RRType::A | RRType::AAAA if ns_names.iter().any(|name| name.eq(&rrset.name)) => {
+                    Trust::Glue
+                }
+                _ => Trust::Additional,
+            };
+            self.insert_rrset(rrset.clone(), trust);
+        }
+    }
+
+    //fresh data with remaining ttl, nxdomain of the name is returned for
+    //any type
+    pub fn get(&mut self, name: &Name, typ: RRType, class: RRClass) -> Option<CacheAnswer> {
+        self.lookup(name, typ, class, false)
+    }
+
+    //fresh data, or expired data still in the stale window with a short ttl
+    pub fn get_stale(&mut self, name: &Name, typ: RRType, class: RRClass) -> Option<CacheAnswer> {
+        self.lookup(name, typ, class, true)
+    }
+
+    //ns of the nearest ancestor to start resolving from
+    pub fn closest_ns(&mut self, name: &Name, class: RRClass) -> Option<RRset> {
+        (0..name.label_count())
+            .filter_map(|level| name.parent(level).ok())
+            .find_map(|parent| match self.get(&parent, RRType::NS, class) {
+                Some(CacheAnswer::RRset(ns)) => Some(ns),
+                _ => None,
+            })
+    }
+
+    //build the whole response from cached data following cnames, return
+    //none if any part of the answer isn't cached, the edns of the query
+    //decides the udp size of the response
+    pub fn synthesize(&mut self, req: &Request, edns: Option<&Edns>) -> Option<Response> {
+        let class = req.question.class;
+        let typ = req.question.typ;
+        let mut answers = Vec::new();
+        let mut name = req.question.name.clone();
+        let (rcode, soa) = loop {
+            if answers.len() > MAX_CNAME_CHAIN {
+                return None;
+            }
+            match self.get(&name, typ, class) {
+                Some(CacheAnswer::RRset(rrset)) => {
+                    answers.push(rrset);
+                    break (Rcode::NoError, None);
+                }
+                Some(CacheAnswer::NXDomain(soa)) => break (Rcode::NXDomain, Some(soa)),
+                Some(CacheAnswer::NoData(soa)) => break (Rcode::NoError, Some(soa)),
+                None if typ != RRType::CNAME => {}
+                None => return None,
+            }
+            let cname = match self.get(&name, RRType::CNAME, class) {
+                Some(CacheAnswer::RRset(cname)) => cname,
+                _ => return None,
+            };
+            name = match cname.rdatas.first() {
+                Some(RData::CName(target)) => target.name.clone(),
+                _ => return None,
+            };
+            if answers.iter().any(|rrset: &RRset| rrset.name.eq(&name)) {
+                return None;
+            }
+            answers.push(cname);
+        };
+
+        let mut resp = Response {
+            header: req.header.clone(),
+            question: req.question.clone(),
+            sections: [Section(None), Section(None), Section(None)],
+        };
+        let mut builder = ResponseBuilder::new(&mut resp);
+        builder
+            .make_response()
+            .set_flag(HeaderFlag::RecursionAvailable)
+            .clear_flag(HeaderFlag::AuthAnswer)
+            .rcode(rcode);
+        for mut rrset in answers {
+            rrset.ttl = RRTtl(rrset.ttl.0.min(self.max_ttl));
+            builder.add_rrset(SectionType::Answer, rrset);
+        }
+        match soa {
+            Some(mut soa) => {
+                soa.ttl = RRTtl(soa.ttl.0.min(self.max_ttl));
+                builder.add_rrset(SectionType::Authority, soa);
+            }
+            None => {
+                if let Some(mut ns) = self.closest_ns(&name, class) {
+                    ns.ttl = RRTtl(ns.ttl.0.min(self.max_ttl));
+                    builder.add_authority_ns(ns);
+                }
+            }
+        }
+        let max_ttl = self.max_ttl;
+        builder.add_additional(|name, typ| match self.get(name, typ, class) {
+            Some(CacheAnswer::RRset(mut rrset)) => {
+                rrset.ttl = RRTtl(rrset.ttl.0.min(max_ttl));
+                Some(rrset)
+            }
+            _ => None,
+        });
+        builder.query_edns(edns).done();
+        Some(resp)
+    }
+
+    pub fn remove(&mut self, name: &Name, typ: RRType, class: RRClass) {
+        if let Some(entry) = self.remove_entry(name, (typ, class)) {
+            self.lru.remove(&entry.tick);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.tree.clear();
+        self.lru.clear();
+        self.size = 0;
+    }
+
+    fn lookup(
+        &mut self,
+        name: &Name,
+        typ: RRType,
+        class: RRClass,
+        allow_stale: bool,
+    ) -> Option<CacheAnswer> {
+        self.lookup_key(name, (typ, class), allow_stale)
+            .or_else(|| {
+                //nodata of type any only answers query of type any
+                match self.lookup_key(name, (RRType::ANY, class), allow_stale) {
+                    Some(answer @ CacheAnswer::NXDomain(_)) => Some(answer),
+                    _ => None,
+                }
+            })
+    }
+
+    fn lookup_key(&mut self, name: &Name, key: Key, allow_stale: bool) -> Option<CacheAnswer> {
+        let now = self.clock.now();
+        let max_stale = self.max_stale as u64;
+        let tick = self.next_tick();
+        let (mut answer, expire, old_tick) = {
+            let entry = self.entries_mut(name)?.get_mut(&key)?;
+            let old_tick = entry.tick;
+            if entry.expire <= now && entry.expire + max_stale <= now {
+                (None, entry.expire, old_tick)
+            } else {
+                entry.tick = tick;
+                (Some(entry.answer.clone()), entry.expire, old_tick)
+            }
+        };
+        match answer {
+            None => {
+                self.remove(name, key.0, key.1);
+                None
+            }
+            Some(_) if expire <= now && !allow_stale => {
+                self.lru.remove(&old_tick);
+                self.lru.insert(tick, (name.clone(), key));
+                None
+            }
+            Some(ref mut cached) => {
+                self.lru.remove(&old_tick);
+                self.lru.insert(tick, (name.clone(), key));
+                if expire > now {
+                    cached.set_ttl((expire - now) as u32);
+                } else {
+                    cached.set_ttl(STALE_TTL);
+                }
+                answer
+            }
+        }
+    }
+
+    fn insert(&mut self, name: Name, key: Key, answer: CacheAnswer, ttl: u32, trust: Trust) {
+        if ttl == 0 {
+            return;
+        }
+        let now = self.clock.now();
+        if let Some(entry) = self
+            .entries_mut(&name)
+            .and_then(|entries| entries.get(&key))
+        {
+            if entry.expire > now && entry.trust > trust {
+                return;
+            }
+        }
+        self.remove(&name, key.0, key.1);
+        //records of the name prove it exists
+        if let CacheAnswer::RRset(_) = answer {
+            self.remove(&name, RRType::ANY, key.1);
+        }
+
+        let tick = self.next_tick();
+        let size = answer_size(&mut self.buf, &answer);
+        let entry = Entry {
+            answer,
+            trust,
+            expire: now + ttl.min(self.max_ttl) as u64,
+            tick,
+            size,
+        };
+        match self.entries_mut(&name) {
+            Some(entries) => {
+                entries.insert(key, entry);
+            }
+            None => {
+                let mut entries = HashMap::new();
+                entries.insert(key, entry);
+                self.tree.insert(name.clone(), Some(entries));
+            }
+        }
+        self.lru.insert(tick, (name, key));
+        self.size += size;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.max_size {
+            let tick = match self.lru.keys().next() {
+                Some(tick) => *tick,
+                None => break,
+            };
+            let (name, key) = self.lru.remove(&tick).unwrap();
+            self.remove_entry(&name, key);
+        }
+    }
+
+    fn remove_entry(&mut self, name: &Name, key: Key) -> Option<Entry> {
+        let entries = self.entries_mut(name)?;
+        let entry = entries.remove(&key)?;
+        if entries.is_empty() {
+            self.tree.remove(name);
+        }
+        self.size -= entry.size;
+        Some(entry)
+    }
+
+    fn entries_mut(&mut self, name: &Name) -> Option<&mut Entries> {
+        let result = self.tree.find(name);
+        if result.flag == FindResultFlag::ExacatMatch {
+            result.get_value_mut()
+        } else {
+            None
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+fn negative_ttl(soa: &RRset) -> Option<u32> {
+    match soa.rdatas.first() {
+        Some(RData::SOA(rdata)) => Some(soa.ttl.0.min(rdata.minimum)),
+        _ => None,
+    }
+}
+
+//wire length of the rrset without compression
+//buf is reused to render rdatas since the cache knows no rdata length
+fn answer_size(buf: &mut [u8], answer: &CacheAnswer) -> usize {
+    let rrset = match answer {
+        CacheAnswer::RRset(rrset) | CacheAnswer::NXDomain(rrset) | CacheAnswer::NoData(rrset) => {
+            rrset
+        }
+    };
+    let mut render = MessageRender::new(buf);
+    render.set_compress(false);
+    rrset
+        .rdatas
+        .iter()
+        .map(|rdata| {
+            let rdata_len = render
+                .truncate(0)
+                .and_then(|_| rdata.to_wire(&mut render))
+                .map_or(0, |_| render.len());
+            rrset.name.len() + RR_HEADER_LEN + rdata_len
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::request::Request;
+    use crate::util::fixture::{build_zone, name, rrset};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[derive(Clone, Default)]
+    struct TestClock(Rc<Cell<u64>>);
+
+    impl TestClock {
+        fn advance(&self, secs: u64) {
+            self.0.set(self.0.get() + secs);
+        }
+    }
+
+    impl Clock for TestClock {
+        fn now(&self) -> u64 {
+            self.0.get()
+        }
+    }
+
+    fn get_rrset<C: Clock>(cache: &mut Cache<C>, n: &str, typ: RRType) -> Option<RRset> {
+        match cache.get(&name(n), typ, RRClass::IN) {
+            Some(CacheAnswer::RRset(rrset)) => Some(rrset),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_ttl_and_trust() {
+        let clock = TestClock::default();
+        let mut cache = Cache::with_clock(clock.clone());
+        cache.insert_rrset(rrset("www.example.com. 300 IN A 1.1.1.1"), Trust::Answer);
+        cache.insert_rrset(rrset("www.example.com. 0 IN AAAA ::1"), Trust::Answer);
+        assert_eq!(cache.len(), 1);
+        clock.advance(100);
+        let cached = get_rrset(&mut cache, "www.example.com.", RRType::A).unwrap();
+        assert_eq!(cached.ttl.0, 200);
+        assert!(get_rrset(&mut cache, "example.com.", RRType::A).is_none());
+
+        //glue doesn't replace answer until it expires
+        cache.insert_rrset(rrset("www.example.com. 300 IN A 2.2.2.2"), Trust::Glue);
+        let cached = get_rrset(&mut cache, "www.example.com.", RRType::A).unwrap();
+        assert_eq!(cached, rrset("www.example.com. 200 IN A 1.1.1.1"));
+        cache.insert_rrset(rrset("www.example.com. 300 IN A 3.3.3.3"), Trust::Answer);
+        let cached = get_rrset(&mut cache, "www.example.com.", RRType::A).unwrap();
+        assert_eq!(cached, rrset("www.example.com. 300 IN A 3.3.3.3"));
+
+        clock.advance(300);
+        assert!(get_rrset(&mut cache, "www.example.com.", RRType::A).is_none());
+        assert!(cache.is_empty());
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[test]
+    fn test_negative_cache() {
+        let clock = TestClock::default();
+        let mut cache = Cache::with_clock(clock.clone());
+        let soa = rrset(
+            "example.com. 3600 IN SOA ns1.example.com. root.example.com. 1 3600 900 86400 300",
+        );
+        cache.insert_nxdomain(name("a.example.com."), RRClass::IN, soa.clone());
+        cache.insert_nodata(name("example.com."), RRType::AAAA, RRClass::IN, soa.clone());
+        assert!(matches!(
+            cache.get(&name("a.example.com."), RRType::MX, RRClass::IN),
+            Some(CacheAnswer::NXDomain(soa)) if soa.ttl.0 == 300
+        ));
+        assert!(matches!(
+            cache.get(&name("example.com."), RRType::AAAA, RRClass::IN),
+            Some(CacheAnswer::NoData(_))
+        ));
+        assert!(cache
+            .get(&name("example.com."), RRType::A, RRClass::IN)
+            .is_none());
+        clock.advance(300);
+        assert!(cache
+            .get(&name("a.example.com."), RRType::MX, RRClass::IN)
+            .is_none());
+
+        //nodata of type any doesn't answer other types
+        cache.insert_nodata(
+            name("b.example.com."),
+            RRType::ANY,
+            RRClass::IN,
+            soa.clone(),
+        );
+        assert!(cache
+            .get(&name("b.example.com."), RRType::A, RRClass::IN)
+            .is_none());
+        assert!(matches!(
+            cache.get(&name("b.example.com."), RRType::ANY, RRClass::IN),
+            Some(CacheAnswer::NoData(_))
+        ));
+
+        //records of the name replace its nxdomain
+        cache.insert_nxdomain(name("c.example.com."), RRClass::IN, soa);
+        cache.insert_rrset(rrset("c.example.com. 300 IN A 1.1.1.1"), Trust::Answer);
+        assert!(cache
+            .get(&name("c.example.com."), RRType::MX, RRClass::IN)
+            .is_none());
+        assert!(matches!(
+            cache.get(&name("c.example.com."), RRType::A, RRClass::IN),
+            Some(CacheAnswer::RRset(_))
+        ));
+    }
+
+    #[test]
+    fn test_insert_response() {
+        const ZONE: &str = r#"
+$ORIGIN example.com.
+$TTL 3600
+@           IN SOA   ns1 hostmaster 1 3600 900 604800 300
+            IN NS    ns1
+ns1         IN A     1.1.1.1
+www         IN CNAME web
+web         IN A     3.3.3.3
+sub         IN NS    ns.sub
+ns.sub      IN A     4.4.4.4
+"#;
+        let zone = build_zone("example.com.", ZONE);
+        let mut cache = Cache::with_clock(TestClock::default());
+        for (qname, qtype) in [
+            ("www.example.com.", RRType::A),
+            ("a.sub.example.com.", RRType::A),
+            ("none.example.com.", RRType::A),
+            ("web.example.com.", RRType::MX),
+        ] {
+            cache.insert_response(
+                &zone.query(&Request::new(name(qname), qtype), None),
+                zone.origin(),
+            );
+        }
+        assert!(get_rrset(&mut cache, "www.example.com.", RRType::CNAME).is_some());
+        assert!(get_rrset(&mut cache, "web.example.com.", RRType::A).is_some());
+        assert!(get_rrset(&mut cache, "ns.sub.example.com.", RRType::A).is_some());
+        assert_eq!(
+            cache
+                .closest_ns(&name("a.b.sub.example.com."), RRClass::IN)
+                .unwrap()
+                .name,
+            name("sub.example.com.")
+        );
+        assert!(matches!(
+            cache.get(&name("none.example.com."), RRType::A, RRClass::IN),
+            Some(CacheAnswer::NXDomain(_))
+        ));
+        assert!(matches!(
+            cache.get(&name("web.example.com."), RRType::MX, RRClass::IN),
+            Some(CacheAnswer::NoData(_))
+        ));
+    }
+
+    #[test]
+    fn test_insert_response_bailiwick() {
+        let mut resp = Response::with_question(name("www.example.com."), RRType::A);
+        let mut builder = ResponseBuilder::new(&mut resp);
+        builder.make_response();
+        for s in [
+            "www.example.com. 300 IN CNAME web.example.com.",
+            "web.example.com. 300 IN A 3.3.3.3",
+            "other.example.com. 300 IN A 5.5.5.5",
+            "www.example.net. 300 IN A 6.6.6.6",
+        ] {
+            builder.add_rrset(SectionType::Answer, rrset(s));
+        }
+        for s in [
+            "com. 300 IN NS ns.example.net.",
+            "other.example.com. 300 IN NS ns.example.net.",
+            "example.com. 300 IN NS ns1.example.com.",
+        ] {
+            builder.add_rrset(SectionType::Authority, rrset(s));
+        }
+        for s in [
+            "ns1.example.com. 300 IN A 1.1.1.1",
+            "ns.example.net. 300 IN A 7.7.7.7",
+        ] {
+            builder.add_rrset(SectionType::Additional, rrset(s));
+        }
+        builder.done();
+
+        let mut cache = Cache::with_clock(TestClock::default());
+        cache.insert_response(&resp, &name("example.com."));
+        for (n, typ) in [
+            ("www.example.com.", RRType::CNAME),
+            ("web.example.com.", RRType::A),
+            ("example.com.", RRType::NS),
+            ("ns1.example.com.", RRType::A),
+        ] {
+            assert!(get_rrset(&mut cache, n, typ).is_some());
+        }
+        for (n, typ) in [
+            ("other.example.com.", RRType::A),
+            ("www.example.net.", RRType::A),
+            ("com.", RRType::NS),
+            ("other.example.com.", RRType::NS),
+            ("ns.example.net.", RRType::A),
+        ] {
+            assert!(get_rrset(&mut cache, n, typ).is_none());
+        }
+    }
+
+    #[test]
+    fn test_synthesize() {
+        let mut cache = Cache::with_clock(TestClock::default()).max_ttl(3600);
+        for s in [
+            "example.com. 86400 IN NS ns1.example.com.",
+            "ns1.example.com. 86400 IN A 1.1.1.1",
+            "www.example.com. 300 IN CNAME web.example.com.",
+            "web.example.com. 300 IN A 3.3.3.3",
+            "loop.example.com. 300 IN CNAME loop.example.com.",
+        ] {
+            cache.insert_rrset(rrset(s), Trust::Answer);
+        }
+        let soa = rrset(
+            "example.com. 3600 IN SOA ns1.example.com. root.example.com. 1 3600 900 86400 300",
+        );
+        cache.insert_nxdomain(name("none.example.com."), RRClass::IN, soa);
+
+        let req = Request::new(name("www.example.com."), RRType::A);
+        let edns = Edns {
+            version: 0,
+            extened_rcode: 0,
+            udp_size: 4096,
+            dnssec_aware: false,
+            options: None,
+        };
+        let resp = cache.synthesize(&req, Some(&edns)).unwrap();
+        assert_eq!(resp.header.id, req.header.id);
+        assert!(resp.header.is_flag_set(HeaderFlag::RecursionAvailable));
+        assert!(resp.header.is_flag_set(HeaderFlag::RecursionDesired));
+        assert!(!resp.header.is_flag_set(HeaderFlag::AuthAnswer));
+        let answers = resp.section(SectionType::Answer).unwrap();
+        assert_eq!(answers.len(), 2);
+        assert_eq!(answers[1], rrset("web.example.com. 300 IN A 3.3.3.3"));
+        let authority = &resp.section(SectionType::Authority).unwrap()[0];
+        assert_eq!(authority.typ, RRType::NS);
+        assert_eq!(authority.ttl.0, 3600);
+        assert_eq!(resp.get_edns().unwrap().udp_size, 4096);
+        assert_eq!(resp.section_rrset_count(SectionType::Additional), 2);
+
+        let resp = cache
+            .synthesize(&Request::new(name("none.example.com."), RRType::MX), None)
+            .unwrap();
+        assert_eq!(resp.header.rcode, Rcode::NXDomain);
+        assert_eq!(resp.section(SectionType::Authority).unwrap()[0].ttl.0, 300);
+        assert!(resp.get_edns().is_none());
+
+        for qname in ["loop.example.com.", "ftp.example.com."] {
+            assert!(cache
+                .synthesize(&Request::new(name(qname), RRType::A), None)
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn test_eviction_and_stale() {
+        let clock = TestClock::default();
+        let one = answer_size(
+            &mut [0; 512],
+            &CacheAnswer::RRset(rrset("a.example.com. 300 IN A 1.1.1.1")),
+        );
+        let mut cache = Cache::with_clock(clock.clone())
+            .max_size(one * 2)
+            .max_stale(600);
+        cache.insert_rrset(rrset("a.example.com. 300 IN A 1.1.1.1"), Trust::Answer);
+        cache.insert_rrset(rrset("b.example.com. 300 IN A 1.1.1.1"), Trust::Answer);
+        assert!(get_rrset(&mut cache, "a.example.com.", RRType::A).is_some());
+        cache.insert_rrset(rrset("c.example.com. 300 IN A 1.1.1.1"), Trust::Answer);
+        assert_eq!(cache.len(), 2);
+        assert!(get_rrset(&mut cache, "b.example.com.", RRType::A).is_none());
+        assert!(get_rrset(&mut cache, "a.example.com.", RRType::A).is_some());
+
+        clock.advance(400);
+        assert!(get_rrset(&mut cache, "a.example.com.", RRType::A).is_none());
+        match cache.get_stale(&name("a.example.com."), RRType::A, RRClass::IN) {
+            Some(CacheAnswer::RRset(rrset)) => assert_eq!(rrset.ttl.0, STALE_TTL),
+            _ => panic!("stale data should be served"),
+        }
+        clock.advance(600);
+        assert!(cache
+            .get_stale(&name("a.example.com."), RRType::A, RRClass::IN)
+            .is_none());
+        assert_eq!(cache.len(), 1);
+    }
+}
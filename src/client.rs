@@ -0,0 +1,311 @@
+use crate::edns::{Edns, MIN_UDP_SIZE};
+use crate::error::Error;
+use crate::header_flag::HeaderFlag;
+use crate::message::Message;
+use crate::message_builder::MessageBuilder;
+use crate::message_render::MessageRender;
+use crate::rcode::Rcode;
+use crate::request::Request;
+use crate::response::Response;
+use crate::util::tcp::{read_message, write_message};
+use crate::util::udp::bind_for;
+use anyhow::{bail, Result};
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+const DEFAULT_ATTEMPTS: usize = 2;
+const DEFAULT_UDP_SIZE: u16 = 1232;
+
+//blocking stub client, query is sent over udp to servers in turn and
+//resent over tcp when the response is truncated
+#[derive(Debug, Clone)]
+pub struct Client {
+    servers: Vec<SocketAddr>,
+    timeout: Duration,
+    attempts: usize,
+    udp_size: u16,
+    dnssec_ok: bool,
+}
+
+impl Client {
+    pub fn new(servers: Vec<SocketAddr>) -> Self {
+        Client {
+            servers,
+            timeout: DEFAULT_TIMEOUT,
+            attempts: DEFAULT_ATTEMPTS,
+            udp_size: DEFAULT_UDP_SIZE,
+            dnssec_ok: false,
+        }
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    //rounds over all the servers
+    pub fn attempts(mut self, attempts: usize) -> Self {
+        self.attempts = attempts;
+        self
+    }
+
+    pub fn udp_size(mut self, udp_size: u16) -> Self {
+        self.udp_size = udp_size;
+        self
+    }
+
+    pub fn dnssec_ok(mut self, dnssec_ok: bool) -> Self {
+        self.dnssec_ok = dnssec_ok;
+        self
+    }
+
+    //each attempt uses a new id so late response of the former one is
+    //ignored, the response always carries the original id
+    pub fn query(&self, req: &Request) -> Result<Response> {
+        let mut req = req.clone();
+        let id = req.header.id;
+        let mut last_err = anyhow::Error::new(Error::NoServer);
+        for _ in 0..self.attempts {
+            for server in &self.servers {
+                match self.query_server(&req, *server) {
+                    Ok(mut resp) => {
+                        resp.header.id = id;
+                        return Ok(resp);
+                    }
+                    Err(e) => last_err = e,
+                }
+                let last_id = req.header.id;
+                while req.header.id == last_id {
+                    req.header.id = rand::random::<u16>();
+                }
+            }
+        }
+        Err(last_err)
+    }
+
+    pub fn query_server(&self, req: &Request, server: SocketAddr) -> Result<Response> {
+        let use_edns = self.udp_size > MIN_UDP_SIZE;
+        let mut resp = self.query_udp(req, server, use_edns)?;
+        //server doesn't support edns, rfc6891 7
+        if use_edns
+            && resp.get_edns().is_none()
+            && matches!(resp.header.rcode, Rcode::FormErr | Rcode::NotImp)
+        {
+            resp = self.query_udp(req, server, false)?;
+        }
+        if resp.header.is_flag_set(HeaderFlag::Truncation) {
+            resp = self.query_tcp(req, server, use_edns)?;
+        }
+        Ok(resp)
+    }
+
+    fn render_query(&self, req: &Request, use_edns: bool) -> Result<Vec<u8>> {
+        let udp_size = if use_edns { Some(self.udp_size) } else { None };
+        render_query(req, udp_size, self.dnssec_ok)
+    }
+
+    fn query_udp(&self, req: &Request, server: SocketAddr, use_edns: bool) -> Result<Response> {
+        let socket = bind_for(server)?;
+        socket.send_to(&self.render_query(req, use_edns)?, server)?;
+
+        let deadline = Instant::now() + self.timeout;
+        let mut buf = vec![0; self.udp_size.max(MIN_UDP_SIZE) as usize];
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                bail!(Error::Timeout);
+            }
+            socket.set_read_timeout(Some(deadline - now))?;
+            let (len, from) = match socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    bail!(Error::Timeout);
+                }
+                Err(e) => return Err(e.into()),
+            };
+            //ignore packets which aren't the answer of the query
+            if from != server {
+                continue;
+            }
+            if let Ok(resp) = Response::from_wire(&buf[..len]) {
+                if is_response_of(&resp, req) {
+                    return Ok(resp);
+                }
+            }
+        }
+    }
+
+    fn query_tcp(&self, req: &Request, server: SocketAddr, use_edns: bool) -> Result<Response> {
+        let mut stream = TcpStream::connect_timeout(&server, self.timeout).map_err(timeout_err)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        write_message(&mut stream, &self.render_query(req, use_edns)?).map_err(timeout_err)?;
+        let raw = read_message(&mut stream).map_err(timeout_err)?;
+        let resp = Response::from_wire(&raw)?;
+        if !is_response_of(&resp, req) {
+            bail!("response from {} doesn't match query", server);
+        }
+        Ok(resp)
+    }
+}
+
+//query with edns when udp size is specified
+pub(crate) fn render_query(
+    req: &Request,
+    udp_size: Option<u16>,
+    dnssec_ok: bool,
+) -> Result<Vec<u8>> {
+    let mut query = Message::from(req.clone());
+    if let Some(udp_size) = udp_size {
+        MessageBuilder::new(&mut query)
+            .edns(Edns {
+                version: 0,
+                extened_rcode: 0,
+                udp_size,
+                dnssec_aware: dnssec_ok,
+                options: None,
+            })
+            .done();
+    }
+    let mut buf = [0; 512];
+    let mut render = MessageRender::new(&mut buf);
+    let len = query.to_wire(&mut render)?;
+    Ok(buf[..len].to_vec())
+}
+
+pub(crate) fn is_response_of(resp: &Response, req: &Request) -> bool {
+    resp.header.id == req.header.id
+        && resp.header.is_flag_set(HeaderFlag::QueryRespone)
+        && resp.question == req.question
+}
+
+fn timeout_err<E: Into<anyhow::Error>>(e: E) -> anyhow::Error {
+    let e = e.into();
+    match e.downcast_ref::<std::io::Error>().map(|e| e.kind()) {
+        Some(ErrorKind::WouldBlock) | Some(ErrorKind::TimedOut) => Error::Timeout.into(),
+        _ => e,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::name::Name;
+    use crate::response::SectionType;
+    use crate::response_builder::ResponseBuilder;
+    use crate::rr_type::RRType;
+    use crate::util::fixture::big_rrset;
+    use std::net::{TcpListener, UdpSocket};
+    use std::thread;
+
+    fn answer(raw: &[u8], truncated: bool) -> Vec<u8> {
+        let query = Message::from_wire(raw).unwrap();
+        let edns = query
+            .section(SectionType::Additional)
+            .and_then(|rrsets| rrsets.last())
+            .map(|rrset| Edns::from_rrset(rrset).unwrap());
+        let req = Request::from_wire(raw).unwrap();
+        let mut resp = Response::with_question(req.question.name.clone(), req.question.typ);
+        resp.header.id = req.header.id;
+        let mut builder = ResponseBuilder::new(&mut resp);
+        builder.set_flag(HeaderFlag::QueryRespone);
+        if truncated {
+            builder.set_flag(HeaderFlag::Truncation);
+        } else {
+            builder.add_rrset(SectionType::Answer, big_rrset("www.example.com.", 50));
+        }
+        if let Some(edns) = edns {
+            builder.add_rrset(SectionType::Additional, edns.to_rrset());
+        }
+        builder.done();
+        let mut buf = [0; 2048];
+        let mut render = MessageRender::new(&mut buf);
+        let len = resp.to_wire(&mut render).unwrap();
+        buf[..len].to_vec()
+    }
+
+    #[test]
+    fn test_query() {
+        //the first server never answers
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = udp.local_addr().unwrap();
+        let tcp = TcpListener::bind(addr).unwrap();
+        let server = thread::spawn(move || {
+            let mut buf = [0; 512];
+            let (len, from) = udp.recv_from(&mut buf).unwrap();
+            let query = Message::from_wire(&buf[..len]).unwrap();
+            //answer with another id which should be ignored
+            let mut other = buf[..len].to_vec();
+            other[0] = other[0].wrapping_add(1);
+            udp.send_to(&answer(&other, true), from).unwrap();
+            udp.send_to(&answer(&buf[..len], true), from).unwrap();
+
+            let (mut stream, _) = tcp.accept().unwrap();
+            let raw = read_message(&mut stream).unwrap();
+            write_message(&mut stream, &answer(&raw, false)).unwrap();
+            query
+        });
+
+        let client = Client::new(vec![silent.local_addr().unwrap(), addr])
+            .timeout(Duration::from_millis(200))
+            .udp_size(4096);
+        let req = Request::new(Name::new("www.example.com.").unwrap(), RRType::A);
+        let resp = client.query(&req).unwrap();
+        assert_eq!(resp.header.id, req.header.id);
+        assert!(!resp.header.is_flag_set(HeaderFlag::Truncation));
+        assert_eq!(
+            resp.section(SectionType::Answer).unwrap()[0].rdatas.len(),
+            50
+        );
+        assert_eq!(resp.get_edns().unwrap().udp_size, 4096);
+        let query = server.join().unwrap();
+        assert!(query.section(SectionType::Additional).is_some());
+        //the query to the second server is a new attempt
+        assert_ne!(query.header.id, req.header.id);
+
+        let client = Client::new(vec![silent.local_addr().unwrap()])
+            .timeout(Duration::from_millis(50))
+            .attempts(1);
+        let err = client.query(&req).unwrap_err();
+        assert_eq!(err.downcast_ref::<Error>(), Some(&Error::Timeout));
+        let err = Client::new(Vec::new()).query(&req).unwrap_err();
+        assert_eq!(err.downcast_ref::<Error>(), Some(&Error::NoServer));
+    }
+
+    #[test]
+    fn test_edns_fallback() {
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = udp.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut buf = [0; 512];
+            for _ in 0..2 {
+                let (len, from) = udp.recv_from(&mut buf).unwrap();
+                let query = Message::from_wire(&buf[..len]).unwrap();
+                let resp = if query.section(SectionType::Additional).is_some() {
+                    let req = Request::from_wire(&buf[..len]).unwrap();
+                    let mut resp = Message::from(req);
+                    MessageBuilder::new(&mut resp)
+                        .make_response()
+                        .rcode(Rcode::FormErr)
+                        .done();
+                    let mut buf = [0; 512];
+                    let mut render = MessageRender::new(&mut buf);
+                    let len = resp.to_wire(&mut render).unwrap();
+                    buf[..len].to_vec()
+                } else {
+                    answer(&buf[..len], false)
+                };
+                udp.send_to(&resp, from).unwrap();
+            }
+        });
+
+        let req = Request::new(Name::new("www.example.com.").unwrap(), RRType::A);
+        let resp = Client::new(vec![addr]).query(&req).unwrap();
+        assert_eq!(resp.header.rcode, Rcode::NoError);
+        assert!(resp.get_edns().is_none());
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::bail;
+use std::fmt;
+use std::str::FromStr;
+
+//algorithms which could be verified, rsa/sha1 and older ones are deprecated
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DnssecAlgorithm {
+    RsaSha256,
+    RsaSha512,
+    EcdsaP256Sha256,
+    EcdsaP384Sha384,
+    Ed25519,
+}
+
+const ALL_ALGORITHMS: [DnssecAlgorithm; 5] = [
+    DnssecAlgorithm::RsaSha256,
+    DnssecAlgorithm::RsaSha512,
+    DnssecAlgorithm::EcdsaP256Sha256,
+    DnssecAlgorithm::EcdsaP384Sha384,
+    DnssecAlgorithm::Ed25519,
+];
+
+impl DnssecAlgorithm {
+    pub fn new(value: u8) -> Option<Self> {
+        match value {
+            8 => Some(DnssecAlgorithm::RsaSha256),
+            10 => Some(DnssecAlgorithm::RsaSha512),
+            13 => Some(DnssecAlgorithm::EcdsaP256Sha256),
+            14 => Some(DnssecAlgorithm::EcdsaP384Sha384),
+            15 => Some(DnssecAlgorithm::Ed25519),
+            _ => None,
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            DnssecAlgorithm::RsaSha256 => 8,
+            DnssecAlgorithm::RsaSha512 => 10,
+            DnssecAlgorithm::EcdsaP256Sha256 => 13,
+            DnssecAlgorithm::EcdsaP384Sha384 => 14,
+            DnssecAlgorithm::Ed25519 => 15,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            DnssecAlgorithm::RsaSha256 => "RSASHA256",
+            DnssecAlgorithm::RsaSha512 => "RSASHA512",
+            DnssecAlgorithm::EcdsaP256Sha256 => "ECDSAP256SHA256",
+            DnssecAlgorithm::EcdsaP384Sha384 => "ECDSAP384SHA384",
+            DnssecAlgorithm::Ed25519 => "ED25519",
+        }
+    }
+}
+
+impl FromStr for DnssecAlgorithm {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        let alg = match s.parse::<u8>() {
+            Ok(value) => DnssecAlgorithm::new(value),
+            Err(_) => ALL_ALGORITHMS
+                .iter()
+                .find(|alg| alg.to_str().eq_ignore_ascii_case(s))
+                .copied(),
+        };
+        match alg {
+            Some(alg) => Ok(alg),
+            None => bail!("dnssec algorithm {} isn't supported", s),
+        }
+    }
+}
+
+impl fmt::Display for DnssecAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.to_str())
+    }
+}
@@ -0,0 +1,115 @@
+use crate::message_render::MessageRender;
+use crate::name::Name;
+use crate::rdatas::RRSig;
+use crate::rr_type::RRType;
+use crate::rrset::RRset;
+use anyhow::{bail, Result};
+
+//types whose names in rdata are lowercased in canonical form, rfc4034 6.2,
+//nsec and rrsig are excluded by rfc6840 5.1
+fn has_lowercase_name(typ: RRType) -> bool {
+    matches!(
+        typ,
+        RRType::NS
+            | RRType::CNAME
+            | RRType::SOA
+            | RRType::PTR
+            | RRType::MX
+            | RRType::RP
+            | RRType::SRV
+            | RRType::NAPTR
+            | RRType::DNAME
+    )
+}
+
+//rdatas in canonical form and canonical order, duplicates are removed
+pub(crate) fn canonical_rdatas(rrset: &RRset) -> Result<Vec<Vec<u8>>> {
+    let mut buf = vec![0; u16::MAX as usize];
+    let mut render = MessageRender::new(&mut buf);
+    render.set_compress(false);
+    render.set_lowercase(has_lowercase_name(rrset.typ));
+    let mut rdatas = Vec::with_capacity(rrset.rdatas.len());
+    for rdata in &rrset.rdatas {
+        render.reset();
+        rdata.to_wire(&mut render)?;
+        rdatas.push(render.data().to_vec());
+    }
+    rdatas.sort();
+    rdatas.dedup();
+    Ok(rdatas)
+}
+
+//owner name used in signature, wildcard expanded name is restored by the
+//labels field of rrsig
+pub(crate) fn signed_owner(name: &Name, labels: u8) -> Result<Name> {
+    let label_count = name.label_count() - 1;
+    let labels = labels as usize;
+    let mut owner = if labels == label_count || (name.is_wildcard() && labels + 1 == label_count) {
+        name.clone()
+    } else if labels < label_count {
+        Name::new("*")?.concat(&name.parent(label_count - labels)?)?
+    } else {
+        bail!("rrsig labels {} exceeds owner {}", labels, name);
+    };
+    owner.as_lowercase();
+    Ok(owner)
+}
+
+//rfc4034 3.1.8.1, rrsig rdata without signature followed by all the rrs
+pub(crate) fn signed_data(rrset: &RRset, rrsig: &RRSig) -> Result<Vec<u8>> {
+    let mut signer = rrsig.signer.clone();
+    signer.as_lowercase();
+    let mut data = Vec::with_capacity(512);
+    data.extend_from_slice(&rrsig.covered.as_u16().to_be_bytes());
+    data.push(rrsig.algorithm);
+    data.push(rrsig.labels);
+    data.extend_from_slice(&rrsig.original_ttl.to_be_bytes());
+    data.extend_from_slice(&rrsig.sig_expire.to_be_bytes());
+    data.extend_from_slice(&rrsig.incpetion.to_be_bytes());
+    data.extend_from_slice(&rrsig.tag.to_be_bytes());
+    data.extend_from_slice(signer.raw_data());
+
+    let owner = signed_owner(&rrset.name, rrsig.labels)?;
+    for rdata in canonical_rdatas(rrset)? {
+        data.extend_from_slice(owner.raw_data());
+        data.extend_from_slice(&rrset.typ.as_u16().to_be_bytes());
+        data.extend_from_slice(&rrset.class.to_u16().to_be_bytes());
+        data.extend_from_slice(&rrsig.original_ttl.to_be_bytes());
+        data.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        data.extend_from_slice(&rdata);
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_canonical_rdatas() {
+        let rrset = RRset::from_strs(&[
+            "Example.com. 300 IN MX 10 Mail.EXAMPLE.com.",
+            "example.com. 300 IN MX 5 mail.example.com.",
+            "example.com. 300 IN MX 10 mail.example.com.",
+        ])
+        .unwrap();
+        let rdatas = canonical_rdatas(&rrset).unwrap();
+        assert_eq!(rdatas.len(), 2);
+        assert_eq!(rdatas[0][0..2], [0, 5]);
+        assert_eq!(rdatas[1][3..7], *b"mail");
+
+        let wildcard = Name::from_str("*.example.com.").unwrap();
+        for (name, labels) in [
+            ("a.b.Example.com.", 2),
+            ("*.example.com.", 2),
+            ("A.example.com.", 2),
+        ] {
+            assert_eq!(
+                signed_owner(&Name::from_str(name).unwrap(), labels).unwrap(),
+                wildcard
+            );
+        }
+        assert!(signed_owner(&Name::from_str("example.com.").unwrap(), 3).is_err());
+    }
+}
@@ -0,0 +1,479 @@
+use crate::dnssec::nsec3::{nsec3_hash, NSEC3_FLAG_OPT_OUT, NSEC3_HASH_SHA1};
+use crate::error::Error;
+use crate::name::Name;
+use crate::rdata::RData;
+use crate::rdatafield::TypeBitmap;
+use crate::rdatas::NSEC3;
+use crate::rr_type::RRType;
+use crate::rrset::RRset;
+use crate::util::base32::from_base32hex;
+use anyhow::{bail, Result};
+
+//what nsec or nsec3 records in authority section prove
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Denial {
+    NXDomain,
+    NoData,
+    //qname doesn't exist but the wildcard at closest encloser isn't denied,
+    //answer should be synthesized from the wildcard
+    WildcardExpansion,
+    //qname doesn't exist and the wildcard has no data of qtype
+    WildcardNoData,
+    //next closer name is covered by an opt-out nsec3, so qname could be an
+    //unsigned delegation and the response is insecure
+    OptOut,
+}
+
+//decide what the nsec or nsec3 rrsets prove for qname and qtype, caller
+//should check the result matches rcode and answer of the response
+pub fn prove_denial(qname: &Name, qtype: RRType, authority: &[RRset]) -> Result<Denial> {
+    let nsecs = NsecSet::new(authority);
+    if !nsecs.is_empty() {
+        return nsecs.prove(qname, qtype);
+    }
+    let nsec3s = Nsec3Set::new(authority)?;
+    if !nsec3s.is_empty() {
+        return nsec3s.prove(qname, qtype);
+    }
+    bail!(Error::NoDenialProof)
+}
+
+//answer synthesized from wildcard should prove qname doesn't exist, labels
+//is the labels field of the rrsig covering the answer
+pub fn prove_wildcard_answer(qname: &Name, labels: u8, authority: &[RRset]) -> Result<()> {
+    let label_count = qname.label_count() - 1;
+    if labels as usize >= label_count {
+        bail!(Error::NoDenialProof);
+    }
+    let nsecs = NsecSet::new(authority);
+    let proved = if !nsecs.is_empty() {
+        nsecs.find_cover(qname).is_some()
+    } else {
+        let next_closer = ancestor(qname, labels as usize + 2);
+        Nsec3Set::new(authority)?.find_cover(&next_closer).is_some()
+    };
+    if !proved {
+        bail!(Error::NoDenialProof);
+    }
+    Ok(())
+}
+
+//types of name listed by the matching nsec or nsec3
+pub(crate) fn matching_types(name: &Name, authority: &[RRset]) -> Option<TypeBitmap> {
+    let nsecs = NsecSet::new(authority);
+    if !nsecs.is_empty() {
+        return nsecs.find_match(name).cloned();
+    }
+    Nsec3Set::new(authority)
+        .ok()?
+        .find_match(name)
+        .map(|nsec3| nsec3.types.clone())
+}
+
+//nsec at the parent side of a zone cut or owns dname can't prove anything
+//below it, rfc6840 4.1
+fn is_cut(types: &TypeBitmap) -> bool {
+    (types.has_type(RRType::NS) && !types.has_type(RRType::SOA)) || types.has_type(RRType::DNAME)
+}
+
+fn nodata(qtype: RRType, types: &TypeBitmap) -> Result<Denial> {
+    if types.has_type(qtype) || types.has_type(RRType::CNAME) {
+        bail!(Error::NoDenialProof);
+    }
+    //referral from parent side can't deny other types than ds
+    if qtype != RRType::DS && types.has_type(RRType::NS) && !types.has_type(RRType::SOA) {
+        bail!(Error::NoDenialProof);
+    }
+    Ok(Denial::NoData)
+}
+
+//label_count includes the root label
+fn ancestor(name: &Name, label_count: usize) -> Name {
+    name.parent(name.label_count() - label_count)
+        .expect("ancestor has less labels")
+}
+
+struct NsecSet<'a>(Vec<(&'a Name, &'a Name, &'a TypeBitmap)>);
+
+impl<'a> NsecSet<'a> {
+    fn new(authority: &'a [RRset]) -> Self {
+        let mut nsecs = Vec::new();
+        for rrset in authority.iter().filter(|rrset| rrset.typ == RRType::NSEC) {
+            for rdata in &rrset.rdatas {
+                if let RData::NSEC(ref nsec) = rdata {
+                    nsecs.push((&rrset.name, &nsec.next_domain_name, &nsec.type_bit_map));
+                }
+            }
+        }
+        NsecSet(nsecs)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn find_match(&self, name: &Name) -> Option<&TypeBitmap> {
+        self.0
+            .iter()
+            .find(|(owner, _, _)| *owner == name)
+            .map(|(_, _, types)| *types)
+    }
+
+    //owner < name < next in canonical order, the last nsec points back to
+    //zone apex
+    fn find_cover(&self, name: &Name) -> Option<(&'a Name, &'a Name)> {
+        self.0
+            .iter()
+            .find(|(owner, next, types)| {
+                let covered = if owner < next {
+                    *owner < name && name < *next
+                } else {
+                    *owner < name && name.is_subdomain(next)
+                };
+                covered && !(is_cut(types) && name.is_subdomain(owner))
+            })
+            .map(|(owner, next, _)| (*owner, *next))
+    }
+
+    fn prove(&self, qname: &Name, qtype: RRType) -> Result<Denial> {
+        if let Some(types) = self.find_match(qname) {
+            return nodata(qtype, types);
+        }
+        let (owner, next) = match self.find_cover(qname) {
+            Some(cover) => cover,
+            None => bail!(Error::NoDenialProof),
+        };
+        //qname is an empty non-terminal
+        if next.is_subdomain(qname) {
+            return Ok(Denial::NoData);
+        }
+
+        //closest encloser is the longest common ancestor of qname with
+        //either end of the covering nsec
+        let common = qname
+            .get_relation(owner)
+            .common_label_count
+            .max(qname.get_relation(next).common_label_count);
+        let wildcard = Name::new("*")?.concat(&ancestor(qname, common as usize))?;
+        if let Some(types) = self.find_match(&wildcard) {
+            if types.has_type(qtype) || types.has_type(RRType::CNAME) {
+                Ok(Denial::WildcardExpansion)
+            } else {
+                Ok(Denial::WildcardNoData)
+            }
+        } else if self.find_cover(&wildcard).is_some() {
+            Ok(Denial::NXDomain)
+        } else {
+            bail!(Error::NoDenialProof)
+        }
+    }
+}
+
+struct Nsec3Set<'a> {
+    zone: Option<Name>,
+    salt: &'a [u8],
+    iterations: u16,
+    records: Vec<(Vec<u8>, &'a NSEC3)>,
+}
+
+impl<'a> Nsec3Set<'a> {
+    //nsec3 with unknown hash algorithm is ignored, rfc5155 8.1
+    fn new(authority: &'a [RRset]) -> Result<Self> {
+        let mut set = Nsec3Set {
+            zone: None,
+            salt: &[],
+            iterations: 0,
+            records: Vec::new(),
+        };
+        for rrset in authority.iter().filter(|rrset| rrset.typ == RRType::NSEC3) {
+            let nsec3 = match rrset.rdatas.first() {
+                Some(RData::NSEC3(ref nsec3)) if nsec3.algorithm == NSEC3_HASH_SHA1 => nsec3,
+                _ => continue,
+            };
+            let label = rrset.name.to_string();
+            let hash = match label.split('.').next().and_then(from_base32hex) {
+                Some(hash) if rrset.name.label_count() > 1 => hash,
+                _ => bail!(Error::NoDenialProof),
+            };
+            let zone = rrset.name.parent(1)?;
+            match set.zone {
+                Some(ref current) => {
+                    if !zone.eq(current)
+                        || set.salt != nsec3.salt.as_slice()
+                        || set.iterations != nsec3.iterations
+                    {
+                        bail!(Error::NoDenialProof);
+                    }
+                }
+                None => {
+                    set.zone = Some(zone);
+                    set.salt = &nsec3.salt;
+                    set.iterations = nsec3.iterations;
+                }
+            }
+            set.records.push((hash, nsec3.as_ref()));
+        }
+        Ok(set)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    fn hash(&self, name: &Name) -> Option<Vec<u8>> {
+        match self.zone {
+            Some(ref zone) if name.is_subdomain(zone) => {
+                Some(nsec3_hash(name, self.salt, self.iterations))
+            }
+            _ => None,
+        }
+    }
+
+    fn find_match(&self, name: &Name) -> Option<&'a NSEC3> {
+        let hash = self.hash(name)?;
+        self.records
+            .iter()
+            .find(|(owner, _)| *owner == hash)
+            .map(|(_, nsec3)| *nsec3)
+    }
+
+    fn find_cover(&self, name: &Name) -> Option<&'a NSEC3> {
+        let hash = self.hash(name)?;
+        self.records
+            .iter()
+            .find(|(owner, nsec3)| {
+                let next = &nsec3.next_hash;
+                if owner < next {
+                    *owner < hash && hash < *next
+                } else {
+                    *owner < hash || hash < *next
+                }
+            })
+            .map(|(_, nsec3)| *nsec3)
+    }
+
+    fn prove(&self, qname: &Name, qtype: RRType) -> Result<Denial> {
+        if let Some(nsec3) = self.find_match(qname) {
+            return nodata(qtype, &nsec3.types);
+        }
+
+        //closest provable encloser, rfc5155 8.3
+        let zone = match self.zone {
+            Some(ref zone) if qname.is_subdomain(zone) => zone,
+            _ => bail!(Error::NoDenialProof),
+        };
+        let mut label_count = qname.label_count() - 1;
+        let closest_encloser = loop {
+            if label_count < zone.label_count() {
+                bail!(Error::NoDenialProof);
+            }
+            let candidate = ancestor(qname, label_count);
+            if let Some(nsec3) = self.find_match(&candidate) {
+                if is_cut(&nsec3.types) && !candidate.eq(zone) {
+                    bail!(Error::NoDenialProof);
+                }
+                break candidate;
+            }
+            label_count -= 1;
+        };
+        let next_closer = ancestor(qname, closest_encloser.label_count() + 1);
+        let cover = match self.find_cover(&next_closer) {
+            Some(cover) => cover,
+            None => bail!(Error::NoDenialProof),
+        };
+        if cover.flags & NSEC3_FLAG_OPT_OUT != 0 {
+            return Ok(Denial::OptOut);
+        }
+
+        let wildcard = Name::new("*")?.concat(&closest_encloser)?;
+        if let Some(nsec3) = self.find_match(&wildcard) {
+            if nsec3.types.has_type(qtype) || nsec3.types.has_type(RRType::CNAME) {
+                Ok(Denial::WildcardExpansion)
+            } else {
+                Ok(Denial::WildcardNoData)
+            }
+        } else if self.find_cover(&wildcard).is_some() {
+            Ok(Denial::NXDomain)
+        } else {
+            bail!(Error::NoDenialProof)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dnssec::{Nsec3Config, SigningKey, ZoneSigner};
+    use crate::rdatas::DNSKey;
+    use crate::util::fixture::build_zone;
+
+    const ZONE: &str = r#"
+example.com. 3600 IN SOA ns1.example.com. hostmaster.example.com. 1 3600 900 604800 300
+example.com. 3600 IN NS ns1.example.com.
+ns1.example.com. 3600 IN A 1.1.1.1
+www.example.com. 300 IN A 2.2.2.2
+*.example.com. 3600 IN TXT "wild card"
+a.b.c.example.com. 3600 IN A 3.3.3.3
+sub.example.com. 3600 IN NS ns.sub.example.com.
+ns.sub.example.com. 3600 IN A 4.4.4.4
+"#;
+
+    //all the nsec or nsec3 records of the signed zone
+    fn denial_records(nsec3: Option<Nsec3Config>) -> Vec<RRset> {
+        let mut zone = build_zone("example.com.", ZONE);
+        let origin = zone.origin().clone();
+        let dnskey = DNSKey {
+            flags: 257,
+            protocol: 3,
+            algorithm: 15,
+            public_key: base64::decode("l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=").unwrap(),
+        };
+        let key = SigningKey::new(
+            origin,
+            dnskey,
+            "Private-key-format: v1.2\nAlgorithm: 15\nPrivateKey: ODIyNjAzODQ2MjgwODAxMjI2NDUxOTAyMDQxNDIyNjI=",
+        )
+        .unwrap();
+        let mut signer = ZoneSigner::new(vec![key]);
+        if let Some(config) = nsec3 {
+            signer = signer.nsec3(config);
+        }
+        signer.sign(&mut zone, 1700000000).unwrap();
+        zone.rrsets()
+            .into_iter()
+            .filter(|rrset| rrset.typ == RRType::NSEC || rrset.typ == RRType::NSEC3)
+            .cloned()
+            .collect()
+    }
+
+    fn check(authority: &[RRset], cases: &[(&str, RRType, Option<Denial>)]) {
+        for (qname, qtype, expect) in cases {
+            let result = prove_denial(&Name::new(qname).unwrap(), *qtype, authority);
+            match expect {
+                Some(denial) => assert_eq!(result.unwrap(), *denial, "{} {}", qname, qtype),
+                None => assert_eq!(
+                    result.map_err(|e| e.downcast::<Error>().unwrap()),
+                    Err(Error::NoDenialProof),
+                    "{} {}",
+                    qname,
+                    qtype
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_nsec_denial() {
+        let authority = denial_records(None);
+        check(
+            &authority,
+            &[
+                ("www.example.com.", RRType::A, None),
+                ("www.example.com.", RRType::TXT, Some(Denial::NoData)),
+                ("b.c.example.com.", RRType::A, Some(Denial::NoData)),
+                ("x.b.c.example.com.", RRType::A, Some(Denial::NXDomain)),
+                (
+                    "zzz.example.com.",
+                    RRType::TXT,
+                    Some(Denial::WildcardExpansion),
+                ),
+                ("zzz.example.com.", RRType::A, Some(Denial::WildcardNoData)),
+                ("sub.example.com.", RRType::DS, Some(Denial::NoData)),
+                ("sub.example.com.", RRType::A, None),
+                ("ns.sub.example.com.", RRType::A, None),
+                ("www.example.net.", RRType::A, None),
+            ],
+        );
+        assert!(prove_denial(&Name::new("x.example.com.").unwrap(), RRType::A, &[]).is_err());
+
+        //wildcard which is neither matched nor covered isn't denied
+        let qname = Name::new("x.b.c.example.com.").unwrap();
+        let cover: Vec<RRset> = authority
+            .iter()
+            .filter(|rrset| rrset.name.eq(&Name::new("a.b.c.example.com.").unwrap()))
+            .cloned()
+            .collect();
+        check(&cover, &[("x.b.c.example.com.", RRType::A, None)]);
+        assert!(NsecSet::new(&cover).find_cover(&qname).is_some());
+
+        let name = Name::new("x.y.example.com.").unwrap();
+        prove_wildcard_answer(&name, 2, &authority).unwrap();
+        assert!(
+            prove_wildcard_answer(&Name::new("www.example.com.").unwrap(), 2, &authority).is_err()
+        );
+    }
+
+    #[test]
+    fn test_nsec3_denial() {
+        let config = Nsec3Config {
+            salt: vec![0xaa, 0xbb, 0xcc, 0xdd],
+            iterations: 12,
+            opt_out: false,
+        };
+        let authority = denial_records(Some(config.clone()));
+        check(
+            &authority,
+            &[
+                ("www.example.com.", RRType::A, None),
+                ("www.example.com.", RRType::TXT, Some(Denial::NoData)),
+                ("b.c.example.com.", RRType::A, Some(Denial::NoData)),
+                ("x.b.c.example.com.", RRType::A, Some(Denial::NXDomain)),
+                (
+                    "zzz.example.com.",
+                    RRType::TXT,
+                    Some(Denial::WildcardExpansion),
+                ),
+                ("zzz.example.com.", RRType::A, Some(Denial::WildcardNoData)),
+                ("sub.example.com.", RRType::DS, Some(Denial::NoData)),
+                ("ns.sub.example.com.", RRType::A, None),
+                ("www.example.net.", RRType::A, None),
+            ],
+        );
+        //drop the nsec3 covering wildcard, other proofs are still there
+        let wildcard = Name::new("*.b.c.example.com.").unwrap();
+        let nsec3s = Nsec3Set::new(&authority).unwrap();
+        let wildcard_cover = nsec3s.find_cover(&wildcard).unwrap().next_hash.clone();
+        let next_closer = Name::new("x.b.c.example.com.").unwrap();
+        assert_ne!(
+            nsec3s.find_cover(&next_closer).unwrap().next_hash,
+            wildcard_cover
+        );
+        let partial: Vec<RRset> = authority
+            .iter()
+            .filter(|rrset| match rrset.rdatas[0] {
+                RData::NSEC3(ref nsec3) => nsec3.next_hash != wildcard_cover,
+                _ => true,
+            })
+            .cloned()
+            .collect();
+        check(&partial, &[("x.b.c.example.com.", RRType::A, None)]);
+
+        prove_wildcard_answer(&Name::new("x.y.example.com.").unwrap(), 2, &authority).unwrap();
+        //www exists, so wildcard can't be expanded under it
+        assert!(
+            prove_wildcard_answer(&Name::new("x.www.example.com.").unwrap(), 2, &authority)
+                .is_err()
+        );
+
+        //nsec3 of other zone or parameters can't be mixed
+        let mut mixed = authority.clone();
+        mixed.extend(denial_records(Some(Nsec3Config {
+            iterations: 1,
+            ..config.clone()
+        })));
+        check(&mixed, &[("x.b.c.example.com.", RRType::A, None)]);
+
+        let authority = denial_records(Some(Nsec3Config {
+            opt_out: true,
+            ..config
+        }));
+        check(
+            &authority,
+            &[
+                ("sub.example.com.", RRType::DS, Some(Denial::OptOut)),
+                ("ns.sub.example.com.", RRType::A, Some(Denial::OptOut)),
+                ("www.example.com.", RRType::TXT, Some(Denial::NoData)),
+            ],
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::name::Name;
+use crate::rdatas::{DNSKey, DS};
+use ring::digest;
+
+//digest algorithms of ds, rfc4034 5.1.3, rfc4509 and rfc6605
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DigestType {
+    Sha1,
+    Sha256,
+    Sha384,
+}
+
+impl DigestType {
+    pub fn new(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(DigestType::Sha1),
+            2 => Some(DigestType::Sha256),
+            4 => Some(DigestType::Sha384),
+            _ => None,
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            DigestType::Sha1 => 1,
+            DigestType::Sha256 => 2,
+            DigestType::Sha384 => 4,
+        }
+    }
+
+    fn algorithm(self) -> &'static digest::Algorithm {
+        match self {
+            DigestType::Sha1 => &digest::SHA1_FOR_LEGACY_USE_ONLY,
+            DigestType::Sha256 => &digest::SHA256,
+            DigestType::Sha384 => &digest::SHA384,
+        }
+    }
+}
+
+impl DNSKey {
+    //rfc4034 appendix b
+    pub fn key_tag(&self) -> u16 {
+        let header = [
+            (self.flags >> 8) as u8,
+            self.flags as u8,
+            self.protocol,
+            self.algorithm,
+        ];
+        let mut ac = 0u32;
+        for (i, &b) in header.iter().chain(self.public_key.iter()).enumerate() {
+            if i & 1 == 0 {
+                ac += (b as u32) << 8;
+            } else {
+                ac += b as u32;
+            }
+        }
+        ac += (ac >> 16) & 0xffff;
+        (ac & 0xffff) as u16
+    }
+
+    //rfc4034 5.1.4, digest of the owner name in canonical form followed by
+    //dnskey rdata
+    pub fn digest(&self, owner: &Name, digest_type: DigestType) -> Vec<u8> {
+        let mut owner = owner.clone();
+        owner.as_lowercase();
+        let mut ctx = digest::Context::new(digest_type.algorithm());
+        ctx.update(owner.raw_data());
+        ctx.update(&self.flags.to_be_bytes());
+        ctx.update(&[self.protocol, self.algorithm]);
+        ctx.update(&self.public_key);
+        ctx.finish().as_ref().to_vec()
+    }
+
+    pub fn to_ds(&self, owner: &Name, digest_type: DigestType) -> DS {
+        DS {
+            key_tag: self.key_tag(),
+            algorithm: self.algorithm,
+            digest_type: digest_type.to_u8(),
+            digest: self.digest(owner, digest_type),
+        }
+    }
+}
+
+impl DS {
+    //owner is the name of the ds which is also the owner of dnskey, ds with
+    //unknown digest type never matches
+    pub fn matches(&self, owner: &Name, key: &DNSKey) -> bool {
+        match DigestType::new(self.digest_type) {
+            Some(digest_type) => {
+                self.key_tag == key.key_tag()
+                    && self.algorithm == key.algorithm
+                    && self.digest == key.digest(owner, digest_type)
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rdata::RData;
+    use crate::rrset::RRset;
+    use std::str::FromStr;
+
+    fn dnskey(rrset: &str) -> (Name, DNSKey) {
+        let mut rrset = RRset::from_str(rrset).unwrap();
+        match rrset.rdatas.remove(0) {
+            RData::DNSKey(key) => (rrset.name, *key),
+            _ => unreachable!(),
+        }
+    }
+
+    fn ds(rrset: &str) -> DS {
+        match RRset::from_str(rrset).unwrap().rdatas.remove(0) {
+            RData::DS(ds) => *ds,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_ds() {
+        //rfc4034 5.4 and rfc4509 2.3
+        let (owner, key) = dnskey("dskey.example.com. 86400 IN DNSKEY 256 3 5 AQOeiiR0GOMYkDshWoSKz9XzfwJr1AYtsmx3TGkJaNXVbfi/2pHm822aJ5iI9BMzNXxeYCmZDRD99WYwYqUSdjMmmAphXdvxegXd/M5+X7OrzKBaMbCVdFLUUh6DhweJBjEVv5f2wwjM9XzcnOf+EPbtG9DMBmADjFDc2w/rljwvFw==");
+        assert_eq!(key.key_tag(), 60485);
+        let sha1 =
+            ds("dskey.example.com. 86400 IN DS 60485 5 1 2bb183af5f22588179a53b0a98631fad1a292118");
+        assert_eq!(key.to_ds(&owner, DigestType::Sha1), sha1);
+        let sha256 = ds("dskey.example.com. 86400 IN DS 60485 5 2 d4b7d520e7bb5f0f67674a0cceb1e3e0614b93c4f9e99b8383f6a1e4469da50a");
+        assert_eq!(key.to_ds(&owner, DigestType::Sha256), sha256);
+        let upper = Name::new("DSKEY.Example.COM.").unwrap();
+        assert!(sha256.matches(&upper, &key));
+        assert!(!sha256.matches(&Name::new("example.com.").unwrap(), &key));
+
+        //rfc6605 6.2
+        let (owner, key) = dnskey("example.net. 3600 IN DNSKEY 257 3 14 xKYaNhWdGOfJ+nPrL8/arkwf2EY3MDJ+SErKivBVSum1w/egsXvSADtNJhyem5RCOpgQ6K8X1DRSEkrbYQ+OB+v8/uX45NBwY8rp65F6Glur8I/mlVNgF6W/qTI37m40");
+        let sha384 = ds("example.net. 3600 IN DS 10771 14 4 72d7b62976ce06438e9c0bf319013cf801f09ecc84b8d7e9495f27e305c6a9b0563a9b5f4d288405c3008a946df983d6");
+        assert_eq!(key.to_ds(&owner, DigestType::Sha384), sha384);
+        assert!(sha384.matches(&owner, &key));
+
+        let mut other = key.clone();
+        other.flags = 256;
+        assert!(!sha384.matches(&owner, &other));
+        let mut unknown = sha384;
+        unknown.digest_type = 3;
+        assert!(!unknown.matches(&owner, &key));
+    }
+}
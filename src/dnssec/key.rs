@@ -0,0 +1,329 @@
+use crate::dnssec::algorithm::DnssecAlgorithm;
+use crate::dnssec::canonical::signed_data;
+use crate::name::{root, Name};
+use crate::rdata::RData;
+use crate::rdatas::{DNSKey, RRSig};
+use crate::rr_type::RRType;
+use crate::rrset::RRset;
+use crate::zonefile::parse_zone;
+use anyhow::{anyhow, bail, ensure, Result};
+use ring::rand::SystemRandom;
+use ring::signature::{self, EcdsaKeyPair, Ed25519KeyPair, RsaKeyPair};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const SEP_FLAG: u16 = 0x0001;
+
+enum KeyPair {
+    Rsa(RsaKeyPair),
+    Ecdsa(EcdsaKeyPair),
+    Ed25519(Ed25519KeyPair),
+}
+
+//private key with its dnskey, loaded from bind style key files
+pub struct SigningKey {
+    pub name: Name,
+    pub dnskey: DNSKey,
+    pub algorithm: DnssecAlgorithm,
+    key_pair: KeyPair,
+    rng: SystemRandom,
+}
+
+impl SigningKey {
+    //private_key is the content of bind v1.2/v1.3 private key file
+    pub fn new(name: Name, dnskey: DNSKey, private_key: &str) -> Result<Self> {
+        let algorithm = DnssecAlgorithm::new(dnskey.algorithm)
+            .ok_or_else(|| anyhow!("dnskey algorithm {} isn't supported", dnskey.algorithm))?;
+        let fields = parse_private_key(private_key)?;
+        let field = |key: &str| -> Result<Vec<u8>> {
+            let value = fields
+                .get(key)
+                .ok_or_else(|| anyhow!("private key has no {}", key))?;
+            base64::decode(value).map_err(|_| anyhow!("{} isn't valid base64", key))
+        };
+
+        let alg = fields
+            .get("Algorithm")
+            .and_then(|s| s.split_whitespace().next())
+            .ok_or_else(|| anyhow!("private key has no algorithm"))?;
+        ensure!(
+            alg.parse::<u8>().ok() == Some(dnskey.algorithm),
+            "private key algorithm {} doesn't match dnskey",
+            alg
+        );
+
+        let key_pair = match algorithm {
+            DnssecAlgorithm::RsaSha256 | DnssecAlgorithm::RsaSha512 => {
+                let components = [
+                    "Modulus",
+                    "PublicExponent",
+                    "PrivateExponent",
+                    "Prime1",
+                    "Prime2",
+                    "Exponent1",
+                    "Exponent2",
+                    "Coefficient",
+                ]
+                .iter()
+                .map(|key| field(key))
+                .collect::<Result<Vec<_>>>()?;
+                ensure!(
+                    rsa_public_key(&components[1], &components[0]) == dnskey.public_key,
+                    "rsa private key doesn't match dnskey"
+                );
+                let key_pair = RsaKeyPair::from_der(&rsa_private_key_der(&components))
+                    .map_err(|e| anyhow!("invalid rsa private key: {}", e))?;
+                KeyPair::Rsa(key_pair)
+            }
+            DnssecAlgorithm::EcdsaP256Sha256 | DnssecAlgorithm::EcdsaP384Sha384 => {
+                let params = if algorithm == DnssecAlgorithm::EcdsaP256Sha256 {
+                    &signature::ECDSA_P256_SHA256_FIXED_SIGNING
+                } else {
+                    &signature::ECDSA_P384_SHA384_FIXED_SIGNING
+                };
+                let mut public_key = vec![4];
+                public_key.extend_from_slice(&dnskey.public_key);
+                let key_pair = EcdsaKeyPair::from_private_key_and_public_key(
+                    params,
+                    &field("PrivateKey")?,
+                    &public_key,
+                )
+                .map_err(|e| anyhow!("invalid ecdsa private key: {}", e))?;
+                KeyPair::Ecdsa(key_pair)
+            }
+            DnssecAlgorithm::Ed25519 => {
+                let key_pair = Ed25519KeyPair::from_seed_and_public_key(
+                    &field("PrivateKey")?,
+                    &dnskey.public_key,
+                )
+                .map_err(|e| anyhow!("invalid ed25519 private key: {}", e))?;
+                KeyPair::Ed25519(key_pair)
+            }
+        };
+
+        Ok(SigningKey {
+            name,
+            dnskey,
+            algorithm,
+            key_pair,
+            rng: SystemRandom::new(),
+        })
+    }
+
+    //key_file holds the dnskey record, private_file holds the private key
+    pub fn from_files<P: AsRef<Path>>(key_file: P, private_file: P) -> Result<Self> {
+        let content = fs::read_to_string(key_file)?;
+        let rrsets = parse_zone(&format!("$TTL 3600\n{}", content), &root())?;
+        let (name, dnskey) = match rrsets
+            .into_iter()
+            .find(|rrset| rrset.typ == RRType::DNSKEY)
+            .map(|mut rrset| (rrset.name, rrset.rdatas.remove(0)))
+        {
+            Some((name, RData::DNSKey(dnskey))) => (name, *dnskey),
+            _ => bail!("no dnskey in key file"),
+        };
+        SigningKey::new(name, dnskey, &fs::read_to_string(private_file)?)
+    }
+
+    pub fn key_tag(&self) -> u16 {
+        self.dnskey.key_tag()
+    }
+
+    pub fn is_ksk(&self) -> bool {
+        self.dnskey.flags & SEP_FLAG != 0
+    }
+
+    pub fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self.key_pair {
+            KeyPair::Rsa(ref key_pair) => {
+                let encoding = if self.algorithm == DnssecAlgorithm::RsaSha256 {
+                    &signature::RSA_PKCS1_SHA256
+                } else {
+                    &signature::RSA_PKCS1_SHA512
+                };
+                let mut sig = vec![0; key_pair.public_modulus_len()];
+                key_pair
+                    .sign(encoding, &self.rng, data, &mut sig)
+                    .map_err(|_| anyhow!("rsa sign failed"))?;
+                Ok(sig)
+            }
+            KeyPair::Ecdsa(ref key_pair) => key_pair
+                .sign(&self.rng, data)
+                .map(|sig| sig.as_ref().to_vec())
+                .map_err(|_| anyhow!("ecdsa sign failed")),
+            KeyPair::Ed25519(ref key_pair) => Ok(key_pair.sign(data).as_ref().to_vec()),
+        }
+    }
+
+    pub fn sign_rrset(&self, rrset: &RRset, inception: u32, expiration: u32) -> Result<RRSig> {
+        let mut labels = rrset.name.label_count() - 1;
+        if rrset.name.is_wildcard() {
+            labels -= 1;
+        }
+        let mut rrsig = RRSig {
+            covered: rrset.typ,
+            algorithm: self.algorithm.to_u8(),
+            labels: labels as u8,
+            original_ttl: rrset.ttl.0,
+            sig_expire: expiration,
+            incpetion: inception,
+            tag: self.key_tag(),
+            signer: self.name.clone(),
+            signature: Vec::new(),
+        };
+        rrsig.signature = self.sign(&signed_data(rrset, &rrsig)?)?;
+        Ok(rrsig)
+    }
+}
+
+fn parse_private_key(content: &str) -> Result<HashMap<&str, &str>> {
+    let mut fields = HashMap::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        match line.split_once(':') {
+            Some((key, value)) => fields.insert(key.trim(), value.trim()),
+            None => bail!("invalid line {} in private key", line),
+        };
+    }
+    match fields.get("Private-key-format") {
+        Some(&"v1.2") | Some(&"v1.3") => Ok(fields),
+        _ => bail!("unknown private key format"),
+    }
+}
+
+//rfc3110 2, the format of rsa public key in dnskey
+fn rsa_public_key(e: &[u8], n: &[u8]) -> Vec<u8> {
+    let mut key = Vec::with_capacity(e.len() + n.len() + 3);
+    if e.len() > 255 {
+        key.push(0);
+        key.extend_from_slice(&(e.len() as u16).to_be_bytes());
+    } else {
+        key.push(e.len() as u8);
+    }
+    key.extend_from_slice(e);
+    key.extend_from_slice(n);
+    key
+}
+
+//rfc8017 a.1.2, pkcs#1 RSAPrivateKey which ring accepts
+fn rsa_private_key_der(components: &[Vec<u8>]) -> Vec<u8> {
+    let mut body = Vec::new();
+    der_integer(&mut body, &[0]);
+    for component in components {
+        der_integer(&mut body, component);
+    }
+    let mut der = vec![0x30];
+    der_length(&mut der, body.len());
+    der.extend_from_slice(&body);
+    der
+}
+
+fn der_integer(out: &mut Vec<u8>, value: &[u8]) {
+    let zeros = value.iter().take_while(|&&b| b == 0).count();
+    let value = &value[zeros.min(value.len().saturating_sub(1))..];
+    let pad = !value.is_empty() && value[0] & 0x80 != 0;
+    out.push(0x02);
+    der_length(out, value.len() + pad as usize);
+    if pad {
+        out.push(0);
+    }
+    out.extend_from_slice(value);
+}
+
+fn der_length(out: &mut Vec<u8>, len: usize) {
+    if len < 0x80 {
+        out.push(len as u8);
+    } else if len <= 0xff {
+        out.extend_from_slice(&[0x81, len as u8]);
+    } else {
+        out.extend_from_slice(&[0x82, (len >> 8) as u8, len as u8]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dnssec::verifier::verify_rrsig;
+    use std::str::FromStr;
+
+    const ED25519_KEY: &str =
+        "example.com. 3600 IN DNSKEY 257 3 15 l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=";
+    const ED25519_PRIVATE: &str = "Private-key-format: v1.2
+Algorithm: 15 (ED25519)
+PrivateKey: ODIyNjAzODQ2MjgwODAxMjI2NDUxOTAyMDQxNDIyNjI=";
+
+    const RSA_KEY: &str = "example.com. IN DNSKEY 256 3 8 AwEAAbjHlMMDEuvDyM17neSckMlBOs/c6edKbjTIk8QJ0Iu2VahJge5IzLp6tyBjIZ4+sIdGGLG9IMF3bDDIAhilSEqGyriQZEWaPTdz9aQGd11U8qfg1tS3iOprUhgA/y6314gJ/JqgkbJYyouFQboecUFGMpNQEn5I5X04YJE0zJwGq3spkzCROj375l/M2VVlS58OovSdtXkCDGXCsTMMhkTrP6F3MOwz0Yp64hX9j/hE2TnNq492gvCZqceEDiVXx+kLoGE5dl8GiBG2036IOt00hWid2Ndq3ikvOplfbpqaZMEo9zoqX4VrQ7Eofxv21+wesaYWMseCwnjatmWjKBU=";
+    const RSA_PRIVATE: &str = "Private-key-format: v1.3
+Algorithm: 8 (RSASHA256)
+Modulus: uMeUwwMS68PIzXud5JyQyUE6z9zp50puNMiTxAnQi7ZVqEmB7kjMunq3IGMhnj6wh0YYsb0gwXdsMMgCGKVISobKuJBkRZo9N3P1pAZ3XVTyp+DW1LeI6mtSGAD/LrfXiAn8mqCRsljKi4VBuh5xQUYyk1ASfkjlfThgkTTMnAareymTMJE6PfvmX8zZVWVLnw6i9J21eQIMZcKxMwyGROs/oXcw7DPRinriFf2P+ETZOc2rj3aC8Jmpx4QOJVfH6QugYTl2XwaIEbbTfog63TSFaJ3Y12reKS86mV9umppkwSj3OipfhWtDsSh/G/bX7B6xphYyx4LCeNq2ZaMoFQ==
+PublicExponent: AQAB
+PrivateExponent: CHey6D3dkdrKuqnhyi5njyVzV7+wo1X8l2Qiq17kxOZB5iCaOOxyn1AM0MniKkGGruhGLOz1EZCSr2iW+gCm/61OIGeThMIE8KPae54FPcxtA0bxKVXQyydIX4u6Je6sTJJilkZp7tGVVI/ye2A2Qe7A1hHbCxj1lUeuWzEfeD3c/LpjolcPX6dpP00PnPD8/M/s953nyIzIIpyk7t0AKhXyl6yMdsu99WAcX2j8v7737CZZk0nUvDsh2eVhPdWDZH+59VvJ8x3hEWhRo61usnwBU9u8FL40SS3vEr7uq225AgkRszVxLR+w61xfiRhxVKQJvWxhpyZRLyKNsrU9GQ==
+Prime1: 7nHFodjMR+ZyTYmQTQIpCE12jZPbFbGn9KhVtx3toT8a1FOZfjiPDoO5o/sUMFeW2gc2SfkJsuroNbINKnqpozCcsPJpgwU0vl+XOCnwBi/1IvVX8c2wtIbq7IKPpr9ezmKzj/r6ZMIhIjrc3Ku1rT9+C2go1F6X1thW0bMOOik=
+Prime2: xmJUHvhIaaPIV8IzLdBhwPWrSBJJFDjCbuhtYMEvbdO/L27tbz8qYG9nUEJ5sbzKaQ6NxbvlfZFO0pemfBSn2+GH2cDLBeCkh80njS5FydWSFZiKFEKfX4mSITyt8n0dVQ1ovwrsLh1sAqSey8MBDOsI/pj0ScHu4IHUWpBHFA0=
+Exponent1: TDrjTBLbfEEYWeGY2IXQSG87b5fNeuoJIBaRELHovpxGH4t6jvfWBHXoa5xhn/Lgr2OO36dWinS/OCtsOiBtC1cHQSq+wLxxWEuHNNVzfwbl//8f1pZD3/MiN+qlslGMJb84WNeMJZpKrX7UDVh4BKM/0LXEF+EF+hG/79qmucE=
+Exponent2: dllIp+UkhekQZtJS59ztxEiK8wb9k9O8op+ESPlWyyVMZAbAHOLI7BjpwGa35MGk2wG0sCbE5KEgLeAMkO9dqH4mJlCujYYTgXRxSPc26Q7BBKMolKeeHkea5rslZokfS5MhTv88INKqkfUVSqe946//gKtM6QeJ82NghNqXnK0=
+Coefficient: aiyP7CgCz3Uzu2bg98VuDPCzY/xaS26ja2OBSqM8ZR1ADk51/PEkhvYlyChEwoIfiiJxive463bTLoyoh+Gu1CMx0eKRrGNgk6QdbAVwreHIP8h2omU5oIZhU8lza4+LsFJkCxrXu32IousU/77k/BlHblxOrMZa3jpSwiJr3QI=";
+
+    fn load_key(key: &str, private_key: &str) -> SigningKey {
+        let rrset = RRset::from_str(key).unwrap();
+        let dnskey = match rrset.rdatas[0] {
+            RData::DNSKey(ref dnskey) => *dnskey.clone(),
+            _ => unreachable!(),
+        };
+        SigningKey::new(rrset.name, dnskey, private_key).unwrap()
+    }
+
+    #[test]
+    fn test_sign_ed25519() {
+        //rfc8080 6.1, ed25519 signature is deterministic
+        let key = load_key(ED25519_KEY, ED25519_PRIVATE);
+        assert!(key.is_ksk());
+        assert_eq!(key.key_tag(), 3613);
+        let rrset = RRset::from_str("example.com. 3600 IN MX 10 mail.example.com.").unwrap();
+        let rrsig = key.sign_rrset(&rrset, 1438207200, 1440021600).unwrap();
+        assert_eq!(
+            base64::encode(&rrsig.signature),
+            "oL9krJun7xfBOIWcGHi7mag5/hdZrKWw15jPGrHpjQeRAvTdszaPD+QLs3fx8A4M3e23mRZ9VrbpMngwcrqNAg=="
+        );
+
+        let wildcard = RRset::from_str("*.example.com. 3600 IN A 1.1.1.1").unwrap();
+        let rrsig = key.sign_rrset(&wildcard, 1438207200, 1440021600).unwrap();
+        assert_eq!(rrsig.labels, 2);
+        let mut expanded = wildcard.clone();
+        expanded.name = Name::new("a.b.example.com.").unwrap();
+        verify_rrsig(&expanded, &rrsig, &key.dnskey, 1439000000).unwrap();
+    }
+
+    #[test]
+    fn test_load_rsa_key() {
+        let dir = std::env::temp_dir().join(format!("r53-key-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let key_file = dir.join("Kexample.com.+008+50123.key");
+        let private_file = dir.join("Kexample.com.+008+50123.private");
+        fs::write(&key_file, format!("; zone-signing key\n{}\n", RSA_KEY)).unwrap();
+        fs::write(&private_file, RSA_PRIVATE).unwrap();
+        let key = SigningKey::from_files(&key_file, &private_file).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!key.is_ksk());
+        assert_eq!(key.algorithm, DnssecAlgorithm::RsaSha256);
+        let rrset = RRset::from_str("www.example.com. 300 IN A 1.1.1.1").unwrap();
+        let rrsig = key.sign_rrset(&rrset, 1600000000, 1900000000).unwrap();
+        assert_eq!(rrsig.signature.len(), 256);
+        verify_rrsig(&rrset, &rrsig, &key.dnskey, 1700000000).unwrap();
+
+        let rrset = RRset::from_str(ED25519_KEY).unwrap();
+        let dnskey = match rrset.rdatas[0] {
+            RData::DNSKey(ref dnskey) => *dnskey.clone(),
+            _ => unreachable!(),
+        };
+        assert!(SigningKey::new(rrset.name.clone(), dnskey.clone(), RSA_PRIVATE).is_err());
+        let mut other = dnskey;
+        other.public_key[0] ^= 1;
+        assert!(SigningKey::new(rrset.name, other, ED25519_PRIVATE).is_err());
+    }
+}
@@ -0,0 +1,18 @@
+mod algorithm;
+mod canonical;
+mod denial;
+mod ds;
+mod key;
+mod nsec3;
+mod signer;
+mod validator;
+mod verifier;
+
+pub use algorithm::DnssecAlgorithm;
+pub use denial::{prove_denial, prove_wildcard_answer, Denial};
+pub use ds::DigestType;
+pub use key::SigningKey;
+pub use nsec3::{nsec3_hash, nsec3_owner, NSEC3_FLAG_OPT_OUT, NSEC3_HASH_SHA1};
+pub use signer::{Nsec3Config, ZoneSigner};
+pub use validator::{TrustAnchor, ValidationResult, Validator};
+pub use verifier::verify_rrsig;
@@ -0,0 +1,53 @@
+use crate::name::Name;
+use crate::util::base32::to_base32hex;
+use anyhow::Result;
+use ring::digest;
+
+pub const NSEC3_HASH_SHA1: u8 = 1;
+pub const NSEC3_FLAG_OPT_OUT: u8 = 1;
+
+//rfc5155 5, iterated sha1 of the lowercased wire format name
+pub fn nsec3_hash(name: &Name, salt: &[u8], iterations: u16) -> Vec<u8> {
+    let mut name = name.clone();
+    name.as_lowercase();
+    let mut hash = digest_with_salt(name.raw_data(), salt);
+    for _ in 0..iterations {
+        hash = digest_with_salt(&hash, salt);
+    }
+    hash
+}
+
+fn digest_with_salt(data: &[u8], salt: &[u8]) -> Vec<u8> {
+    let mut ctx = digest::Context::new(&digest::SHA1_FOR_LEGACY_USE_ONLY);
+    ctx.update(data);
+    ctx.update(salt);
+    ctx.finish().as_ref().to_vec()
+}
+
+pub fn nsec3_owner(hash: &[u8], zone: &Name) -> Result<Name> {
+    Name::new(&to_base32hex(hash))?.concat(zone)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_nsec3_hash() {
+        //rfc5155 appendix a
+        let zone = Name::new("example.").unwrap();
+        let salt = [0xaa, 0xbb, 0xcc, 0xdd];
+        for (name, owner) in [
+            ("example.", "0p9mhaveqvm6t7vbl5lop2u3t2rp3tom.example."),
+            ("A.example.", "35mthgpgcu1qg68fab165klnsnk3dpvl.example."),
+            ("ns1.example.", "2t7b4g4vsa5smi47k61mv5bv1a22bojr.example."),
+            ("x.w.example.", "b4um86eghhds6nea196smvmlo4ors995.example."),
+        ] {
+            let hash = nsec3_hash(&Name::new(name).unwrap(), &salt, 12);
+            assert_eq!(
+                nsec3_owner(&hash, &zone).unwrap().to_string(),
+                owner.to_string()
+            );
+        }
+    }
+}
@@ -0,0 +1,682 @@
+use crate::dnssec::key::SigningKey;
+use crate::dnssec::nsec3::{nsec3_hash, nsec3_owner, NSEC3_FLAG_OPT_OUT, NSEC3_HASH_SHA1};
+use crate::dnssec::verifier::verify_rrsig;
+use crate::name::Name;
+use crate::rdata::RData;
+use crate::rdatafield::TypeBitmap;
+use crate::rdatas::{NSEC3Param, RRSig, NSEC, NSEC3};
+use crate::rr_type::RRType;
+use crate::rrset::{RRTtl, RRset};
+use crate::zone::Zone;
+use anyhow::{bail, ensure, Result};
+use std::collections::{BTreeMap, HashMap};
+
+const DEFAULT_VALIDITY: u32 = 30 * 24 * 3600;
+const DEFAULT_REFRESH: u32 = 7 * 24 * 3600;
+//inception is moved back to tolerate clock skew of validators
+const INCEPTION_OFFSET: u32 = 3600;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nsec3Config {
+    pub salt: Vec<u8>,
+    pub iterations: u16,
+    pub opt_out: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum NameKind {
+    Authoritative,
+    Delegation { secure: bool },
+    EmptyNonTerminal,
+}
+
+pub struct ZoneSigner {
+    keys: Vec<SigningKey>,
+    validity: u32,
+    refresh: u32,
+    nsec3: Option<Nsec3Config>,
+}
+
+impl ZoneSigner {
+    pub fn new(keys: Vec<SigningKey>) -> Self {
+        ZoneSigner {
+            keys,
+            validity: DEFAULT_VALIDITY,
+            refresh: DEFAULT_REFRESH,
+            nsec3: None,
+        }
+    }
+
+    //seconds from signing time to expiration
+    pub fn validity(mut self, validity: u32) -> Self {
+        self.validity = validity;
+        self
+    }
+
+    //signatures expire within refresh seconds are regenerated
+    pub fn refresh(mut self, refresh: u32) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    pub fn nsec3(mut self, config: Nsec3Config) -> Self {
+        self.nsec3 = Some(config);
+        self
+    }
+
+    //sign or re-sign the zone, now is seconds since unix epoch, the denial
+    //chain is rebuilt while valid signatures of unchanged rrsets are kept
+    pub fn sign(&self, zone: &mut Zone, now: u64) -> Result<()> {
+        ensure!(!self.keys.is_empty(), "no key to sign zone");
+        ensure!(
+            self.refresh < self.validity,
+            "refresh should be less than validity"
+        );
+        for key in &self.keys {
+            ensure!(
+                key.name.eq(zone.origin()),
+                "key {} doesn't belong to zone {}",
+                key.name,
+                zone.origin()
+            );
+        }
+        let soa_ttl = match zone.soa().map(|soa| (soa.ttl.0, &soa.rdatas[0])) {
+            Some((ttl, RData::SOA(soa))) => ttl.min(soa.minimum),
+            _ => bail!("zone {} has no soa", zone.origin()),
+        };
+
+        //apex dnskey rrset is replaced by the keys of signer, so removed
+        //keys are no longer published
+        let origin = zone.origin().clone();
+        let dnskey_ttl = zone
+            .delete_rrset(&origin, RRType::DNSKEY)
+            .map_or(soa_ttl, |rrset| rrset.ttl.0);
+        zone.add_rrset(RRset {
+            name: origin,
+            typ: RRType::DNSKEY,
+            class: zone.class(),
+            ttl: RRTtl(dnskey_ttl),
+            rdatas: self
+                .keys
+                .iter()
+                .map(|key| RData::DNSKey(Box::new(key.dnskey.clone())))
+                .collect(),
+        })?;
+
+        let old_sigs = strip_dnssec_records(zone);
+        let names = classify_names(zone);
+        match self.nsec3 {
+            Some(ref config) => add_nsec3_chain(zone, &names, config, soa_ttl)?,
+            None => add_nsec_chain(zone, &names, soa_ttl)?,
+        }
+        self.sign_rrsets(zone, &names, &old_sigs, now)
+    }
+
+    fn sign_rrsets(
+        &self,
+        zone: &mut Zone,
+        names: &BTreeMap<Name, NameKind>,
+        old_sigs: &HashMap<(Name, RRType), Vec<RRSig>>,
+        now: u64,
+    ) -> Result<()> {
+        let inception = (now as u32).wrapping_sub(INCEPTION_OFFSET);
+        let expiration = (now as u32).wrapping_add(self.validity);
+
+        //nsec3 records live on hashed names which aren't classified
+        let mut owners: Vec<&Name> = names.keys().collect();
+        let hashed: Vec<Name> = zone
+            .rrsets()
+            .into_iter()
+            .filter(|rrset| rrset.typ == RRType::NSEC3)
+            .map(|rrset| rrset.name.clone())
+            .collect();
+        owners.extend(hashed.iter());
+
+        let mut rrsigs = Vec::new();
+        for name in owners {
+            let kind = names.get(name).copied().unwrap_or(NameKind::Authoritative);
+            //each rrsig is sent with the ttl of the rrset it covers, which
+            //is its original ttl
+            let mut sigs = Vec::new();
+            let mut ttl = 0;
+            for rrset in zone.rrsets_at(name) {
+                let signed = match kind {
+                    NameKind::Authoritative => true,
+                    NameKind::Delegation { .. } => {
+                        rrset.typ == RRType::DS || rrset.typ == RRType::NSEC
+                    }
+                    NameKind::EmptyNonTerminal => false,
+                };
+                if !signed {
+                    continue;
+                }
+                for key in self.signing_keys(rrset.typ) {
+                    let reused = old_sigs.get(&(name.clone(), rrset.typ)).and_then(|old| {
+                        old.iter().find(|sig| {
+                            sig.tag == key.key_tag()
+                                && sig.algorithm == key.algorithm.to_u8()
+                                && sig.original_ttl == rrset.ttl.0
+                                && sig.sig_expire.wrapping_sub(now as u32) as i32
+                                    > self.refresh as i32
+                                && verify_rrsig(rrset, sig, &key.dnskey, now).is_ok()
+                        })
+                    });
+                    let sig = match reused {
+                        Some(sig) => sig.clone(),
+                        None => key.sign_rrset(rrset, inception, expiration)?,
+                    };
+                    sigs.push(RData::RRSig(Box::new(sig)));
+                }
+                ttl = ttl.max(rrset.ttl.0);
+            }
+            if !sigs.is_empty() {
+                rrsigs.push(RRset {
+                    name: name.clone(),
+                    typ: RRType::RRSIG,
+                    class: zone.class(),
+                    ttl: RRTtl(ttl),
+                    rdatas: sigs,
+                });
+            }
+        }
+        for rrset in rrsigs {
+            zone.add_rrset(rrset)?;
+        }
+        Ok(())
+    }
+
+    //ksk signs dnskey and zsk signs the others, if one kind is missing, the
+    //other kind signs everything
+    fn signing_keys(&self, typ: RRType) -> impl Iterator<Item = &SigningKey> {
+        let want_ksk = typ == RRType::DNSKEY;
+        let has_kind = self.keys.iter().any(|key| key.is_ksk() == want_ksk);
+        self.keys
+            .iter()
+            .filter(move |key| !has_kind || key.is_ksk() == want_ksk)
+    }
+}
+
+//remove all the signatures and denial records, old signatures are returned
+//to be reused
+fn strip_dnssec_records(zone: &mut Zone) -> HashMap<(Name, RRType), Vec<RRSig>> {
+    let mut old_sigs: HashMap<(Name, RRType), Vec<RRSig>> = HashMap::new();
+    let dnssec_rrsets: Vec<(Name, RRType)> = zone
+        .rrsets()
+        .into_iter()
+        .filter(|rrset| {
+            matches!(
+                rrset.typ,
+                RRType::RRSIG | RRType::NSEC | RRType::NSEC3 | RRType::NSEC3PARAM
+            )
+        })
+        .map(|rrset| (rrset.name.clone(), rrset.typ))
+        .collect();
+    for (name, typ) in dnssec_rrsets {
+        if let Some(rrset) = zone.delete_rrset(&name, typ) {
+            for rdata in rrset.rdatas {
+                if let RData::RRSig(sig) = rdata {
+                    old_sigs
+                        .entry((name.clone(), sig.covered))
+                        .or_default()
+                        .push(*sig);
+                }
+            }
+        }
+    }
+    old_sigs
+}
+
+//names in canonical order, data below zone cut or dname is excluded
+fn classify_names(zone: &Zone) -> BTreeMap<Name, NameKind> {
+    let origin = zone.origin();
+    let mut names = BTreeMap::new();
+    let mut cuts: Vec<Name> = Vec::new();
+    for name in zone.names() {
+        if cuts.iter().any(|cut| name.is_subdomain(cut)) {
+            continue;
+        }
+        let has = |typ| zone.get_rrset(&name, typ).is_some();
+        let kind = if has(RRType::NS) && !name.eq(origin) {
+            cuts.push(name.clone());
+            NameKind::Delegation {
+                secure: has(RRType::DS),
+            }
+        } else {
+            if has(RRType::DNAME) {
+                cuts.push(name.clone());
+            }
+            NameKind::Authoritative
+        };
+        names.insert(name, kind);
+    }
+
+    let mut ents = Vec::new();
+    for name in names.keys() {
+        let mut parent = name.clone();
+        while parent.label_count() > origin.label_count() + 1 {
+            parent = parent.parent(1).expect("name has parent");
+            if !names.contains_key(&parent) {
+                ents.push(parent.clone());
+            }
+        }
+    }
+    for name in ents {
+        names.insert(name, NameKind::EmptyNonTerminal);
+    }
+    names
+}
+
+fn types_at(zone: &Zone, name: &Name, kind: NameKind) -> TypeBitmap {
+    let mut types: TypeBitmap = zone
+        .rrsets_at(name)
+        .into_iter()
+        .map(|rrset| rrset.typ)
+        .filter(|&typ| match kind {
+            NameKind::Delegation { .. } => typ == RRType::NS || typ == RRType::DS,
+            _ => true,
+        })
+        .collect();
+    if let NameKind::Authoritative | NameKind::Delegation { secure: true } = kind {
+        types.insert(RRType::RRSIG);
+    }
+    types
+}
+
+fn add_nsec_chain(zone: &mut Zone, names: &BTreeMap<Name, NameKind>, ttl: u32) -> Result<()> {
+    let chain: Vec<(&Name, NameKind)> = names
+        .iter()
+        .filter(|(_, &kind)| kind != NameKind::EmptyNonTerminal)
+        .map(|(name, &kind)| (name, kind))
+        .collect();
+    let mut nsecs = Vec::with_capacity(chain.len());
+    for (i, &(name, kind)) in chain.iter().enumerate() {
+        let mut types = types_at(zone, name, kind);
+        types.insert(RRType::NSEC);
+        types.insert(RRType::RRSIG);
+        nsecs.push(RRset {
+            name: name.clone(),
+            typ: RRType::NSEC,
+            class: zone.class(),
+            ttl: RRTtl(ttl),
+            rdatas: vec![RData::NSEC(Box::new(NSEC {
+                next_domain_name: chain[(i + 1) % chain.len()].0.clone(),
+                type_bit_map: types,
+            }))],
+        });
+    }
+    for nsec in nsecs {
+        zone.add_rrset(nsec)?;
+    }
+    Ok(())
+}
+
+fn add_nsec3_chain(
+    zone: &mut Zone,
+    names: &BTreeMap<Name, NameKind>,
+    config: &Nsec3Config,
+    ttl: u32,
+) -> Result<()> {
+    let mut hashes = BTreeMap::new();
+    for (name, &kind) in names {
+        if config.opt_out && kind == (NameKind::Delegation { secure: false }) {
+            continue;
+        }
+        let mut types = types_at(zone, name, kind);
+        if name.eq(zone.origin()) {
+            types.insert(RRType::NSEC3PARAM);
+        }
+        let hash = nsec3_hash(name, &config.salt, config.iterations);
+        if hashes.insert(hash, types).is_some() {
+            bail!("nsec3 hash collision on {}", name);
+        }
+    }
+
+    let flags = if config.opt_out {
+        NSEC3_FLAG_OPT_OUT
+    } else {
+        0
+    };
+    let hashes: Vec<(Vec<u8>, TypeBitmap)> = hashes.into_iter().collect();
+    let mut nsec3s = Vec::with_capacity(hashes.len());
+    for (i, (hash, types)) in hashes.iter().enumerate() {
+        nsec3s.push(RRset {
+            name: nsec3_owner(hash, zone.origin())?,
+            typ: RRType::NSEC3,
+            class: zone.class(),
+            ttl: RRTtl(ttl),
+            rdatas: vec![RData::NSEC3(Box::new(NSEC3 {
+                algorithm: NSEC3_HASH_SHA1,
+                flags,
+                iterations: config.iterations,
+                salt: config.salt.clone(),
+                next_hash: hashes[(i + 1) % hashes.len()].0.clone(),
+                types: types.clone(),
+            }))],
+        });
+    }
+    nsec3s.push(RRset {
+        name: zone.origin().clone(),
+        typ: RRType::NSEC3PARAM,
+        class: zone.class(),
+        ttl: RRTtl(0),
+        rdatas: vec![RData::NSEC3Param(Box::new(NSEC3Param {
+            hash_algorithm: NSEC3_HASH_SHA1,
+            flags: 0,
+            iterations: config.iterations,
+            salt: config.salt.clone(),
+        }))],
+    });
+    for rrset in nsec3s {
+        zone.add_rrset(rrset)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::fixture::{build_zone, name};
+    use crate::zonefile::{parse_zone, ZoneWriter};
+    use std::str::FromStr;
+
+    const ZONE: &str = r#"
+example.com. 3600 IN SOA ns1.example.com. hostmaster.example.com. 1 3600 900 604800 300
+example.com. 3600 IN NS ns1.example.com.
+ns1.example.com. 3600 IN A 1.1.1.1
+www.example.com. 300 IN A 2.2.2.2
+*.example.com. 3600 IN TXT "wild card"
+a.b.c.example.com. 3600 IN A 3.3.3.3
+sub.example.com. 3600 IN NS ns.sub.example.com.
+ns.sub.example.com. 3600 IN A 4.4.4.4
+sec.example.com. 3600 IN NS ns.sec.example.com.
+sec.example.com. 3600 IN DS 60485 5 1 2BB183AF5F22588179A53B0A98631FAD1A292118
+"#;
+
+    fn keys() -> Vec<SigningKey> {
+        [
+            ("example.com. 3600 IN DNSKEY 257 3 15 l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=",
+             "Private-key-format: v1.2\nAlgorithm: 15 (ED25519)\nPrivateKey: ODIyNjAzODQ2MjgwODAxMjI2NDUxOTAyMDQxNDIyNjI="),
+            ("example.com. 3600 IN DNSKEY 256 3 13 3IgJ3dapSaQmYA349Lbj6p4NhMUj0TS80JIY/E00YT5zyXiGf1EAjjbZkdw2eX1GryCnK2YQAWsGQ22nKx0lBg==",
+             "Private-key-format: v1.3\nAlgorithm: 13 (ECDSAP256SHA256)\nPrivateKey: lOKKUSQJ1pZ5Ob1zXsyy1juUz7Z7PeQHLnOsLdvATr8="),
+        ]
+        .iter()
+        .map(|(key, private_key)| {
+            let rrset = RRset::from_str(key).unwrap();
+            match rrset.rdatas[0] {
+                RData::DNSKey(ref dnskey) => {
+                    SigningKey::new(rrset.name, *dnskey.clone(), private_key).unwrap()
+                }
+                _ => unreachable!(),
+            }
+        })
+        .collect()
+    }
+
+    fn rrsigs(zone: &Zone, owner: &Name) -> Vec<RRSig> {
+        zone.get_rrset(owner, RRType::RRSIG)
+            .map(|rrset| {
+                rrset
+                    .rdatas
+                    .iter()
+                    .map(|rdata| match rdata {
+                        RData::RRSig(sig) => *sig.clone(),
+                        _ => unreachable!(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    //every signed rrset verifies with the key named by its signature, and
+    //returns the owner and type of signed rrsets
+    fn verify_zone(zone: &Zone, signer: &ZoneSigner, now: u64) -> Vec<(Name, RRType)> {
+        let mut signed = Vec::new();
+        for rrset in zone.rrsets() {
+            if rrset.typ == RRType::RRSIG {
+                continue;
+            }
+            let sigs: Vec<RRSig> = rrsigs(zone, &rrset.name)
+                .into_iter()
+                .filter(|sig| sig.covered == rrset.typ)
+                .collect();
+            for sig in &sigs {
+                let key = signer
+                    .keys
+                    .iter()
+                    .find(|key| key.key_tag() == sig.tag)
+                    .unwrap();
+                verify_rrsig(rrset, sig, &key.dnskey, now).unwrap();
+            }
+            if !sigs.is_empty() {
+                signed.push((rrset.name.clone(), rrset.typ));
+            }
+        }
+        signed
+    }
+
+    #[test]
+    fn test_sign_with_nsec() {
+        let now = 1700000000;
+        let signer = ZoneSigner::new(keys());
+        let mut zone = build_zone("example.com.", ZONE);
+        signer.sign(&mut zone, now).unwrap();
+        let signed = verify_zone(&zone, &signer, now);
+
+        let apex = name("example.com.");
+        for typ in [RRType::SOA, RRType::NS, RRType::DNSKEY, RRType::NSEC] {
+            assert!(signed.contains(&(apex.clone(), typ)));
+        }
+        //ksk only signs dnskey
+        for sig in rrsigs(&zone, &apex) {
+            assert_eq!(sig.algorithm == 15, sig.covered == RRType::DNSKEY);
+        }
+        let wildcard = rrsigs(&zone, &name("*.example.com."));
+        assert_eq!(wildcard[0].labels, 2);
+        //only ds and nsec are signed at delegation, glue isn't signed
+        assert!(!signed.contains(&(name("sub.example.com."), RRType::NS)));
+        assert!(signed.contains(&(name("sub.example.com."), RRType::NSEC)));
+        assert!(signed.contains(&(name("sec.example.com."), RRType::DS)));
+        assert!(rrsigs(&zone, &name("ns.sub.example.com.")).is_empty());
+        assert!(zone
+            .get_rrset(&name("ns.sub.example.com."), RRType::NSEC)
+            .is_none());
+
+        let mut owner = apex.clone();
+        let mut chain = Vec::new();
+        loop {
+            let nsec = match zone.get_rrset(&owner, RRType::NSEC).unwrap().rdatas[0] {
+                RData::NSEC(ref nsec) => nsec.clone(),
+                _ => unreachable!(),
+            };
+            chain.push(owner.to_string());
+            owner = nsec.next_domain_name.clone();
+            if owner == apex {
+                break;
+            }
+        }
+        assert_eq!(
+            chain,
+            vec![
+                "example.com.",
+                "*.example.com.",
+                "a.b.c.example.com.",
+                "ns1.example.com.",
+                "sec.example.com.",
+                "sub.example.com.",
+                "www.example.com.",
+            ]
+        );
+        assert_eq!(
+            zone.get_rrset(&name("sub.example.com."), RRType::NSEC)
+                .unwrap()
+                .rdatas[0],
+            RData::NSEC(Box::new(NSEC {
+                next_domain_name: name("www.example.com."),
+                type_bit_map: "NS NSEC RRSIG".parse().unwrap(),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_resign() {
+        let now = 1700000000;
+        let signer = ZoneSigner::new(keys())
+            .validity(30 * 86400)
+            .refresh(7 * 86400);
+        let mut zone = build_zone("example.com.", ZONE);
+        signer.sign(&mut zone, now).unwrap();
+        let www = name("www.example.com.");
+        let ns1 = name("ns1.example.com.");
+        let old_www = rrsigs(&zone, &www);
+        let old_ns1 = rrsigs(&zone, &ns1);
+
+        //unchanged rrsets keep the signature, changed ones are signed again
+        zone.add_rrset(RRset::from_str("www.example.com. 300 IN A 5.5.5.5").unwrap())
+            .unwrap();
+        signer.sign(&mut zone, now + 86400).unwrap();
+        verify_zone(&zone, &signer, now + 86400);
+        assert_eq!(rrsigs(&zone, &ns1), old_ns1);
+        let new_www = rrsigs(&zone, &www);
+        let a_sig = |sigs: &[RRSig]| {
+            sigs.iter()
+                .find(|sig| sig.covered == RRType::A)
+                .cloned()
+                .unwrap()
+        };
+        assert_eq!(
+            a_sig(&new_www).incpetion,
+            (now + 86400 - INCEPTION_OFFSET as u64) as u32
+        );
+        assert_ne!(a_sig(&new_www), a_sig(&old_www));
+
+        //signatures about to expire are refreshed
+        let later = now + 24 * 86400;
+        signer.sign(&mut zone, later).unwrap();
+        verify_zone(&zone, &signer, later);
+        for sig in rrsigs(&zone, &ns1) {
+            assert_eq!(sig.sig_expire, later as u32 + 30 * 86400);
+        }
+    }
+
+    #[test]
+    fn test_sign_with_nsec3() {
+        let now = 1700000000;
+        let config = Nsec3Config {
+            salt: vec![0xaa, 0xbb, 0xcc, 0xdd],
+            iterations: 5,
+            opt_out: true,
+        };
+        let signer = ZoneSigner::new(keys()).nsec3(config.clone());
+        let mut zone = build_zone("example.com.", ZONE);
+        signer.sign(&mut zone, now).unwrap();
+        let signed = verify_zone(&zone, &signer, now);
+        assert!(zone
+            .get_rrset(&name("example.com."), RRType::NSEC3PARAM)
+            .is_some());
+
+        let nsec3s: Vec<&RRset> = zone
+            .rrsets()
+            .into_iter()
+            .filter(|rrset| rrset.typ == RRType::NSEC3)
+            .collect();
+        //apex, *, a.b.c, b.c, c, ns1, sec and www, insecure sub is opted out
+        assert_eq!(nsec3s.len(), 8);
+        let origin = name("example.com.");
+        for owner in ["c.example.com.", "sec.example.com.", "example.com."] {
+            let hash = nsec3_hash(&name(owner), &config.salt, config.iterations);
+            let hashed = nsec3_owner(&hash, &origin).unwrap();
+            assert!(signed.contains(&(hashed, RRType::NSEC3)));
+        }
+        let hash = nsec3_hash(&name("sub.example.com."), &config.salt, config.iterations);
+        assert!(zone
+            .get_rrset(&nsec3_owner(&hash, &origin).unwrap(), RRType::NSEC3)
+            .is_none());
+
+        let mut hashes: Vec<(Vec<u8>, Vec<u8>)> = nsec3s
+            .iter()
+            .map(|rrset| match rrset.rdatas[0] {
+                RData::NSEC3(ref nsec3) => {
+                    assert_eq!(nsec3.flags, NSEC3_FLAG_OPT_OUT);
+                    let label = rrset.name.to_string();
+                    let label = label.split('.').next().unwrap().to_string();
+                    (
+                        crate::util::base32::from_base32hex(&label).unwrap(),
+                        nsec3.next_hash.clone(),
+                    )
+                }
+                _ => unreachable!(),
+            })
+            .collect();
+        hashes.sort();
+        for i in 0..hashes.len() {
+            assert_eq!(hashes[i].1, hashes[(i + 1) % hashes.len()].0);
+        }
+
+        //switching back to nsec removes the nsec3 chain
+        let signer = ZoneSigner::new(keys());
+        signer.sign(&mut zone, now).unwrap();
+        assert!(zone
+            .rrsets()
+            .iter()
+            .all(|rrset| rrset.typ != RRType::NSEC3 && rrset.typ != RRType::NSEC3PARAM));
+    }
+
+    #[test]
+    fn test_rrsig_ttl_and_dnskey() {
+        let now = 1700000000;
+        let mut zone = build_zone("example.com.", ZONE);
+        ZoneSigner::new(keys()).sign(&mut zone, now).unwrap();
+        let apex = name("example.com.");
+        let rrsigs = zone.get_rrset(&apex, RRType::RRSIG).unwrap();
+        for rdata in &rrsigs.rdatas {
+            let (covered, ttl) = match rdata {
+                RData::RRSig(sig) => (sig.covered, rrsigs.rr_ttl(rdata)),
+                _ => unreachable!(),
+            };
+            assert_eq!(ttl, zone.get_rrset(&apex, covered).unwrap().ttl);
+        }
+        assert!(rrsigs
+            .to_string()
+            .contains("example.com.\t3600\tIN\tRRSIG\tSOA "));
+        assert!(rrsigs
+            .to_string()
+            .contains("example.com.\t300\tIN\tRRSIG\tDNSKEY "));
+
+        //keys which aren't used by signer any more are removed
+        let signer = ZoneSigner::new(keys().into_iter().take(1).collect());
+        signer.sign(&mut zone, now).unwrap();
+        let dnskey = zone.get_rrset(&apex, RRType::DNSKEY).unwrap();
+        assert_eq!(dnskey.rdatas.len(), 1);
+        verify_zone(&zone, &signer, now);
+    }
+
+    #[test]
+    fn test_write_signed_zone() {
+        let now = 1700000000;
+        let config = Nsec3Config {
+            salt: Vec::new(),
+            iterations: 0,
+            opt_out: false,
+        };
+        let mut zone = build_zone("example.com.", ZONE);
+        ZoneSigner::new(keys())
+            .nsec3(config)
+            .sign(&mut zone, now)
+            .unwrap();
+        let origin = name("example.com.");
+        let param = zone.get_rrset(&origin, RRType::NSEC3PARAM).unwrap();
+        assert!(param.to_string().ends_with("NSEC3PARAM\t1 0 0 -\n"));
+
+        let canonical = |mut rrsets: Vec<RRset>| {
+            rrsets.iter_mut().for_each(|rrset| rrset.rdatas.sort());
+            rrsets.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.typ.cmp(&b.typ)));
+            rrsets
+        };
+        let rrsets: Vec<RRset> = zone.rrsets().into_iter().cloned().collect();
+        let output = ZoneWriter::new(origin.clone())
+            .write_to_string(&rrsets)
+            .unwrap();
+        let parsed = parse_zone(&output, &origin).unwrap();
+        assert_eq!(canonical(parsed), canonical(rrsets));
+    }
+}
@@ -0,0 +1,672 @@
+use crate::dnssec::algorithm::DnssecAlgorithm;
+use crate::dnssec::denial::{matching_types, prove_denial, prove_wildcard_answer, Denial};
+use crate::dnssec::ds::DigestType;
+use crate::dnssec::verifier::verify_rrsig;
+use crate::header_flag::HeaderFlag;
+use crate::name::Name;
+use crate::rcode::Rcode;
+use crate::rdata::RData;
+use crate::rdatas::{DNSKey, RRSig, DS};
+use crate::response::{Response, SectionType};
+use crate::response_builder::ResponseBuilder;
+use crate::rr_type::RRType;
+use crate::rrset::RRset;
+use anyhow::{bail, ensure, Result};
+use std::collections::HashMap;
+
+//security status of a response, rfc4035 4.3, variants are ordered from the
+//best to the worst, status of a response is the worst of its rrsets
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ValidationResult {
+    Secure,
+    //data is under a delegation proved to be unsigned
+    Insecure,
+    //chain of trust can't be built, like the name is outside the trust
+    //anchor or the fetch of ds or dnskey failed
+    Indeterminate,
+    Bogus,
+}
+
+//name and keys of the zone which owns a name, or why the chain of trust stops
+type ZoneKeys = std::result::Result<(Name, Vec<DNSKey>), ValidationResult>;
+
+#[derive(Debug, Clone)]
+pub struct TrustAnchor {
+    zone: Name,
+    ds: Vec<DS>,
+    keys: Vec<DNSKey>,
+}
+
+impl TrustAnchor {
+    //rrset of ds or dnskey configured for the anchor zone
+    pub fn new(rrset: RRset) -> Result<Self> {
+        let mut ds = Vec::new();
+        let mut keys = Vec::new();
+        for rdata in rrset.rdatas {
+            match rdata {
+                RData::DS(rdata) => ds.push(*rdata),
+                RData::DNSKey(rdata) => keys.push(*rdata),
+                _ => bail!("trust anchor should be ds or dnskey"),
+            }
+        }
+        ensure!(!ds.is_empty() || !keys.is_empty(), "trust anchor is empty");
+        Ok(TrustAnchor {
+            zone: rrset.name,
+            ds,
+            keys,
+        })
+    }
+
+    pub fn zone(&self) -> &Name {
+        &self.zone
+    }
+}
+
+//fetch returns the response of a ds or dnskey query, ds query should be sent
+//to the parent zone of the name
+pub struct Validator<F> {
+    anchor: TrustAnchor,
+    fetch: F,
+}
+
+impl<F> Validator<F>
+where
+    F: FnMut(&Name, RRType) -> Result<Response>,
+{
+    pub fn new(anchor: TrustAnchor, fetch: F) -> Self {
+        Validator { anchor, fetch }
+    }
+
+    //authentic data flag is set only when the response is secure, and
+    //cleared otherwise
+    pub fn validate(&mut self, resp: &mut Response, now: u64) -> ValidationResult {
+        let result = self.validate_response(resp, now);
+        let mut builder = ResponseBuilder::new(resp);
+        if result == ValidationResult::Secure {
+            builder.set_flag(HeaderFlag::AuthenticData);
+        } else {
+            builder.clear_flag(HeaderFlag::AuthenticData);
+        }
+        builder.done();
+        result
+    }
+
+    fn validate_response(&mut self, resp: &Response, now: u64) -> ValidationResult {
+        let mut cache = HashMap::new();
+        let answer = resp.section(SectionType::Answer).map_or(&[][..], |s| s);
+        let authority = resp.section(SectionType::Authority).map_or(&[][..], |s| s);
+        let qtype = resp.question.typ;
+        let mut qname = resp.question.name.clone();
+        let mut answered = false;
+        let mut result = ValidationResult::Secure;
+        for rrset in answer.iter().filter(|rrset| rrset.typ != RRType::RRSIG) {
+            let sigs = rrsigs(answer, rrset);
+            //cname synthesized from dname isn't signed, rfc6672 5.3.3
+            let synthesized = rrset.typ == RRType::CNAME
+                && sigs.is_empty()
+                && answer.iter().any(|dname| {
+                    dname.typ == RRType::DNAME
+                        && rrset.name.is_subdomain(&dname.name)
+                        && rrset.name != dname.name
+                });
+            if !synthesized {
+                let status = self.validate_rrset(rrset, &sigs, &mut cache, now);
+                result = result.max(status);
+                //labels of rrsig less than owner means wildcard expansion
+                let labels = sigs.iter().map(|sig| sig.labels).min();
+                if let Some(labels) = labels {
+                    if status == ValidationResult::Secure
+                        && (labels as usize) < rrset.name.label_count() - 1
+                    {
+                        let status =
+                            self.validate_wildcard(&rrset.name, labels, authority, &mut cache, now);
+                        result = result.max(status);
+                    }
+                }
+            }
+
+            if rrset.name == qname {
+                if rrset.typ == qtype {
+                    answered = true;
+                } else if rrset.typ == RRType::CNAME {
+                    if let Some(RData::CName(cname)) = rrset.rdatas.first() {
+                        qname = cname.name.clone();
+                    }
+                }
+            }
+        }
+
+        if !answered {
+            let status =
+                self.validate_denial(&qname, qtype, resp.header.rcode, authority, &mut cache, now);
+            result = result.max(status);
+        }
+        result
+    }
+
+    fn validate_rrset(
+        &mut self,
+        rrset: &RRset,
+        sigs: &[RRSig],
+        cache: &mut HashMap<Name, ZoneKeys>,
+        now: u64,
+    ) -> ValidationResult {
+        //ds belongs to the parent side of zone cut, nsec at zone cut is on
+        //the parent side unless signed by the child, and nsec3 owner is the
+        //hash label under the zone apex
+        let parent_side = match rrset.typ {
+            RRType::DS | RRType::NSEC3 => true,
+            RRType::NSEC => sigs.iter().all(|sig| sig.signer != rrset.name),
+            _ => false,
+        };
+        let owner = if parent_side && rrset.name.label_count() > 1 {
+            rrset.name.parent(1).expect("owner isn't root")
+        } else {
+            rrset.name.clone()
+        };
+        //unsigned data is only acceptable under an insecure delegation
+        let (zone, keys) = match self.zone_keys(&owner, cache, now) {
+            Ok(zone_keys) => zone_keys,
+            Err(result) => return result,
+        };
+        //signer must be the zone which contains the rrset, rfc4035 5.3.1
+        let sigs = sigs
+            .iter()
+            .filter(|sig| sig.signer == zone)
+            .cloned()
+            .collect::<Vec<_>>();
+        if verify_with_keys(rrset, &sigs, &keys, now) {
+            ValidationResult::Secure
+        } else {
+            ValidationResult::Bogus
+        }
+    }
+
+    fn validate_denial_rrsets(
+        &mut self,
+        authority: &[RRset],
+        cache: &mut HashMap<Name, ZoneKeys>,
+        now: u64,
+    ) -> ValidationResult {
+        authority.iter().filter(|rrset| is_denial(rrset)).fold(
+            ValidationResult::Secure,
+            |result, rrset| {
+                let sigs = rrsigs(authority, rrset);
+                result.max(self.validate_rrset(rrset, &sigs, cache, now))
+            },
+        )
+    }
+
+    fn validate_wildcard(
+        &mut self,
+        name: &Name,
+        labels: u8,
+        authority: &[RRset],
+        cache: &mut HashMap<Name, ZoneKeys>,
+        now: u64,
+    ) -> ValidationResult {
+        let result = self.validate_denial_rrsets(authority, cache, now);
+        if prove_wildcard_answer(name, labels, authority).is_err() {
+            ValidationResult::Bogus
+        } else {
+            result
+        }
+    }
+
+    fn validate_denial(
+        &mut self,
+        qname: &Name,
+        qtype: RRType,
+        rcode: Rcode,
+        authority: &[RRset],
+        cache: &mut HashMap<Name, ZoneKeys>,
+        now: u64,
+    ) -> ValidationResult {
+        let soa = match authority.iter().find(|rrset| rrset.typ == RRType::SOA) {
+            Some(soa) => soa,
+            None => {
+                return match self.zone_keys(qname, cache, now) {
+                    Ok(_) => ValidationResult::Bogus,
+                    Err(result) => result,
+                }
+            }
+        };
+        let result = self.validate_rrset(soa, &rrsigs(authority, soa), cache, now);
+        if result != ValidationResult::Secure {
+            return result;
+        }
+        let result = self.validate_denial_rrsets(authority, cache, now);
+        if result != ValidationResult::Secure {
+            return result;
+        }
+
+        match (rcode, prove_denial(qname, qtype, authority)) {
+            (Rcode::NXDomain, Ok(Denial::NXDomain))
+            | (Rcode::NoError, Ok(Denial::NoData))
+            | (Rcode::NoError, Ok(Denial::WildcardNoData)) => ValidationResult::Secure,
+            (_, Ok(Denial::OptOut)) => ValidationResult::Insecure,
+            _ => ValidationResult::Bogus,
+        }
+    }
+
+    //walk from the anchor down to name, one label each step, the closest
+    //enclosing zone and its keys are returned
+    fn zone_keys(
+        &mut self,
+        name: &Name,
+        cache: &mut HashMap<Name, ZoneKeys>,
+        now: u64,
+    ) -> ZoneKeys {
+        let anchor = self.anchor.zone.clone();
+        if !name.is_subdomain(&anchor) {
+            return Err(ValidationResult::Indeterminate);
+        }
+
+        let mut keys = match cache.get(&anchor) {
+            Some(keys) => keys.clone(),
+            None => {
+                let keys = self.anchor_keys(now);
+                cache.insert(anchor.clone(), keys.clone());
+                keys
+            }
+        }?;
+        for label_count in anchor.label_count() + 1..=name.label_count() {
+            let child = name
+                .parent(name.label_count() - label_count)
+                .expect("ancestor has less labels");
+            //the zone of name is the last one found in the walk
+            keys = match cache.get(&child) {
+                Some(keys) => keys.clone(),
+                None => {
+                    let child_keys = self.child_keys(&keys, &child, now);
+                    cache.insert(child, child_keys.clone());
+                    child_keys
+                }
+            }?;
+        }
+        Ok(keys)
+    }
+
+    fn anchor_keys(&mut self, now: u64) -> ZoneKeys {
+        let zone = self.anchor.zone.clone();
+        if !self.anchor.ds.is_empty() {
+            let ds = self.anchor.ds.clone();
+            return self.keys_by_ds(&zone, &ds, now);
+        }
+        let anchor_keys = self.anchor.keys.clone();
+        self.self_signed_keys(&zone, |key| anchor_keys.contains(key), now)
+    }
+
+    //ds of child is signed by parent, if it doesn't exist, the signed
+    //denial proves either an unsigned delegation or child isn't a zone cut
+    fn child_keys(&mut self, parent: &(Name, Vec<DNSKey>), child: &Name, now: u64) -> ZoneKeys {
+        let parent_keys = &parent.1;
+        let resp = (self.fetch)(child, RRType::DS).map_err(|_| ValidationResult::Indeterminate)?;
+        let answer = resp.section(SectionType::Answer).map_or(&[][..], |s| s);
+        let authority = resp.section(SectionType::Authority).map_or(&[][..], |s| s);
+        if let Some(rrset) = answer
+            .iter()
+            .find(|rrset| rrset.typ == RRType::DS && rrset.name == *child)
+        {
+            if !verify_with_keys(rrset, &rrsigs(answer, rrset), parent_keys, now) {
+                return Err(ValidationResult::Bogus);
+            }
+            let ds = rrset
+                .rdatas
+                .iter()
+                .filter_map(|rdata| match rdata {
+                    RData::DS(ds) => Some(*ds.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            return self.keys_by_ds(child, &ds, now);
+        }
+
+        let mut denials = authority.iter().filter(|rrset| is_denial(rrset)).peekable();
+        if denials.peek().is_none()
+            || !denials
+                .all(|rrset| verify_with_keys(rrset, &rrsigs(authority, rrset), parent_keys, now))
+        {
+            return Err(ValidationResult::Bogus);
+        }
+        match prove_denial(child, RRType::DS, authority) {
+            Ok(Denial::OptOut) => Err(ValidationResult::Insecure),
+            Ok(Denial::NoData) => {
+                if matching_types(child, authority).is_some_and(|types| types.has_type(RRType::NS))
+                {
+                    Err(ValidationResult::Insecure)
+                } else {
+                    Ok(parent.clone())
+                }
+            }
+            //a name which doesn't exist can't be a zone cut
+            Ok(Denial::NXDomain | Denial::WildcardExpansion | Denial::WildcardNoData) => {
+                Ok(parent.clone())
+            }
+            Err(_) => Err(ValidationResult::Bogus),
+        }
+    }
+
+    //zone is treated as unsigned if no ds has supported digest and
+    //algorithm, rfc4035 5.2
+    fn keys_by_ds(&mut self, zone: &Name, ds: &[DS], now: u64) -> ZoneKeys {
+        let ds = ds
+            .iter()
+            .filter(|ds| {
+                DigestType::new(ds.digest_type).is_some()
+                    && DnssecAlgorithm::new(ds.algorithm).is_some()
+            })
+            .collect::<Vec<_>>();
+        if ds.is_empty() {
+            return Err(ValidationResult::Insecure);
+        }
+        self.self_signed_keys(zone, |key| ds.iter().any(|ds| ds.matches(zone, key)), now)
+    }
+
+    //dnskey rrset of zone should be signed by one of the trusted keys
+    fn self_signed_keys<T: Fn(&DNSKey) -> bool>(
+        &mut self,
+        zone: &Name,
+        trusted: T,
+        now: u64,
+    ) -> ZoneKeys {
+        let resp =
+            (self.fetch)(zone, RRType::DNSKEY).map_err(|_| ValidationResult::Indeterminate)?;
+        let answer = resp.section(SectionType::Answer).map_or(&[][..], |s| s);
+        let rrset = answer
+            .iter()
+            .find(|rrset| rrset.typ == RRType::DNSKEY && rrset.name == *zone)
+            .ok_or(ValidationResult::Bogus)?;
+        let keys = rrset
+            .rdatas
+            .iter()
+            .filter_map(|rdata| match rdata {
+                RData::DNSKey(key) => Some(*key.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let trusted_keys = keys
+            .iter()
+            .filter(|key| trusted(key))
+            .cloned()
+            .collect::<Vec<_>>();
+        if verify_with_keys(rrset, &rrsigs(answer, rrset), &trusted_keys, now) {
+            Ok((zone.clone(), keys))
+        } else {
+            Err(ValidationResult::Bogus)
+        }
+    }
+}
+
+fn is_denial(rrset: &RRset) -> bool {
+    rrset.typ == RRType::NSEC || rrset.typ == RRType::NSEC3
+}
+
+//signatures in the section covering rrset
+fn rrsigs(section: &[RRset], rrset: &RRset) -> Vec<RRSig> {
+    section
+        .iter()
+        .filter(|sigs| sigs.typ == RRType::RRSIG && sigs.name == rrset.name)
+        .flat_map(|sigs| sigs.rdatas.iter())
+        .filter_map(|rdata| match rdata {
+            RData::RRSig(sig) if sig.covered == rrset.typ => Some(*sig.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn verify_with_keys(rrset: &RRset, sigs: &[RRSig], keys: &[DNSKey], now: u64) -> bool {
+    sigs.iter().any(|sig| {
+        keys.iter()
+            .any(|key| verify_rrsig(rrset, sig, key, now).is_ok())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dnssec::{Nsec3Config, SigningKey, ZoneSigner};
+    use crate::util::fixture::{build_zone, name};
+    use crate::zone::Zone;
+    use std::str::FromStr;
+
+    const NOW: u64 = 1700000000;
+    const COM_KEY: (&str, &str) = (
+        "com. 3600 IN DNSKEY 257 3 15 l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=",
+        "Private-key-format: v1.2\nAlgorithm: 15 (ED25519)\nPrivateKey: ODIyNjAzODQ2MjgwODAxMjI2NDUxOTAyMDQxNDIyNjI=",
+    );
+    const EXAMPLE_KEY: (&str, &str) = (
+        "example.com. 3600 IN DNSKEY 256 3 13 3IgJ3dapSaQmYA349Lbj6p4NhMUj0TS80JIY/E00YT5zyXiGf1EAjjbZkdw2eX1GryCnK2YQAWsGQ22nKx0lBg==",
+        "Private-key-format: v1.3\nAlgorithm: 13 (ECDSAP256SHA256)\nPrivateKey: lOKKUSQJ1pZ5Ob1zXsyy1juUz7Z7PeQHLnOsLdvATr8=",
+    );
+    const COM: &str = r#"
+com. 3600 IN SOA ns.com. hostmaster.com. 1 3600 900 604800 300
+com. 3600 IN NS ns.com.
+ns.com. 3600 IN A 1.1.1.1
+example.com. 3600 IN NS ns.example.com.
+insecure.com. 3600 IN NS ns.insecure.com.
+"#;
+    const EXAMPLE: &str = r#"
+example.com. 3600 IN SOA ns.example.com. hostmaster.example.com. 1 3600 900 604800 300
+example.com. 3600 IN NS ns.example.com.
+ns.example.com. 3600 IN A 2.2.2.2
+www.example.com. 3600 IN A 3.3.3.3
+*.example.com. 3600 IN TXT "wild card"
+"#;
+
+    fn signing_key((key, private_key): (&str, &str)) -> SigningKey {
+        let rrset = RRset::from_str(key).unwrap();
+        match rrset.rdatas[0] {
+            RData::DNSKey(ref dnskey) => {
+                SigningKey::new(rrset.name, *dnskey.clone(), private_key).unwrap()
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn signed_zone(origin: &str, content: &str, signer: Option<ZoneSigner>) -> Zone {
+        let mut zone = build_zone(origin, content);
+        if let Some(signer) = signer {
+            signer.sign(&mut zone, NOW).unwrap();
+        }
+        zone
+    }
+
+    //com delegates to signed example.com and unsigned insecure.com
+    fn zones() -> Vec<Zone> {
+        let example_key = signing_key(EXAMPLE_KEY);
+        let ds = example_key
+            .dnskey
+            .to_ds(&name("example.com."), DigestType::Sha256);
+        let mut com = signed_zone("com.", COM, None);
+        com.add_rrset(RRset {
+            name: name("example.com."),
+            typ: RRType::DS,
+            class: com.class(),
+            ttl: RRset::from_str(EXAMPLE_KEY.0).unwrap().ttl,
+            rdatas: vec![RData::DS(Box::new(ds))],
+        })
+        .unwrap();
+        ZoneSigner::new(vec![signing_key(COM_KEY)])
+            .sign(&mut com, NOW)
+            .unwrap();
+        let nsec3 = Nsec3Config {
+            salt: vec![0xaa, 0xbb],
+            iterations: 1,
+            opt_out: false,
+        };
+        let example = signed_zone(
+            "example.com.",
+            EXAMPLE,
+            Some(ZoneSigner::new(vec![example_key]).nsec3(nsec3)),
+        );
+        let insecure = signed_zone(
+            "insecure.com.",
+            "insecure.com. 3600 IN SOA ns.insecure.com. hostmaster.insecure.com. 1 3600 900 604800 300\nwww.insecure.com. 3600 IN A 4.4.4.4",
+            None,
+        );
+        vec![com, example, insecure]
+    }
+
+    fn with_sigs(zone: &Zone, rrset: &RRset) -> Vec<RRset> {
+        let mut rrsets = vec![rrset.clone()];
+        if let Some(sigs) = zone.get_rrset(&rrset.name, RRType::RRSIG) {
+            let mut sigs = sigs.clone();
+            sigs.rdatas.retain(|rdata| match rdata {
+                RData::RRSig(sig) => sig.covered == rrset.typ,
+                _ => false,
+            });
+            if !sigs.rdatas.is_empty() {
+                rrsets.push(sigs);
+            }
+        }
+        rrsets
+    }
+
+    //answer from the zone with signatures, or soa and the whole denial
+    //chain in authority, wildcard isn't expanded
+    fn query(zones: &[Zone], qname: &Name, qtype: RRType) -> Response {
+        //ds is served by the parent side of the zone cut
+        let zone = zones
+            .iter()
+            .filter(|zone| {
+                qname.is_subdomain(zone.origin()) && (qtype != RRType::DS || qname != zone.origin())
+            })
+            .max_by_key(|zone| zone.origin().label_count())
+            .unwrap();
+        let mut resp = Response::with_question(qname.clone(), qtype);
+        let mut builder = ResponseBuilder::new(&mut resp);
+        if let Some(rrset) = zone.get_rrset(qname, qtype) {
+            for rrset in with_sigs(zone, rrset) {
+                builder.add_rrset(SectionType::Answer, rrset);
+            }
+        } else {
+            if zone.rrsets_at(qname).is_empty() {
+                builder.rcode(Rcode::NXDomain);
+            }
+            let soa = zone.get_rrset(zone.origin(), RRType::SOA).unwrap();
+            let denials = zone.rrsets().into_iter().filter(|rrset| is_denial(rrset));
+            for rrset in std::iter::once(soa).chain(denials) {
+                for rrset in with_sigs(zone, rrset) {
+                    builder.add_rrset(SectionType::Authority, rrset);
+                }
+            }
+        }
+        builder.done();
+        resp
+    }
+
+    fn validate(zones: &[Zone], resp: &mut Response) -> ValidationResult {
+        let anchor = TrustAnchor::new(RRset::from_str(COM_KEY.0).unwrap()).unwrap();
+        let mut validator = Validator::new(anchor, |name: &Name, typ| Ok(query(zones, name, typ)));
+        validator.validate(resp, NOW)
+    }
+
+    #[test]
+    fn test_validate() {
+        let zones = zones();
+        for (qname, qtype, expect) in [
+            ("www.example.com.", RRType::A, ValidationResult::Secure),
+            ("www.example.com.", RRType::AAAA, ValidationResult::Secure),
+            ("x.www.example.com.", RRType::A, ValidationResult::Secure),
+            ("example.com.", RRType::DS, ValidationResult::Secure),
+            ("nonexist.com.", RRType::A, ValidationResult::Secure),
+            ("www.insecure.com.", RRType::A, ValidationResult::Insecure),
+            (
+                "nonexist.insecure.com.",
+                RRType::A,
+                ValidationResult::Insecure,
+            ),
+        ] {
+            let mut resp = query(&zones, &name(qname), qtype);
+            resp.header.set_flag(HeaderFlag::AuthenticData, true);
+            assert_eq!(validate(&zones, &mut resp), expect, "{} {}", qname, qtype);
+            assert_eq!(
+                resp.header.is_flag_set(HeaderFlag::AuthenticData),
+                expect == ValidationResult::Secure
+            );
+        }
+
+        let mut resp = Response::with_question(name("www.example.net."), RRType::A);
+        ResponseBuilder::new(&mut resp)
+            .add_rrset(
+                SectionType::Answer,
+                RRset::from_str("www.example.net. 3600 IN A 6.6.6.6").unwrap(),
+            )
+            .done();
+        assert_eq!(validate(&zones, &mut resp), ValidationResult::Indeterminate);
+
+        //x.example.com doesn't exist, answer from the wildcard needs proof
+        let example = &zones[1];
+        let wildcard = example
+            .get_rrset(&name("*.example.com."), RRType::TXT)
+            .unwrap();
+        let mut resp = Response::with_question(name("x.example.com."), RRType::TXT);
+        let mut builder = ResponseBuilder::new(&mut resp);
+        for mut rrset in with_sigs(example, wildcard) {
+            rrset.name = name("x.example.com.");
+            builder.add_rrset(SectionType::Answer, rrset);
+        }
+        builder.done();
+        let mut expanded = resp.clone();
+        assert_eq!(validate(&zones, &mut expanded), ValidationResult::Bogus);
+        let proof = query(&zones, &name("x.example.com."), RRType::A);
+        resp.sections[SectionType::Authority as usize] =
+            proof.sections[SectionType::Authority as usize].clone();
+        assert_eq!(validate(&zones, &mut resp), ValidationResult::Secure);
+    }
+
+    #[test]
+    fn test_validate_bogus() {
+        let zones = zones();
+        let mut resp = query(&zones, &name("www.example.com."), RRType::A);
+        resp.section_mut(SectionType::Answer).unwrap()[0].rdatas[0] =
+            RData::from_str(RRType::A, "5.5.5.5").unwrap();
+        assert_eq!(validate(&zones, &mut resp), ValidationResult::Bogus);
+
+        let mut resp = query(&zones, &name("www.example.com."), RRType::A);
+        ResponseBuilder::new(&mut resp)
+            .remove_rrset_by(SectionType::Answer, |rrset| rrset.typ == RRType::RRSIG)
+            .done();
+        assert_eq!(validate(&zones, &mut resp), ValidationResult::Bogus);
+
+        //valid signature by com isn't acceptable for data in example.com
+        let mut resp = query(&zones, &name("www.example.com."), RRType::A);
+        let answer = resp.section_mut(SectionType::Answer).unwrap();
+        let sig = signing_key(COM_KEY)
+            .sign_rrset(&answer[0], NOW as u32 - 3600, NOW as u32 + 3600)
+            .unwrap();
+        answer[1].rdatas = vec![RData::RRSig(Box::new(sig))];
+        assert_eq!(validate(&zones, &mut resp), ValidationResult::Bogus);
+
+        //nxdomain proof doesn't match rcode
+        let mut resp = query(&zones, &name("x.www.example.com."), RRType::A);
+        ResponseBuilder::new(&mut resp).rcode(Rcode::NoError).done();
+        assert_eq!(validate(&zones, &mut resp), ValidationResult::Bogus);
+
+        //ds of example.com in com doesn't match its key
+        let mut zones = zones;
+        let mut ds = zones[0]
+            .get_rrset(&name("example.com."), RRType::DS)
+            .unwrap()
+            .clone();
+        if let RData::DS(ref mut ds) = ds.rdatas[0] {
+            ds.digest[0] ^= 0xff;
+        }
+        zones[0]
+            .delete_rrset(&name("example.com."), RRType::DS)
+            .unwrap();
+        zones[0].add_rrset(ds).unwrap();
+        let mut resp = query(&zones, &name("www.example.com."), RRType::A);
+        assert_eq!(validate(&zones, &mut resp), ValidationResult::Bogus);
+
+        //fetch of keys failed
+        let mut resp = query(&zones, &name("www.example.com."), RRType::A);
+        let anchor = TrustAnchor::new(RRset::from_str(COM_KEY.0).unwrap()).unwrap();
+        let mut validator = Validator::new(anchor, |_: &Name, _| bail!("timeout"));
+        assert_eq!(
+            validator.validate(&mut resp, NOW),
+            ValidationResult::Indeterminate
+        );
+    }
+}
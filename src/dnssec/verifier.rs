@@ -0,0 +1,213 @@
+use crate::dnssec::algorithm::DnssecAlgorithm;
+use crate::dnssec::canonical::signed_data;
+use crate::error::Error;
+use crate::rdatas::{DNSKey, RRSig};
+use crate::rrset::RRset;
+use anyhow::{bail, Result};
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+
+const ZONE_KEY_FLAG: u16 = 0x0100;
+const DNSSEC_PROTOCOL: u8 = 3;
+const MIN_RSA_KEY_BITS: usize = 1024;
+
+//verify the signature of rrset with one candidate key, now is seconds since
+//unix epoch, and validity period is compared with serial number arithmetic
+pub fn verify_rrsig(rrset: &RRset, rrsig: &RRSig, key: &DNSKey, now: u64) -> Result<()> {
+    if rrsig.covered != rrset.typ
+        || rrset.rdatas.is_empty()
+        || !rrset.name.is_subdomain(&rrsig.signer)
+    {
+        bail!(Error::RRsetMismatch);
+    }
+
+    let algorithm = match DnssecAlgorithm::new(rrsig.algorithm) {
+        Some(algorithm) => algorithm,
+        None => bail!(Error::UnsupportedAlgorithm(rrsig.algorithm)),
+    };
+    if key.algorithm != rrsig.algorithm
+        || key.protocol != DNSSEC_PROTOCOL
+        || key.flags & ZONE_KEY_FLAG == 0
+        || key.key_tag() != rrsig.tag
+    {
+        bail!(Error::KeyMismatch);
+    }
+
+    let now = now as u32;
+    if (now.wrapping_sub(rrsig.incpetion) as i32) < 0 {
+        bail!(Error::SignatureNotIncepted);
+    }
+    if (rrsig.sig_expire.wrapping_sub(now) as i32) < 0 {
+        bail!(Error::SignatureExpired);
+    }
+
+    let data = match signed_data(rrset, rrsig) {
+        Ok(data) => data,
+        Err(_) => bail!(Error::RRsetMismatch),
+    };
+    verify_signature(algorithm, &key.public_key, &data, &rrsig.signature)
+}
+
+fn verify_signature(
+    algorithm: DnssecAlgorithm,
+    public_key: &[u8],
+    data: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    let result = match algorithm {
+        DnssecAlgorithm::RsaSha256 | DnssecAlgorithm::RsaSha512 => {
+            let (e, n) = parse_rsa_key(public_key)?;
+            let params = if algorithm == DnssecAlgorithm::RsaSha256 {
+                &signature::RSA_PKCS1_1024_8192_SHA256_FOR_LEGACY_USE_ONLY
+            } else {
+                &signature::RSA_PKCS1_1024_8192_SHA512_FOR_LEGACY_USE_ONLY
+            };
+            RsaPublicKeyComponents { n, e }.verify(params, data, sig)
+        }
+        DnssecAlgorithm::EcdsaP256Sha256 | DnssecAlgorithm::EcdsaP384Sha384 => {
+            let (params, key_len) = if algorithm == DnssecAlgorithm::EcdsaP256Sha256 {
+                (&signature::ECDSA_P256_SHA256_FIXED, 64)
+            } else {
+                (&signature::ECDSA_P384_SHA384_FIXED, 96)
+            };
+            if public_key.len() != key_len {
+                bail!(Error::InvalidKey);
+            }
+            //dnskey stores the uncompressed point without the 0x04 prefix
+            let mut point = Vec::with_capacity(key_len + 1);
+            point.push(4);
+            point.extend_from_slice(public_key);
+            UnparsedPublicKey::new(params, point).verify(data, sig)
+        }
+        DnssecAlgorithm::Ed25519 => {
+            if public_key.len() != 32 {
+                bail!(Error::InvalidKey);
+            }
+            UnparsedPublicKey::new(&signature::ED25519, public_key).verify(data, sig)
+        }
+    };
+    if result.is_err() {
+        bail!(Error::BadSignature);
+    }
+    Ok(())
+}
+
+//rfc3110 2, exponent length is one byte, or zero followed by two bytes
+fn parse_rsa_key(key: &[u8]) -> Result<(&[u8], &[u8])> {
+    let (exp_len, key) = match key {
+        [0, hi, lo, rest @ ..] => ((*hi as usize) << 8 | *lo as usize, rest),
+        [len, rest @ ..] => (*len as usize, rest),
+        [] => bail!(Error::InvalidKey),
+    };
+    if exp_len == 0 || key.len() <= exp_len {
+        bail!(Error::InvalidKey);
+    }
+    let (e, n) = key.split_at(exp_len);
+    let e = strip_leading_zero(e);
+    let n = strip_leading_zero(n);
+    if n.len() * 8 < MIN_RSA_KEY_BITS || e.is_empty() {
+        bail!(Error::InvalidKey);
+    }
+    Ok((e, n))
+}
+
+fn strip_leading_zero(data: &[u8]) -> &[u8] {
+    let zeros = data.iter().take_while(|&&b| b == 0).count();
+    &data[zeros..]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rdata::RData;
+    use std::str::FromStr;
+
+    fn rrsig_and_key(rrsig: &str, key: &str) -> (RRSig, DNSKey) {
+        let rrsig = match RRset::from_str(rrsig).unwrap().rdatas.remove(0) {
+            RData::RRSig(rrsig) => *rrsig,
+            _ => unreachable!(),
+        };
+        let key = match RRset::from_str(key).unwrap().rdatas.remove(0) {
+            RData::DNSKey(key) => *key,
+            _ => unreachable!(),
+        };
+        (rrsig, key)
+    }
+
+    #[test]
+    fn test_verify_ed25519() {
+        //rfc8080 6.1
+        let rrset = RRset::from_str("example.com. 3600 IN MX 10 mail.example.com.").unwrap();
+        let (rrsig, key) = rrsig_and_key(
+            "example.com. 3600 IN RRSIG MX 15 2 3600 20150819220000 20150729220000 3613 example.com. oL9krJun7xfBOIWcGHi7mag5/hdZrKWw15jPGrHpjQeRAvTdszaPD+QLs3fx8A4M3e23mRZ9VrbpMngwcrqNAg==",
+            "example.com. 3600 IN DNSKEY 257 3 15 l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=",
+        );
+        assert_eq!(key.key_tag(), 3613);
+        let now = 1439000000;
+        verify_rrsig(&rrset, &rrsig, &key, now).unwrap();
+
+        let mut bogus = rrset.clone();
+        bogus.rdatas = RRset::from_str("example.com. 3600 IN MX 20 mail.example.com.")
+            .unwrap()
+            .rdatas;
+        let err = verify_rrsig(&bogus, &rrsig, &key, now).unwrap_err();
+        assert_eq!(err.downcast_ref::<Error>(), Some(&Error::BadSignature));
+
+        for (time, expect) in [
+            (1438000000, Error::SignatureNotIncepted),
+            (1441000000, Error::SignatureExpired),
+        ] {
+            let err = verify_rrsig(&rrset, &rrsig, &key, time).unwrap_err();
+            assert_eq!(err.downcast_ref::<Error>(), Some(&expect));
+        }
+
+        let mut other_key = key.clone();
+        other_key.flags = 0;
+        let err = verify_rrsig(&rrset, &rrsig, &other_key, now).unwrap_err();
+        assert_eq!(err.downcast_ref::<Error>(), Some(&Error::KeyMismatch));
+    }
+
+    #[test]
+    fn test_verify_ecdsa() {
+        //rfc6605 6.1
+        let rrset = RRset::from_str("www.example.net. 3600 IN A 192.0.2.1").unwrap();
+        let (rrsig, key) = rrsig_and_key(
+            "www.example.net. 3600 IN RRSIG A 13 3 3600 20100909100439 20100812100439 55648 example.net. qx6wLYqmh+l9oCKTN6qIc+bw6ya+KJ8oMz0YP107epXAyGmt+3SNruPFKG7tZoLBLlUzGGus7ZwmwWep666VCw==",
+            "example.net. 3600 IN DNSKEY 257 3 13 GojIhhXUN/u4v54ZQqGSnyhWJwaubCvTmeexv7bR6edbkrSqQpF64cYbcB7wNcP+e+MAnLr+Wi9xMWyQLc8NAA==",
+        );
+        verify_rrsig(&rrset, &rrsig, &key, 1282000000).unwrap();
+        let err = verify_rrsig(&rrset, &rrsig, &key, 1290000000).unwrap_err();
+        assert_eq!(err.downcast_ref::<Error>(), Some(&Error::SignatureExpired));
+
+        //wildcard expanded answer, signed as *.example.com.
+        let rrset = RRset::from_str("foo.bar.example.com. 300 IN TXT \"hello\"").unwrap();
+        let (rrsig, key) = rrsig_and_key(
+            "foo.bar.example.com. 300 IN RRSIG TXT 14 2 300 20300317174640 20200913122640 24562 example.com. YTW1uj2vWU9mX4hUiK2JkIMC+HiXuPW1RJX69ZL+ZgNDZOJKPTsRhomZzDRXKSKKAHd2WmwauqkzlhzxbgPHiuL/qiMxB5y2AG171MkhfXxCSusyYxBy/IbgNwyP7CL5",
+            "example.com. 3600 IN DNSKEY 256 3 14 tVrot6uCZm4jRoH2RJHzb+Qqa0F7dl/HgniZe6RhFe5BTYZUWxlFjKfp9gP0qj04UYAEtLoeO5mebydfMaTHewFEQHYDgSnjNxINJ5VipPvlmOso0IIxCded8FFwz5H+",
+        );
+        verify_rrsig(&rrset, &rrsig, &key, 1700000000).unwrap();
+    }
+
+    #[test]
+    fn test_verify_rsa() {
+        //rdata is sorted and names in it are lowercased before signing
+        let rrset = RRset::from_strs(&[
+            "Example.COM. 3600 IN NS NS2.example.com.",
+            "Example.COM. 3600 IN NS ns1.Example.com.",
+        ])
+        .unwrap();
+        let (rrsig, key) = rrsig_and_key(
+            "example.com. 3600 IN RRSIG NS 8 2 3600 20300317174640 20200913122640 40065 example.com. sd0ShJnQF+8iYxaZq6fYhgw1IKbN1sdf+1cD6Orj7Q5bU9ySOnRnBxebHSIDqviw1pWizkqKh67Y9gKNCzbHLThc6pg/C8YHpy1weCVSOtsHgLLXm85pHMdaeZrSw7yq9tLIUJNAD2TcUxZ8Rel+D3BMljAHRJYW1GGp7MX6nzI=",
+            "example.com. 3600 IN DNSKEY 256 3 8 AwEAAdXSYQ3rQ06fgwHSlce9fu4v1e0qwLpGXecwAfxu01VhjjmP37HQRSw9oH39ruyVLMHMWw4p7XFnKwak9bJrEZh+YOK+45xfDs+vh1tNBmPa14KaGvMZwwiAaCZy+uJ/eoTkPrpj3M3oh1YdYhrAYwJD4HIyRwkXUoYGBEIyZnhP",
+        );
+        verify_rrsig(&rrset, &rrsig, &key, 1700000000).unwrap();
+
+        let mut wrong_type = rrsig.clone();
+        wrong_type.covered = crate::rr_type::RRType::A;
+        let err = verify_rrsig(&rrset, &wrong_type, &key, 1700000000).unwrap_err();
+        assert_eq!(err.downcast_ref::<Error>(), Some(&Error::RRsetMismatch));
+
+        let mut short_key = key.clone();
+        short_key.public_key.truncate(64);
+        assert!(parse_rsa_key(&short_key.public_key).is_err());
+    }
+}
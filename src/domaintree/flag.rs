@@ -1,4 +1,4 @@
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub struct NodeFlag(u16);
 
 impl NodeFlag {
@@ -27,12 +27,6 @@ impl NodeFlag {
     }
 }
 
-impl Default for NodeFlag {
-    fn default() -> Self {
-        NodeFlag(0)
-    }
-}
-
 const COLOR_MASK: u16 = 0x0001;
 const SUBTREE_ROOT_MASK: u16 = 0x0002;
 const CALLBACK_MASK: u16 = 0x0004;
@@ -111,9 +105,9 @@ mod tests {
     fn test_flag() {
         let mut flag = NodeFlag::default();
         assert!(flag.is_red());
-        assert!(flag.is_subtree_root() == false);
-        assert!(flag.is_callback_enabled() == false);
-        assert!(flag.is_wildcard() == false);
+        assert!(!flag.is_subtree_root());
+        assert!(!flag.is_callback_enabled());
+        assert!(!flag.is_wildcard());
 
         flag.set_color(Color::Red);
         assert!(flag.is_red());
@@ -32,7 +32,7 @@ pub struct NodePtr<T>(pub *mut Node<T>);
 
 impl<T> Clone for NodePtr<T> {
     fn clone(&self) -> NodePtr<T> {
-        NodePtr(self.0)
+        *self
     }
 }
 
@@ -48,7 +48,7 @@ impl<T> Ord for NodePtr<T> {
 
 impl<T> PartialOrd for NodePtr<T> {
     fn partial_cmp(&self, other: &NodePtr<T>) -> Option<Ordering> {
-        unsafe { Some((*self.0).name.cmp(&(*other.0).name)) }
+        Some(self.cmp(other))
     }
 }
 
@@ -238,6 +238,9 @@ impl<T> NodePtr<T> {
         unsafe { &mut (*self.0).down.0 }
     }
 
+    /// # Safety
+    ///
+    /// both nodes and root must point to valid nodes of the same tree
     pub unsafe fn exchange(&mut self, lower: NodePtr<T>, root: *mut *mut Node<T>) {
         swap(&mut (*self.0).left, &mut (*lower.0).left);
         if lower.left() == lower {
@@ -275,6 +278,9 @@ impl<T> NodePtr<T> {
         }
     }
 
+    /// # Safety
+    ///
+    /// the node must be valid and own more labels than parent_label_count
     pub unsafe fn split_to_parent(&mut self, parent_label_count: usize) -> NodePtr<T> {
         let name = &mut (*self.0).name;
         let parent = name
@@ -285,8 +291,16 @@ impl<T> NodePtr<T> {
 }
 
 impl<T: Clone> NodePtr<T> {
+    /// # Safety
+    ///
+    /// the node and all the nodes below it must be valid
     pub unsafe fn deep_clone(self) -> NodePtr<T> {
         let node = NodePtr::new((*self.0).name.clone(), (*self.0).value.clone());
+        (*node.0).flag = (*self.0).flag;
+        if !self.down().is_null() {
+            node.set_down(self.down().deep_clone());
+            node.down().set_parent(node);
+        }
         if !self.left().is_null() {
             node.set_left(self.left().deep_clone());
             node.left().set_parent(node);
@@ -50,8 +50,8 @@ impl<'a, T> NodeChain<'a, T> {
             child.concat_all(&[]).expect("get absolute name failed")
         } else {
             let mut names = [self.nodes[self.level_count - 1].get_name(); MAX_LABEL_COUNT as usize];
-            for i in 1..self.level_count {
-                names[i] = self.nodes[self.level_count - i - 1].get_name();
+            for (i, name) in names.iter_mut().enumerate().take(self.level_count).skip(1) {
+                *name = self.nodes[self.level_count - i - 1].get_name();
             }
             child
                 .concat_all(&names[0..self.level_count])
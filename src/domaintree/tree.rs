@@ -68,7 +68,9 @@ impl<T: Clone> Clone for DomainTree<T> {
     fn clone(&self) -> DomainTree<T> {
         unsafe {
             let mut new = DomainTree::new();
-            new.root = self.root.deep_clone();
+            if !self.root.is_null() {
+                new.root = self.root.deep_clone();
+            }
             new.len = self.len;
             new
         }
@@ -274,12 +276,16 @@ impl<T> DomainTree<T> {
         self.len += 1;
     }
 
-    pub fn find(&self, target: &Name) -> FindResult<T> {
+    pub fn find(&self, target: &Name) -> FindResult<'_, T> {
         let mut node_chain = NodeChain::new(self);
         self.find_node(target, &mut node_chain)
     }
 
-    pub fn find_node<'a>(&'a self, target_: &Name, chain: &mut NodeChain<'a, T>) -> FindResult<T> {
+    pub fn find_node<'a>(
+        &'a self,
+        target_: &Name,
+        chain: &mut NodeChain<'a, T>,
+    ) -> FindResult<'a, T> {
         self.find_node_ext(
             target_,
             chain,
@@ -294,7 +300,7 @@ impl<T> DomainTree<T> {
         chain: &mut NodeChain<'a, T>,
         callback: &mut Option<F>,
         param: &mut P,
-    ) -> FindResult<T> {
+    ) -> FindResult<'a, T> {
         let mut node = self.root;
         let mut result = FindResult::new(self);
         let mut target_slice = LabelSlice::from_name(target);
@@ -342,6 +348,32 @@ impl<T> DomainTree<T> {
         result
     }
 
+    //visit nodes with value, parent comes before its children
+    pub fn for_each<'a, F: FnMut(Name, &'a T)>(&'a self, mut f: F) {
+        let mut chain = NodeChain::new(self);
+        self.for_each_helper(self.root, &mut chain, &mut f);
+    }
+
+    fn for_each_helper<'a, F: FnMut(Name, &'a T)>(
+        &'a self,
+        node: NodePtr<T>,
+        chain: &mut NodeChain<'a, T>,
+        f: &mut F,
+    ) {
+        if node.is_null() {
+            return;
+        }
+
+        self.for_each_helper(node.left(), chain, f);
+        if let Some(v) = unsafe { (*node.0).value.as_ref() } {
+            f(chain.get_absolute_name(node.get_name()), v);
+        }
+        chain.push(node);
+        self.for_each_helper(node.down(), chain, f);
+        chain.pop();
+        self.for_each_helper(node.right(), chain, f);
+    }
+
     pub fn remove(&mut self, name: &Name) -> Option<T> {
         let node = {
             let result = self.find(name);
@@ -493,7 +525,7 @@ impl<T> DomainTree<T> {
                 self.clear_recurse(current.left());
                 self.clear_recurse(current.right());
                 self.clear_recurse(current.down());
-                Box::from_raw(current.0);
+                drop(Box::from_raw(current.0));
             }
         }
     }
@@ -527,7 +559,7 @@ impl<T> DomainTree<T> {
             }
         }
 
-        print!("{} ({:?})", node.get_name().to_string(), node.get_color());
+        print!("{} ({:?})", node.get_name(), node.get_color());
         if node.get_value().is_none() {
             print!("[invisible]");
         }
@@ -539,10 +571,10 @@ impl<T> DomainTree<T> {
         let down = node.down();
         if !down.is_null() {
             indent(depth + 1);
-            println!("begin down from {}\n", down.get_name().to_string());
+            println!("begin down from {}\n", down.get_name());
             self.dump_helper(down, depth + 1);
             indent(depth + 1);
-            println!("end down from {}", down.get_name().to_string());
+            println!("end down from {}", down.get_name());
         }
         self.dump_helper(node.left(), depth + 1);
         self.dump_helper(node.right(), depth + 1);
@@ -551,7 +583,7 @@ impl<T> DomainTree<T> {
 
 fn indent(depth: usize) {
     const INDENT_FOR_EACH_DEPTH: usize = 5;
-    print!("{}", " ".repeat((depth * INDENT_FOR_EACH_DEPTH) as usize));
+    print!("{}", " ".repeat(depth * INDENT_FOR_EACH_DEPTH));
 }
 
 #[cfg(test)]
@@ -560,7 +592,7 @@ mod tests {
     use crate::name::Name;
 
     fn sample_names() -> Vec<(&'static str, i32)> {
-        vec![
+        [
             "c",
             "b",
             "a",
@@ -644,7 +676,7 @@ mod tests {
         let num = Rc::new(Cell::new(0));
         {
             let mut tree = DomainTree::new();
-            for name in vec!["a", "b", "c", "d"] {
+            for name in ["a", "b", "c", "d"] {
                 tree.insert(
                     Name::new(name).unwrap(),
                     Some(NumberWrapper::new(num.clone())),
@@ -658,7 +690,7 @@ mod tests {
     #[test]
     fn test_callback() {
         let mut tree = DomainTree::new();
-        for name in vec!["a", "b", "c", "d"] {
+        for name in ["a", "b", "c", "d"] {
             tree.insert(Name::new(name).unwrap(), Some(10));
         }
         let (n, _) = tree.insert(Name::new("e").unwrap(), Some(20));
@@ -668,7 +700,7 @@ mod tests {
         let mut num = 0;
         let callback = |n: NodePtr<u32>, name: Name, num: &mut u32| {
             assert_eq!(name.to_string(), "e.");
-            *num = *num + n.get_value().unwrap();
+            *num += n.get_value().unwrap();
             false
         };
         let mut node_chain = NodeChain::new(&tree);
@@ -694,7 +726,7 @@ mod tests {
 
         //callback return true, skip travel
         let callback = |n: NodePtr<u32>, _, num: &mut u32| {
-            *num = *num + n.get_value().unwrap();
+            *num += n.get_value().unwrap();
             true
         };
         let mut node_chain = NodeChain::new(&tree);
@@ -708,6 +740,34 @@ mod tests {
         assert_eq!(result.flag, FindResultFlag::PartialMatch);
     }
 
+    #[test]
+    fn test_for_each_and_clone() {
+        let tree = build_tree(&sample_names());
+        let mut names = Vec::new();
+        tree.clone()
+            .for_each(|name, v| names.push((name.to_string(), *v)));
+        assert_eq!(
+            names,
+            vec![
+                ("a.", 2),
+                ("b.", 1),
+                ("c.", 0),
+                ("x.d.e.f.", 3),
+                ("o.w.y.d.e.f.", 7),
+                ("p.w.y.d.e.f.", 9),
+                ("q.w.y.d.e.f.", 10),
+                ("z.d.e.f.", 4),
+                ("j.z.d.e.f.", 8),
+                ("g.h.", 5),
+                ("i.g.h.", 6),
+            ]
+            .into_iter()
+            .map(|(n, v)| (n.to_string(), v))
+            .collect::<Vec<_>>()
+        );
+        assert_eq!(DomainTree::<i32>::new().clone().len(), 0);
+    }
+
     #[test]
     fn test_rand_tree_insert_and_search() {
         use crate::rand_name_generator::RandNameGenerator;
@@ -751,7 +811,7 @@ mod tests {
         for (i, name) in names.iter().enumerate() {
             if !duplicate_name_index.contains(&i) {
                 let mut node_chain = NodeChain::new(&tree);
-                let result = tree.find_node(&name, &mut node_chain);
+                let result = tree.find_node(name, &mut node_chain);
                 let node = result.node;
                 assert_eq!(tree.remove_node(node).unwrap(), i);
             }
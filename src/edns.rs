@@ -1,17 +1,39 @@
 use crate::message_render::MessageRender;
 use crate::name::root;
+use crate::rdata::RData;
+use crate::rdatas::OPT;
 use crate::rr_class::RRClass;
 use crate::rr_type::RRType;
 use crate::rrset::{RRTtl, RRset};
+use crate::util::{hex::to_hex, InputBuffer};
 use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
-use anyhow::Result;
+use anyhow::{bail, ensure, Result};
 
 const VERSION_SHIFT: u32 = 16;
 const EXTRCODE_SHIFT: u32 = 24;
 const VERSION_MASK: u32 = 0x00ff_0000;
 const EXTFLAG_DO: u32 = 0x0000_8000;
 
+pub const OPTION_CODE_NSID: u16 = 3;
+pub const OPTION_CODE_CLIENT_SUBNET: u16 = 8;
+pub const OPTION_CODE_EXPIRE: u16 = 9;
+pub const OPTION_CODE_COOKIE: u16 = 10;
+pub const OPTION_CODE_TCP_KEEPALIVE: u16 = 11;
+pub const OPTION_CODE_PADDING: u16 = 12;
+pub const OPTION_CODE_EXTENDED_ERROR: u16 = 15;
+
+//minimum udp payload size, a client with size not bigger than it doesn't
+//use edns, rfc6891 6.2.5
+pub(crate) const MIN_UDP_SIZE: u16 = 512;
+
+const CLIENT_COOKIE_LEN: usize = 8;
+const MIN_SERVER_COOKIE_LEN: usize = 8;
+const MAX_SERVER_COOKIE_LEN: usize = 32;
+const ADDRESS_FAMILY_IPV4: u16 = 1;
+const ADDRESS_FAMILY_IPV6: u16 = 2;
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Edns {
     pub version: u8,
@@ -22,56 +44,398 @@ pub struct Edns {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
-pub struct EdnsOption {}
+pub struct ClientSubnet {
+    pub source_prefix: u8,
+    pub scope_prefix: u8,
+    pub address: IpAddr,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum EdnsOption {
+    Nsid(Vec<u8>),
+    ClientSubnet(ClientSubnet),
+    //rfc7314, request carries no data
+    Expire(Option<u32>),
+    //client cookie is 8 bytes, server cookie is empty or 8 to 32 bytes
+    Cookie { client: Vec<u8>, server: Vec<u8> },
+    //idle timeout in units of 100 milliseconds, request carries no data
+    TcpKeepalive(Option<u16>),
+    //only the length is kept, padding bytes are always zero
+    Padding(u16),
+    ExtendedError { info_code: u16, extra_text: String },
+    Unknown { code: u16, data: Vec<u8> },
+}
+
+impl ClientSubnet {
+    fn family(&self) -> u16 {
+        match self.address {
+            IpAddr::V4(_) => ADDRESS_FAMILY_IPV4,
+            IpAddr::V6(_) => ADDRESS_FAMILY_IPV6,
+        }
+    }
+
+    fn address_len(&self) -> usize {
+        (self.source_prefix as usize).div_ceil(8)
+    }
+
+    fn from_wire(buf: &mut InputBuffer, len: u16) -> Result<Self> {
+        ensure!(len >= 4, "client subnet option is too short");
+        let family = buf.read_u16()?;
+        let source_prefix = buf.read_u8()?;
+        let scope_prefix = buf.read_u8()?;
+        let addr_len = (len - 4) as usize;
+        ensure!(
+            addr_len == (source_prefix as usize).div_ceil(8),
+            "client subnet address doesn't match source prefix"
+        );
+        let data = buf.read_bytes(addr_len)?;
+        let address = match family {
+            ADDRESS_FAMILY_IPV4 => {
+                check_prefix(32, source_prefix, scope_prefix)?;
+                let mut octs = [0; 4];
+                octs[..addr_len].copy_from_slice(data);
+                IpAddr::V4(Ipv4Addr::from(octs))
+            }
+            ADDRESS_FAMILY_IPV6 => {
+                check_prefix(128, source_prefix, scope_prefix)?;
+                let mut octs = [0; 16];
+                octs[..addr_len].copy_from_slice(data);
+                IpAddr::V6(Ipv6Addr::from(octs))
+            }
+            _ => bail!("unknown client subnet family {}", family),
+        };
+        Ok(ClientSubnet {
+            source_prefix,
+            scope_prefix,
+            address,
+        })
+    }
+
+    fn to_wire(&self, render: &mut MessageRender) -> Result<()> {
+        let max_prefix = match self.address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        check_prefix(max_prefix, self.source_prefix, self.scope_prefix)?;
+        render.write_u16(self.family())?;
+        render.write_u8(self.source_prefix)?;
+        render.write_u8(self.scope_prefix)?;
+        let len = self.address_len();
+        match self.address {
+            IpAddr::V4(addr) => render.write_bytes(&addr.octets()[..len]),
+            IpAddr::V6(addr) => render.write_bytes(&addr.octets()[..len]),
+        }
+    }
+}
+
+fn check_prefix(max_prefix: u8, source_prefix: u8, scope_prefix: u8) -> Result<()> {
+    ensure!(
+        source_prefix <= max_prefix && scope_prefix <= max_prefix,
+        "prefix length is bigger than {}",
+        max_prefix
+    );
+    Ok(())
+}
+
+impl fmt::Display for ClientSubnet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}/{}",
+            self.address, self.source_prefix, self.scope_prefix
+        )
+    }
+}
+
+impl EdnsOption {
+    pub fn code(&self) -> u16 {
+        match *self {
+            EdnsOption::Nsid(_) => OPTION_CODE_NSID,
+            EdnsOption::ClientSubnet(_) => OPTION_CODE_CLIENT_SUBNET,
+            EdnsOption::Expire(_) => OPTION_CODE_EXPIRE,
+            EdnsOption::Cookie { .. } => OPTION_CODE_COOKIE,
+            EdnsOption::TcpKeepalive(_) => OPTION_CODE_TCP_KEEPALIVE,
+            EdnsOption::Padding(_) => OPTION_CODE_PADDING,
+            EdnsOption::ExtendedError { .. } => OPTION_CODE_EXTENDED_ERROR,
+            EdnsOption::Unknown { code, .. } => code,
+        }
+    }
+
+    pub fn data_len(&self) -> usize {
+        match *self {
+            EdnsOption::Nsid(ref data) => data.len(),
+            EdnsOption::ClientSubnet(ref subnet) => 4 + subnet.address_len(),
+            EdnsOption::Expire(expire) => expire.map_or(0, |_| 4),
+            EdnsOption::Cookie {
+                ref client,
+                ref server,
+            } => client.len() + server.len(),
+            EdnsOption::TcpKeepalive(timeout) => timeout.map_or(0, |_| 2),
+            EdnsOption::Padding(len) => len as usize,
+            EdnsOption::ExtendedError { ref extra_text, .. } => 2 + extra_text.len(),
+            EdnsOption::Unknown { ref data, .. } => data.len(),
+        }
+    }
+
+    //option code and option length take 4 bytes
+    pub fn len(&self) -> usize {
+        4 + self.data_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data_len() == 0
+    }
+
+    pub fn from_wire(buf: &mut InputBuffer) -> Result<Self> {
+        let code = buf.read_u16()?;
+        let len = buf.read_u16()?;
+        let pos = buf.position();
+        let option = match code {
+            OPTION_CODE_NSID => EdnsOption::Nsid(buf.read_bytes(len as usize)?.to_vec()),
+            OPTION_CODE_CLIENT_SUBNET => {
+                EdnsOption::ClientSubnet(ClientSubnet::from_wire(buf, len)?)
+            }
+            OPTION_CODE_EXPIRE => match len {
+                0 => EdnsOption::Expire(None),
+                4 => EdnsOption::Expire(Some(buf.read_u32()?)),
+                _ => bail!("expire option should have 0 or 4 bytes"),
+            },
+            OPTION_CODE_COOKIE => {
+                let len = len as usize;
+                ensure!(
+                    len == CLIENT_COOKIE_LEN
+                        || (CLIENT_COOKIE_LEN + MIN_SERVER_COOKIE_LEN
+                            ..=CLIENT_COOKIE_LEN + MAX_SERVER_COOKIE_LEN)
+                            .contains(&len),
+                    "invalid cookie length {}",
+                    len
+                );
+                let client = buf.read_bytes(CLIENT_COOKIE_LEN)?.to_vec();
+                let server = buf.read_bytes(len - CLIENT_COOKIE_LEN)?.to_vec();
+                EdnsOption::Cookie { client, server }
+            }
+            OPTION_CODE_TCP_KEEPALIVE => match len {
+                0 => EdnsOption::TcpKeepalive(None),
+                2 => EdnsOption::TcpKeepalive(Some(buf.read_u16()?)),
+                _ => bail!("tcp keepalive option should have 0 or 2 bytes"),
+            },
+            OPTION_CODE_PADDING => {
+                buf.read_bytes(len as usize)?;
+                EdnsOption::Padding(len)
+            }
+            OPTION_CODE_EXTENDED_ERROR => {
+                ensure!(len >= 2, "extended error option is too short");
+                let info_code = buf.read_u16()?;
+                let text = buf.read_bytes((len - 2) as usize)?;
+                EdnsOption::ExtendedError {
+                    info_code,
+                    extra_text: String::from_utf8_lossy(text).into_owned(),
+                }
+            }
+            _ => EdnsOption::Unknown {
+                code,
+                data: buf.read_bytes(len as usize)?.to_vec(),
+            },
+        };
+        ensure!(
+            buf.position() - pos == len as usize,
+            "option {} has extra data",
+            code
+        );
+        Ok(option)
+    }
+
+    pub fn to_wire(&self, render: &mut MessageRender) -> Result<()> {
+        render.write_u16(self.code())?;
+        render.write_u16(self.data_len() as u16)?;
+        match *self {
+            EdnsOption::Nsid(ref data) => render.write_bytes(data),
+            EdnsOption::ClientSubnet(ref subnet) => subnet.to_wire(render),
+            EdnsOption::Expire(expire) => expire.map_or(Ok(()), |e| render.write_u32(e)),
+            EdnsOption::Cookie {
+                ref client,
+                ref server,
+            } => {
+                render.write_bytes(client)?;
+                render.write_bytes(server)
+            }
+            EdnsOption::TcpKeepalive(timeout) => timeout.map_or(Ok(()), |t| render.write_u16(t)),
+            EdnsOption::Padding(len) => render.write_bytes(&vec![0; len as usize]),
+            EdnsOption::ExtendedError {
+                info_code,
+                ref extra_text,
+            } => {
+                render.write_u16(info_code)?;
+                render.write_bytes(extra_text.as_bytes())
+            }
+            EdnsOption::Unknown { ref data, .. } => render.write_bytes(data),
+        }
+    }
+}
+
+fn extended_error_purpose(info_code: u16) -> &'static str {
+    match info_code {
+        0 => "Other",
+        1 => "Unsupported DNSKEY Algorithm",
+        2 => "Unsupported DS Digest Type",
+        3 => "Stale Answer",
+        4 => "Forged Answer",
+        5 => "DNSSEC Indeterminate",
+        6 => "DNSSEC Bogus",
+        7 => "Signature Expired",
+        8 => "Signature Not Yet Valid",
+        9 => "DNSKEY Missing",
+        10 => "RRSIGs Missing",
+        11 => "No Zone Key Bit Set",
+        12 => "NSEC Missing",
+        13 => "Cached Error",
+        14 => "Not Ready",
+        15 => "Blocked",
+        16 => "Censored",
+        17 => "Filtered",
+        18 => "Prohibited",
+        19 => "Stale NXDOMAIN Answer",
+        20 => "Not Authoritative",
+        21 => "Not Supported",
+        22 => "No Reachable Authority",
+        23 => "Network Error",
+        24 => "Invalid Data",
+        _ => "Unknown",
+    }
+}
+
+impl fmt::Display for EdnsOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EdnsOption::Nsid(ref data) => write!(
+                f,
+                "; NSID: {} (\"{}\")",
+                to_hex(data),
+                String::from_utf8_lossy(data)
+            ),
+            EdnsOption::ClientSubnet(ref subnet) => write!(f, "; CLIENT-SUBNET: {}", subnet),
+            EdnsOption::Expire(None) => write!(f, "; EXPIRE:"),
+            EdnsOption::Expire(Some(expire)) => write!(f, "; EXPIRE: {}", expire),
+            EdnsOption::Cookie {
+                ref client,
+                ref server,
+            } => write!(f, "; COOKIE: {}{}", to_hex(client), to_hex(server)),
+            EdnsOption::TcpKeepalive(None) => write!(f, "; TCP-KEEPALIVE:"),
+            EdnsOption::TcpKeepalive(Some(timeout)) => {
+                write!(f, "; TCP-KEEPALIVE: {}.{} secs", timeout / 10, timeout % 10)
+            }
+            EdnsOption::Padding(len) => write!(f, "; PADDING: {} bytes", len),
+            EdnsOption::ExtendedError {
+                info_code,
+                ref extra_text,
+            } => {
+                write!(
+                    f,
+                    "; EDE: {} ({})",
+                    info_code,
+                    extended_error_purpose(info_code)
+                )?;
+                if !extra_text.is_empty() {
+                    write!(f, ": ({})", extra_text)?;
+                }
+                Ok(())
+            }
+            EdnsOption::Unknown { code, ref data } => {
+                write!(f, "; OPT={}: {}", code, to_hex(data))
+            }
+        }
+    }
+}
 
 impl Edns {
-    pub fn from_rrset(rrset: &RRset) -> Edns {
+    pub fn from_rrset(rrset: &RRset) -> Result<Edns> {
         assert!(rrset.typ == RRType::OPT);
 
         let flags = rrset.ttl.0;
-        Edns {
+        let options = match rrset.rdatas.first() {
+            Some(RData::OPT(ref opt)) if !opt.data.is_empty() => {
+                let mut buf = InputBuffer::new(opt.data.as_slice());
+                let mut options = Vec::new();
+                while buf.position() < buf.len() {
+                    options.push(EdnsOption::from_wire(&mut buf)?);
+                }
+                Some(options)
+            }
+            _ => None,
+        };
+
+        Ok(Edns {
             version: ((flags & VERSION_MASK) >> VERSION_SHIFT) as u8,
             udp_size: rrset.class.to_u16(),
             extened_rcode: (flags >> EXTRCODE_SHIFT) as u8,
             dnssec_aware: (flags & EXTFLAG_DO) != 0,
-            options: None,
+            options,
+        })
+    }
+
+    fn flags(&self) -> u32 {
+        let mut flags = u32::from(self.extened_rcode) << EXTRCODE_SHIFT;
+        flags |= (u32::from(self.version) << VERSION_SHIFT) & VERSION_MASK;
+        if self.dnssec_aware {
+            flags |= EXTFLAG_DO;
         }
+        flags
+    }
+
+    fn options_len(&self) -> usize {
+        self.options.as_ref().map_or(0, |options| {
+            options.iter().fold(0, |len, option| len + option.len())
+        })
     }
 
     pub fn to_rrset(self) -> RRset {
-        let mut flags = (self.extened_rcode as u32) << EXTRCODE_SHIFT;
-        flags |= ((self.version as u32) << VERSION_SHIFT) & VERSION_MASK;
-        if self.dnssec_aware {
-            flags |= EXTFLAG_DO as u32;
+        let mut rdatas = Vec::new();
+        let options_len = self.options_len();
+        if options_len > 0 {
+            let mut data = vec![0; options_len];
+            let mut render = MessageRender::new(&mut data);
+            for option in self.options.as_ref().unwrap() {
+                option
+                    .to_wire(&mut render)
+                    .expect("buffer is large enough for edns options");
+            }
+            rdatas.push(RData::OPT(Box::new(OPT { data })));
         }
-        return RRset {
+
+        RRset {
             name: root(),
             typ: RRType::OPT,
             class: RRClass::new(self.udp_size),
-            ttl: RRTtl(flags),
-            rdatas: Vec::new(),
-        };
+            ttl: RRTtl(self.flags()),
+            rdatas,
+        }
     }
 
     pub fn to_wire(&self, render: &mut MessageRender) -> Result<()> {
-        let mut flags = u32::from(self.extened_rcode) << EXTRCODE_SHIFT;
-        flags |= (u32::from(self.version) << VERSION_SHIFT) & VERSION_MASK;
-        if self.dnssec_aware {
-            flags |= EXTFLAG_DO;
-        }
-
         render.write_u8(0)?;
         RRType::OPT.to_wire(render)?;
         RRClass::Unknown(self.udp_size).to_wire(render)?;
-        RRTtl(flags).to_wire(render)?;
-        render.write_u16(0)
+        RRTtl(self.flags()).to_wire(render)?;
+        let pos = render.len();
+        render.write_u16(0)?;
+        if let Some(ref options) = self.options {
+            for option in options {
+                option.to_wire(render)?;
+            }
+        }
+        let rdlen = render.len() - pos - 2;
+        render.write_u16_at(pos, rdlen as u16)
     }
 
+    //opt record always occupies one rr in additional section
     pub fn rr_count(&self) -> usize {
-        match self.options {
-            Some(ref options) => options.len(),
-            None => 1,
-        }
+        1
+    }
+
+    pub fn get_option(&self, code: u16) -> Option<&EdnsOption> {
+        self.options
+            .as_ref()
+            .and_then(|options| options.iter().find(|option| option.code() == code))
     }
 }
 
@@ -81,21 +445,30 @@ impl fmt::Display for Edns {
         if self.dnssec_aware {
             write!(f, "flags: do; ")?;
         }
-        write!(f, "udp: {}", self.udp_size)
+        write!(f, "udp: {}", self.udp_size)?;
+        if let Some(ref options) = self.options {
+            for option in options {
+                write!(f, "\n{}", option)?;
+            }
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::response::{Response, SectionType};
+    use crate::response_builder::ResponseBuilder;
     use crate::util::{hex::from_hex, InputBuffer};
+    use crate::{Name, RRType};
 
     #[test]
     fn test_edns_to_wire() {
         let raw = from_hex("0000291000000000000000").unwrap();
         let mut buf = InputBuffer::new(raw.as_slice());
         let rrset = RRset::from_wire(&mut buf).unwrap();
-        let edns = Edns::from_rrset(&rrset);
+        let edns = Edns::from_rrset(&rrset).unwrap();
         let desired_edns = Edns {
             version: 0,
             extened_rcode: 0,
@@ -110,4 +483,133 @@ mod test {
         desired_edns.to_wire(&mut render).unwrap();
         assert_eq!(raw.as_slice(), &buf[0..(raw.len())]);
     }
+
+    #[test]
+    fn test_edns_option_from_wire() {
+        //nsid "gpdns", ecs 1.2.3.0/24/0, cookie, keepalive, padding 4, ede 18, expire
+        let raw = from_hex(
+            "000029100000008000004e000300056770646e73\
+             0008000700011800010203\
+             000a0010000102030405060708090a0b0c0d0e0f\
+             000b00020064\
+             000c000400000000\
+             000f000c001270726f68696269746564\
+             0009000400000e10",
+        )
+        .unwrap();
+        let mut buf = InputBuffer::new(raw.as_slice());
+        let rrset = RRset::from_wire(&mut buf).unwrap();
+        let edns = Edns::from_rrset(&rrset).unwrap();
+        assert!(edns.dnssec_aware);
+        assert_eq!(
+            edns.options.as_ref().unwrap(),
+            &vec![
+                EdnsOption::Nsid(b"gpdns".to_vec()),
+                EdnsOption::ClientSubnet(ClientSubnet {
+                    source_prefix: 24,
+                    scope_prefix: 0,
+                    address: "1.2.3.0".parse().unwrap(),
+                }),
+                EdnsOption::Cookie {
+                    client: vec![0, 1, 2, 3, 4, 5, 6, 7],
+                    server: vec![8, 9, 10, 11, 12, 13, 14, 15],
+                },
+                EdnsOption::TcpKeepalive(Some(100)),
+                EdnsOption::Padding(4),
+                EdnsOption::ExtendedError {
+                    info_code: 18,
+                    extra_text: "prohibited".to_string(),
+                },
+                EdnsOption::Expire(Some(3600)),
+            ]
+        );
+
+        let mut buf = [0; 512];
+        let mut render = MessageRender::new(&mut buf);
+        edns.to_wire(&mut render).unwrap();
+        assert_eq!(raw.as_slice(), render.data());
+
+        let mut render = MessageRender::new(&mut buf);
+        edns.clone().to_rrset().to_wire(&mut render).unwrap();
+        assert_eq!(raw.as_slice(), render.data());
+
+        //unknown option is kept as is
+        let raw = from_hex("0000291000000000000006fde9000201ff").unwrap();
+        let rrset = RRset::from_wire(&mut InputBuffer::new(raw.as_slice())).unwrap();
+        let edns = Edns::from_rrset(&rrset).unwrap();
+        assert_eq!(
+            edns.get_option(65001),
+            Some(&EdnsOption::Unknown {
+                code: 65001,
+                data: vec![1, 255]
+            })
+        );
+
+        //cookie with invalid length
+        let raw = from_hex("0000291000000000000008000a000400010203").unwrap();
+        let rrset = RRset::from_wire(&mut InputBuffer::new(raw.as_slice())).unwrap();
+        assert!(Edns::from_rrset(&rrset).is_err());
+
+        //ecs address longer than source prefix
+        let raw = from_hex("000029100000000000000c0008000800011800010203ff").unwrap();
+        let rrset = RRset::from_wire(&mut InputBuffer::new(raw.as_slice())).unwrap();
+        assert!(Edns::from_rrset(&rrset).is_err());
+
+        //prefix longer than address can't be rendered
+        let edns = Edns {
+            version: 0,
+            extened_rcode: 0,
+            udp_size: 4096,
+            dnssec_aware: false,
+            options: Some(vec![EdnsOption::ClientSubnet(ClientSubnet {
+                source_prefix: 40,
+                scope_prefix: 0,
+                address: "1.2.3.0".parse().unwrap(),
+            })]),
+        };
+        let mut render = MessageRender::new(&mut buf);
+        assert!(edns.to_wire(&mut render).is_err());
+    }
+
+    #[test]
+    fn test_edns_option_in_response() {
+        let mut resp = Response::with_question(Name::new("example.com").unwrap(), RRType::A);
+        let edns = Edns {
+            version: 0,
+            extened_rcode: 0,
+            udp_size: 1232,
+            dnssec_aware: false,
+            options: Some(vec![
+                EdnsOption::Nsid(b"ns1".to_vec()),
+                EdnsOption::ClientSubnet(ClientSubnet {
+                    source_prefix: 56,
+                    scope_prefix: 48,
+                    address: "2001:db8:1:200::".parse().unwrap(),
+                }),
+                EdnsOption::ExtendedError {
+                    info_code: 6,
+                    extra_text: String::new(),
+                },
+                EdnsOption::TcpKeepalive(None),
+            ]),
+        };
+        ResponseBuilder::new(&mut resp)
+            .edns(edns.clone())
+            .make_response()
+            .done();
+        assert_eq!(resp.header.ar_count, 1);
+
+        let mut buf = [0; 512];
+        let mut render = MessageRender::new(&mut buf);
+        resp.to_wire(&mut render).unwrap();
+        let resp2 = Response::from_wire(render.data()).unwrap();
+        assert_eq!(resp2.get_edns().unwrap(), edns);
+        assert_eq!(resp2.section_rrset_count(SectionType::Additional), 1);
+
+        let display = resp2.to_string();
+        assert!(display.contains("; NSID: 6e7331 (\"ns1\")"));
+        assert!(display.contains("; CLIENT-SUBNET: 2001:db8:1:200::/56/48"));
+        assert!(display.contains("; EDE: 6 (DNSSEC Bogus)"));
+        assert!(display.contains("; TCP-KEEPALIVE:"));
+    }
 }
@@ -0,0 +1,50 @@
+use crate::tsig::TsigError;
+use std::fmt;
+
+//functions return anyhow::Error like everywhere else, failures which
+//caller may need to tell apart are created from this type, so they could be
+//got back by downcast, other failures are plain messages from bail!
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Error {
+    //client
+    NoServer,
+    Timeout,
+    //recursor
+    TooManyQueries,
+    TooDeep,
+    Loop,
+    //tsig verification
+    Tsig(TsigError),
+    //dnssec signature and denial verification
+    UnsupportedAlgorithm(u8),
+    InvalidKey,
+    KeyMismatch,
+    RRsetMismatch,
+    SignatureNotIncepted,
+    SignatureExpired,
+    BadSignature,
+    NoDenialProof,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NoServer => f.write_str("no server to query"),
+            Error::Timeout => f.write_str("query timeout"),
+            Error::TooManyQueries => f.write_str("too many queries"),
+            Error::TooDeep => f.write_str("resolving is too deep"),
+            Error::Loop => f.write_str("resolving loops"),
+            Error::Tsig(err) => write!(f, "tsig error {}", err),
+            Error::UnsupportedAlgorithm(alg) => write!(f, "algorithm {} isn't supported", alg),
+            Error::InvalidKey => f.write_str("dnskey is malformed"),
+            Error::KeyMismatch => f.write_str("dnskey doesn't match rrsig"),
+            Error::RRsetMismatch => f.write_str("rrsig doesn't cover the rrset"),
+            Error::SignatureNotIncepted => f.write_str("rrsig isn't valid yet"),
+            Error::SignatureExpired => f.write_str("rrsig has expired"),
+            Error::BadSignature => f.write_str("signature is bogus"),
+            Error::NoDenialProof => f.write_str("denial of existence isn't proved"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
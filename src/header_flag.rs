@@ -39,7 +39,7 @@ impl HeaderFlag {
 
 impl fmt::Display for HeaderFlag {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(&self.to_str())
+        f.write_str(self.to_str())
     }
 }
 
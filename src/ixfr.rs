@@ -0,0 +1,589 @@
+use crate::axfr::{render_transfer, request_transfer, split_rrset, DEFAULT_MESSAGE_LEN};
+use crate::header_flag::HeaderFlag;
+use crate::message::{Message, MAX_MESSAGE_LEN};
+use crate::message_builder::MessageBuilder;
+use crate::rcode::Rcode;
+use crate::response::SectionType;
+use crate::rr_type::RRType;
+use crate::rrset::RRset;
+use crate::tsig::TsigKey;
+use crate::util::tcp::write_message;
+use crate::zone::{group_rrs, serial_gt, soa_serial, ChangeSet, Zone};
+use anyhow::{bail, ensure, Result};
+use std::io::{Read, Write};
+
+//history of a zone as continuous changes, each one starts from the serial
+//where the previous one ends
+#[derive(Debug, Clone, Default)]
+pub struct Journal {
+    changes: Vec<ChangeSet>,
+}
+
+impl Journal {
+    pub fn new() -> Self {
+        Journal {
+            changes: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    //serial of the oldest version which can be transferred incrementally
+    pub fn first_serial(&self) -> Option<u32> {
+        self.changes.first().map(|changes| changes.old_serial())
+    }
+
+    pub fn last_serial(&self) -> Option<u32> {
+        self.changes.last().map(|changes| changes.new_serial())
+    }
+
+    //empty changes are ignored
+    pub fn append(&mut self, changes: ChangeSet) -> Result<()> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        ensure!(
+            serial_gt(changes.new_serial(), changes.old_serial()),
+            "serial of changes doesn't increase"
+        );
+        if let Some(last) = self.last_serial() {
+            ensure!(
+                last == changes.old_serial(),
+                "journal ends at {} but changes start from {}",
+                last,
+                changes.old_serial()
+            );
+        }
+        self.changes.push(changes);
+        Ok(())
+    }
+
+    //none means the history from serial isn't available
+    pub fn changes_since(&self, serial: u32) -> Option<&[ChangeSet]> {
+        if self.last_serial() == Some(serial) {
+            return Some(&[]);
+        }
+        self.changes
+            .iter()
+            .position(|changes| changes.old_serial() == serial)
+            .map(|pos| &self.changes[pos..])
+    }
+
+    //merge all the changes into one, after that only the oldest version
+    //can be transferred incrementally
+    pub fn condense(&mut self) -> Result<()> {
+        let mut changes = std::mem::take(&mut self.changes).into_iter();
+        if let Some(first) = changes.next() {
+            let condensed = changes.try_fold(first, |merged, next| merged.merge(&next))?;
+            self.changes = vec![condensed];
+        }
+        Ok(())
+    }
+
+    //drop the oldest changes to keep at most max_len of them
+    pub fn truncate(&mut self, max_len: usize) {
+        if self.changes.len() > max_len {
+            self.changes.drain(..self.changes.len() - max_len);
+        }
+    }
+}
+
+//answer ixfr with difference sequences from the journal, rfc1995 4, or the
+//whole zone when the history from client serial isn't available
+pub struct IxfrSender<'a> {
+    zone: &'a Zone,
+    journal: &'a Journal,
+    max_len: usize,
+    tsig: Option<(&'a TsigKey, Vec<u8>)>,
+}
+
+impl<'a> IxfrSender<'a> {
+    pub fn new(zone: &'a Zone, journal: &'a Journal) -> Self {
+        IxfrSender {
+            zone,
+            journal,
+            max_len: DEFAULT_MESSAGE_LEN,
+            tsig: None,
+        }
+    }
+
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len.min(MAX_MESSAGE_LEN);
+        self
+    }
+
+    pub fn tsig(mut self, key: &'a TsigKey, request_mac: &[u8]) -> Self {
+        self.tsig = Some((key, request_mac.to_vec()));
+        self
+    }
+
+    pub fn messages(&self, req: &Message, now: u64) -> Result<Vec<Vec<u8>>> {
+        let question = match req.question() {
+            Some(question) => question,
+            None => bail!("ixfr request has no question"),
+        };
+        ensure!(
+            question.typ == RRType::IXFR && question.name.eq(self.zone.origin()),
+            "request isn't ixfr of zone {}",
+            self.zone.origin()
+        );
+        let client_serial = match req
+            .section(SectionType::Authority)
+            .and_then(|rrsets| rrsets.iter().find(|rrset| rrset.typ == RRType::SOA))
+        {
+            Some(soa) => soa_serial(soa),
+            None => bail!("ixfr request has no soa"),
+        };
+        let soa = match self.zone.soa() {
+            Some(soa) => soa,
+            None => bail!("zone {} has no soa", self.zone.origin()),
+        };
+
+        let mut records = vec![soa];
+        if serial_gt(soa_serial(soa), client_serial) {
+            match self
+                .journal
+                .changes_since(client_serial)
+                .filter(|_| self.journal.last_serial() == Some(soa_serial(soa)))
+            {
+                Some(changes) => {
+                    for changes in changes {
+                        records.push(&changes.old_soa);
+                        records.extend(changes.deleted.iter());
+                        records.push(&changes.new_soa);
+                        records.extend(changes.added.iter());
+                    }
+                }
+                None => records.extend(self.zone.rrsets().into_iter().skip(1)),
+            }
+            records.push(soa);
+        }
+        render_transfer(
+            req.header.id,
+            question,
+            records.into_iter(),
+            self.max_len,
+            self.tsig.as_ref(),
+            now,
+        )
+    }
+
+    pub fn send<W: Write>(&self, stream: &mut W, req: &Message, now: u64) -> Result<()> {
+        for message in self.messages(req, now)? {
+            write_message(stream, &message)?;
+        }
+        Ok(())
+    }
+}
+
+pub enum IxfrResponse {
+    UpToDate,
+    Incremental(Vec<ChangeSet>),
+    //server falls back to send the whole zone
+    Full(Zone),
+}
+
+//request changes since the current version of zone
+pub struct IxfrReceiver<'a> {
+    zone: &'a Zone,
+    tsig: Option<&'a TsigKey>,
+}
+
+impl<'a> IxfrReceiver<'a> {
+    pub fn new(zone: &'a Zone) -> Self {
+        IxfrReceiver { zone, tsig: None }
+    }
+
+    pub fn tsig(mut self, key: &'a TsigKey) -> Self {
+        self.tsig = Some(key);
+        self
+    }
+
+    //ixfr query carries the soa of the client in authority section
+    pub fn request(&self) -> Result<Message> {
+        let soa = match self.zone.soa() {
+            Some(soa) => soa.clone(),
+            None => bail!("zone {} has no soa", self.zone.origin()),
+        };
+        let mut req = Message::with_query(self.zone.origin().clone(), RRType::IXFR);
+        MessageBuilder::new(&mut req)
+            .clear_flag(HeaderFlag::RecursionDesired)
+            .add_rrset(SectionType::Authority, soa)
+            .done();
+        Ok(req)
+    }
+
+    pub fn receive<S: Read + Write>(&self, stream: &mut S, now: u64) -> Result<IxfrResponse> {
+        let req = self.request()?;
+        let mut transfer = IxfrTransfer::new(self.zone, req.header.id);
+        request_transfer(stream, &req, self.tsig, now, |msg| transfer.consume(msg))?;
+        transfer.finish()
+    }
+}
+
+enum State {
+    //waiting for the first soa
+    Start,
+    //first soa is received, the second rr decides the format
+    Began,
+    //axfr format, the whole zone is sent
+    Full,
+    //expecting the old soa of next sequence or the last soa
+    OldSoa,
+    Deleting,
+    Adding,
+    Done,
+}
+
+struct IxfrTransfer<'a> {
+    zone: &'a Zone,
+    id: u16,
+    serial: u32,
+    state: State,
+    first_soa: Option<RRset>,
+    records: Vec<RRset>,
+    changes: Vec<ChangeSet>,
+}
+
+impl<'a> IxfrTransfer<'a> {
+    fn new(zone: &'a Zone, id: u16) -> Self {
+        IxfrTransfer {
+            zone,
+            id,
+            serial: 0,
+            state: State::Start,
+            first_soa: None,
+            records: Vec::new(),
+            changes: Vec::new(),
+        }
+    }
+
+    //return true when the transfer is done
+    fn consume(&mut self, mut msg: Message) -> Result<bool> {
+        ensure!(
+            msg.header.id == self.id,
+            "response id doesn't match request"
+        );
+        ensure!(
+            msg.header.rcode == Rcode::NoError,
+            "ixfr failed with {}",
+            msg.header.rcode
+        );
+        for question in &msg.questions {
+            ensure!(
+                question.typ == RRType::IXFR && question.name.eq(self.zone.origin()),
+                "question of response doesn't match request"
+            );
+        }
+        let answer = msg.take_section(SectionType::Answer).unwrap_or_default();
+        ensure!(!answer.is_empty(), "ixfr response has no record");
+        for rr in answer.iter().flat_map(split_rrset) {
+            self.consume_rr(rr)?;
+        }
+        //only one soa which isn't newer means client is up to date
+        let client_serial = soa_serial(self.zone.soa().expect("zone has soa"));
+        if matches!(self.state, State::Began) && !serial_gt(self.serial, client_serial) {
+            self.state = State::Done;
+        }
+        Ok(matches!(self.state, State::Done))
+    }
+
+    fn consume_rr(&mut self, rr: RRset) -> Result<()> {
+        let soa = if rr.typ == RRType::SOA {
+            ensure!(
+                rr.name.eq(self.zone.origin()),
+                "soa of {} isn't at zone apex",
+                rr.name
+            );
+            Some(soa_serial(&rr))
+        } else {
+            None
+        };
+        match (&mut self.state, soa) {
+            (State::Done, _) => bail!("record {} after the last soa", rr.name),
+            (State::Start, Some(serial)) => {
+                self.serial = serial;
+                self.first_soa = Some(rr);
+                self.state = State::Began;
+            }
+            (State::Start, None) => bail!("first record isn't soa"),
+            (State::Began, Some(serial)) => {
+                self.state = State::OldSoa;
+                self.start_sequence(rr, serial)?;
+            }
+            (State::Began, None) => {
+                self.records.extend(self.first_soa.take());
+                self.records.push(rr);
+                self.state = State::Full;
+            }
+            (State::Full, Some(serial)) => {
+                ensure!(
+                    serial == self.serial,
+                    "serial changed from {} to {} during transfer",
+                    self.serial,
+                    serial
+                );
+                self.state = State::Done;
+            }
+            (State::Full, None) => self.records.push(rr),
+            (State::OldSoa, Some(serial)) | (State::Adding, Some(serial)) => {
+                self.start_sequence(rr, serial)?;
+            }
+            (State::OldSoa, None) => bail!("difference sequence doesn't start with soa"),
+            (State::Deleting, Some(_)) => {
+                let changes = self.changes.last_mut().unwrap();
+                ensure!(
+                    serial_gt(soa_serial(&rr), changes.old_serial()),
+                    "serial of difference sequence doesn't increase"
+                );
+                changes.new_soa = rr;
+                self.state = State::Adding;
+            }
+            (State::Deleting, None) => self.changes.last_mut().unwrap().deleted.push(rr),
+            (State::Adding, None) => self.changes.last_mut().unwrap().added.push(rr),
+        }
+        Ok(())
+    }
+
+    //soa which is the old soa of next difference sequence, or the last soa
+    //if the previous sequence ends at the serial of the first soa
+    fn start_sequence(&mut self, rr: RRset, serial: u32) -> Result<()> {
+        let expected = match self.changes.last() {
+            Some(changes) => changes.new_serial(),
+            None => soa_serial(self.zone.soa().expect("zone has soa")),
+        };
+        if matches!(self.state, State::Adding) && expected == self.serial {
+            ensure!(
+                serial == self.serial,
+                "last soa doesn't match the first one"
+            );
+            self.state = State::Done;
+            return Ok(());
+        }
+        ensure!(
+            serial == expected,
+            "difference sequence starts from {} instead of {}",
+            serial,
+            expected
+        );
+        self.changes.push(ChangeSet {
+            old_soa: rr.clone(),
+            deleted: Vec::new(),
+            new_soa: rr,
+            added: Vec::new(),
+        });
+        self.state = State::Deleting;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<IxfrResponse> {
+        match self.state {
+            State::Done => {}
+            _ => bail!("ixfr response is incomplete"),
+        }
+        if !self.records.is_empty() {
+            let zone = Zone::from_rrsets(self.zone.origin().clone(), self.records)?;
+            return Ok(IxfrResponse::Full(zone));
+        }
+        if self.changes.is_empty() {
+            return Ok(IxfrResponse::UpToDate);
+        }
+        let changes = self
+            .changes
+            .into_iter()
+            .map(|changes| ChangeSet {
+                deleted: group_rrs(changes.deleted),
+                added: group_rrs(changes.added),
+                ..changes
+            })
+            .collect();
+        Ok(IxfrResponse::Incremental(changes))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::fixture::{build_zone, name, rrset, tsig_key, update, ZONE};
+    use crate::util::tcp::read_message;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    //zone with serial 13 and the journal from serial 10
+    fn build_history() -> (Zone, Journal) {
+        let mut zone = build_zone("example.com.", ZONE);
+        let mut journal = Journal::new();
+        journal
+            .append(
+                update(&mut zone, |b| {
+                    b.delete_rr(rrset("www.example.com. 0 IN A 4.4.4.4"));
+                })
+                .unwrap(),
+            )
+            .unwrap();
+        journal
+            .append(
+                update(&mut zone, |b| {
+                    b.add_rrset(rrset("new.example.com. 300 IN A 5.5.5.5"))
+                        .add_rrset(rrset("www.example.com. 3600 IN A 6.6.6.6"));
+                })
+                .unwrap(),
+            )
+            .unwrap();
+        journal
+            .append(
+                update(&mut zone, |b| {
+                    b.delete_name(name("new.example.com."));
+                })
+                .unwrap(),
+            )
+            .unwrap();
+        (zone, journal)
+    }
+
+    fn transfer(server: &Zone, journal: &Journal, client: &Zone) -> IxfrResponse {
+        let req = IxfrReceiver::new(client).request().unwrap();
+        let messages = IxfrSender::new(server, journal)
+            .max_len(512)
+            .messages(&req, 0)
+            .unwrap();
+        let mut transfer = IxfrTransfer::new(client, req.header.id);
+        for message in &messages {
+            transfer
+                .consume(Message::from_wire(message).unwrap())
+                .unwrap();
+        }
+        transfer.finish().unwrap()
+    }
+
+    fn apply(zone: &Zone, changes: &[ChangeSet]) -> Zone {
+        let mut zone = zone.clone();
+        for changes in changes {
+            zone.apply_changeset(changes).unwrap();
+        }
+        zone
+    }
+
+    #[test]
+    fn test_journal() {
+        let origin = build_zone("example.com.", ZONE);
+        let (zone, mut journal) = build_history();
+        assert_eq!(journal.len(), 3);
+        assert_eq!(journal.first_serial(), Some(10));
+        assert_eq!(journal.last_serial(), Some(13));
+
+        let first = journal.changes_since(10).unwrap()[0].clone();
+        assert!(journal.append(first.clone()).is_err());
+        let mut unchanged = zone.clone();
+        journal
+            .append(
+                update(&mut unchanged, |b| {
+                    b.delete_name(name("nonexist.example.com."));
+                })
+                .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(journal.len(), 3);
+
+        assert_eq!(journal.changes_since(12).unwrap().len(), 1);
+        assert!(journal.changes_since(13).unwrap().is_empty());
+        assert!(journal.changes_since(9).is_none());
+        assert_eq!(
+            apply(&origin, journal.changes_since(10).unwrap()).rrsets(),
+            zone.rrsets()
+        );
+
+        let mut truncated = journal.clone();
+        truncated.truncate(1);
+        assert_eq!(truncated.first_serial(), Some(12));
+        assert!(truncated.changes_since(10).is_none());
+
+        journal.condense().unwrap();
+        assert_eq!(journal.len(), 1);
+        let condensed = &journal.changes_since(10).unwrap()[0];
+        assert_eq!(condensed.new_serial(), 13);
+        assert_eq!(
+            condensed.deleted,
+            vec![rrset("www.example.com. 3600 IN A 4.4.4.4")]
+        );
+        assert_eq!(
+            condensed.added,
+            vec![rrset("www.example.com. 3600 IN A 6.6.6.6")]
+        );
+        assert_eq!(
+            apply(&origin, journal.changes_since(10).unwrap()).rrsets(),
+            zone.rrsets()
+        );
+        assert!(first.merge(&first).is_err());
+    }
+
+    #[test]
+    fn test_ixfr_messages() {
+        let origin = build_zone("example.com.", ZONE);
+        let (zone, journal) = build_history();
+
+        assert!(matches!(
+            transfer(&zone, &journal, &zone),
+            IxfrResponse::UpToDate
+        ));
+
+        match transfer(&zone, &journal, &origin) {
+            IxfrResponse::Incremental(changes) => {
+                assert_eq!(changes.len(), 3);
+                assert_eq!(apply(&origin, &changes).rrsets(), zone.rrsets());
+            }
+            _ => panic!("expect incremental transfer"),
+        }
+
+        //history from serial 10 is lost
+        let mut truncated = journal.clone();
+        truncated.truncate(2);
+        match transfer(&zone, &truncated, &origin) {
+            IxfrResponse::Full(received) => assert_eq!(received.rrsets(), zone.rrsets()),
+            _ => panic!("expect full transfer"),
+        }
+
+        let req = Message::with_query(zone.origin().clone(), RRType::IXFR);
+        assert!(IxfrSender::new(&zone, &journal).messages(&req, 0).is_err());
+    }
+
+    #[test]
+    fn test_ixfr_over_tcp() {
+        let key = tsig_key();
+        let origin = build_zone("example.com.", ZONE);
+        let (zone, journal) = build_history();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server_key = key.clone();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let raw = read_message(&mut stream).unwrap();
+            let req = Message::from_wire(&raw).unwrap();
+            let mac = server_key.verify(&raw, None, 1600000000).unwrap().mac;
+            IxfrSender::new(&zone, &journal)
+                .tsig(&server_key, &mac)
+                .send(&mut stream, &req, 1600000000)
+                .unwrap();
+            zone
+        });
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let response = IxfrReceiver::new(&origin)
+            .tsig(&key)
+            .receive(&mut stream, 1600000000)
+            .unwrap();
+        let zone = server.join().unwrap();
+        match response {
+            IxfrResponse::Incremental(changes) => {
+                assert_eq!(apply(&origin, &changes).rrsets(), zone.rrsets())
+            }
+            _ => panic!("expect incremental transfer"),
+        }
+    }
+}
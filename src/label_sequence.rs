@@ -49,7 +49,7 @@ impl LabelSequence {
     }
 
     pub fn split(&mut self, start_label: usize, label_count: usize) -> Result<LabelSequence> {
-        let max_label_count = self.label_count() as usize;
+        let max_label_count = self.label_count();
         ensure!(
             start_label < max_label_count && label_count > 0,
             "invalide label index"
@@ -75,7 +75,7 @@ impl LabelSequence {
                 *v -= data_offset;
             }
         } else {
-            for (i, v) in (&mut self.offsets).iter_mut().enumerate() {
+            for (i, v) in self.offsets.iter_mut().enumerate() {
                 if i >= start_label {
                     *v -= data_offset;
                 }
@@ -129,13 +129,13 @@ impl LabelSequence {
             "label count exceed limit"
         );
 
-        let mut data = Vec::with_capacity(final_length as usize);
+        let mut data = Vec::with_capacity(final_length);
         data.extend_from_slice(self.data.as_ref());
         suffixes
             .iter()
             .for_each(|suffix| data.extend_from_slice(suffix.data.as_ref()));
 
-        let mut offsets = Vec::with_capacity(final_label_count as usize);
+        let mut offsets = Vec::with_capacity(final_label_count);
         let mut offset_pos: usize = 0;
         for _ in 0..final_label_count {
             offsets.push(offset_pos as u8);
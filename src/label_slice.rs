@@ -14,7 +14,7 @@ pub struct LabelSlice<'a> {
 }
 
 impl<'a> LabelSlice<'a> {
-    pub fn from_name(name: &'a Name) -> LabelSlice {
+    pub fn from_name(name: &'a Name) -> LabelSlice<'a> {
         LabelSlice {
             data: name.raw_data(),
             offsets: name.offsets(),
@@ -23,7 +23,7 @@ impl<'a> LabelSlice<'a> {
         }
     }
 
-    pub fn from_label_sequence(ls: &'a LabelSequence) -> LabelSlice {
+    pub fn from_label_sequence(ls: &'a LabelSequence) -> LabelSlice<'a> {
         LabelSlice {
             data: ls.data(),
             offsets: ls.offsets(),
@@ -102,15 +102,16 @@ impl<'a> LabelSlice<'a> {
             while count > 0 {
                 let mut label1: u8 = self.data[pos1];
                 let mut label2: u8 = other.data[pos2];
-                let chdiff: i8;
+
                 if !case_sensitive {
                     label1 = lower_case(label1 as usize);
                     label2 = lower_case(label2 as usize);
                 }
-                chdiff = (label1) as i8 - (label2) as i8;
+                //octets are compared as unsigned, rfc4034 6.1
+                let chdiff = label1 as i16 - label2 as i16;
                 if chdiff != 0 {
                     return NameComparisonResult {
-                        order: chdiff,
+                        order: chdiff.signum() as i8,
                         common_label_count: nlabels as u8,
                         relation: if nlabels == 0 {
                             NameRelation::None
@@ -170,7 +171,7 @@ impl<'a> fmt::Display for LabelSlice<'a> {
         let mut i = 0;
         let data = self.data();
         while i < self.len() {
-            let mut count = data[i as usize];
+            let mut count = data[i];
             i += 1;
 
             if count == 0 {
@@ -184,7 +185,7 @@ impl<'a> fmt::Display for LabelSlice<'a> {
 
             while count > 0 {
                 count -= 1;
-                let c: u8 = data[i as usize];
+                let c: u8 = data[i];
                 i += 1;
                 if special_char.contains(&c) {
                     buf.push(b'\\');
@@ -239,8 +240,8 @@ mod test {
             ls2.data(),
             [3, 119, 119, 119, 5, 98, 97, 105, 100, 117, 3, 99, 111, 77, 0]
         );
-        assert_eq!(ls1.equals(&ls2, false), true);
-        assert_eq!(ls1.equals(&ls2, true), false);
+        assert!(ls1.equals(&ls2, false));
+        assert!(!ls1.equals(&ls2, true));
         let grand_parent = Name::new("com").unwrap();
         let ls_grand_parent = LabelSlice::from_name(&grand_parent);
         let parent = Name::new("BaIdU.CoM").unwrap();
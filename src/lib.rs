@@ -1,12 +1,26 @@
+#[cfg(feature = "tokio")]
+pub mod async_client;
+#[cfg(feature = "tokio")]
+pub mod async_server;
+#[cfg(feature = "tokio")]
+pub mod auth;
+pub mod axfr;
+pub mod cache;
+pub mod client;
+pub mod dnssec;
 mod domaintree;
 pub mod edns;
+pub mod error;
 pub mod header;
 pub mod header_flag;
+pub mod ixfr;
 pub mod label_sequence;
 pub mod label_slice;
+pub mod message;
+pub mod message_builder;
 pub mod message_render;
 pub mod name;
-mod notify;
+pub mod notify;
 pub mod opcode;
 pub mod question;
 pub mod rand_name_generator;
@@ -14,6 +28,7 @@ pub mod rcode;
 pub mod rdata;
 pub mod rdatafield;
 pub mod rdatas;
+pub mod recursor;
 pub mod request;
 pub mod response;
 pub mod response_builder;
@@ -21,26 +36,41 @@ pub mod response_iter;
 pub mod rr_class;
 pub mod rr_type;
 pub mod rrset;
+pub mod tsig;
+pub mod update;
 pub mod util;
+pub mod zone;
+pub mod zonefile;
 
+#[cfg(feature = "tokio")]
+pub use async_client::AsyncClient;
+#[cfg(feature = "tokio")]
+pub use async_server::{Handler, Server};
+pub use cache::{Cache, CacheAnswer, Clock, SystemClock, Trust};
+pub use client::Client;
 pub use domaintree::{
     node::NodePtr,
     node_chain::NodeChain,
     tree::{DomainTree, FindResult, FindResultFlag},
 };
+pub use error::Error;
 pub use header::Header;
 pub use header_flag::HeaderFlag;
 pub use label_sequence::LabelSequence;
 pub use label_slice::LabelSlice;
+pub use message::Message;
+pub use message_builder::MessageBuilder;
 pub use message_render::MessageRender;
 pub use name::Name;
 pub use name::NameRelation;
+pub use notify::{Notifier, NotifyRequest, NotifyResponse};
 pub use opcode::Opcode;
 pub use question::Question;
 pub use rand_name_generator::RandNameGenerator;
 pub use rcode::Rcode;
 pub use rdata::RData;
 pub use rdatas::{CName, A, AAAA, MX, NAPTR, NS, OPT, PTR, SOA, SRV};
+pub use recursor::{Recursor, Transport};
 pub use request::Request;
 pub use response::{Response, SectionType, ALL_SECTIONS};
 pub use response_builder::{build, ResponseBuilder};
@@ -48,3 +78,6 @@ pub use rr_class::RRClass;
 pub use rr_type::RRType;
 pub use rrset::RRTtl;
 pub use rrset::RRset;
+pub use tsig::{TsigAlgorithm, TsigError, TsigKey, TsigSigner};
+pub use update::{Prerequisite, Update, UpdateBuilder, UpdateMessage};
+pub use zone::Zone;
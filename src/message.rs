@@ -0,0 +1,402 @@
+use crate::edns::Edns;
+use crate::header::Header;
+use crate::header_flag::HeaderFlag;
+use crate::message_render::MessageRender;
+use crate::name::Name;
+use crate::notify::{NotifyRequest, NotifyResponse};
+use crate::opcode::Opcode;
+use crate::question::Question;
+use crate::request::Request;
+use crate::response::{Response, Section, SectionType};
+use crate::rr_type::RRType;
+use crate::rrset::RRset;
+use crate::update::{Prerequisite, Update, UpdateMessage};
+use crate::util::InputBuffer;
+use anyhow::{bail, ensure, Result};
+use std::fmt;
+
+pub(crate) const MAX_MESSAGE_LEN: usize = 65535;
+
+//message with any opcode and any question count, for update message
+//questions hold the zone section, answer/authority hold prerequisite/update
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Message {
+    pub header: Header,
+    pub questions: Vec<Question>,
+    pub sections: [Section; 3],
+}
+
+impl Message {
+    pub fn with_query(name: Name, qtype: RRType) -> Self {
+        let mut header: Header = Default::default();
+        header.set_flag(HeaderFlag::RecursionDesired, true);
+        header.id = rand::random::<u16>();
+        header.qd_count = 1;
+        Message {
+            header,
+            questions: vec![Question::new(name, qtype)],
+            sections: [Section(None), Section(None), Section(None)],
+        }
+    }
+
+    pub fn from_wire(raw: &[u8]) -> Result<Self> {
+        let buf = &mut InputBuffer::new(raw);
+        let header = Header::from_wire(buf)?;
+        let mut questions = Vec::with_capacity(header.qd_count as usize);
+        for _ in 0..header.qd_count {
+            questions.push(Question::from_wire(buf)?);
+        }
+        let section_from_wire = if header.opcode == Opcode::Update {
+            Section::from_update_wire
+        } else {
+            Section::from_wire
+        };
+        let answer = section_from_wire(buf, header.an_count, SectionType::Answer)?;
+        let auth = section_from_wire(buf, header.ns_count, SectionType::Authority)?;
+        let additional = Section::from_wire(buf, header.ar_count, SectionType::Additional)?;
+        if let Some(rrset) = additional.0.as_ref().and_then(|rrsets| rrsets.last()) {
+            if rrset.typ == RRType::OPT {
+                Edns::from_rrset(rrset)?;
+            }
+        }
+
+        Ok(Message {
+            header,
+            questions,
+            sections: [answer, auth, additional],
+        })
+    }
+
+    pub fn to_wire(&self, render: &mut MessageRender) -> Result<usize> {
+        self.header.to_wire(render)?;
+        for question in &self.questions {
+            question.to_wire(render)?;
+        }
+        let (sections, additional) = self.sections.split_at(2);
+        for section in sections {
+            section.to_wire(render)?;
+        }
+        //tsig rr of the parsed message is replaced when signing
+        let mut ar_count = 0;
+        for rrset in additional[0].0.iter().flatten() {
+            if rrset.typ != RRType::TSIG || render.tsig().is_none() {
+                rrset.to_wire(render)?;
+                ar_count += rrset.rr_count().max(1);
+            }
+        }
+        render.write_u16_at(10, ar_count as u16)?;
+        render.sign()?;
+        Ok(render.len())
+    }
+
+    pub fn question(&self) -> Option<&Question> {
+        self.questions.first()
+    }
+
+    pub fn get_edns(&self) -> Option<Edns> {
+        self.sections[2]
+            .0
+            .as_ref()
+            .and_then(|rrsets| rrsets.last())
+            .filter(|rrset| rrset.typ == RRType::OPT)
+            .and_then(|rrset| Edns::from_rrset(rrset).ok())
+    }
+
+    pub fn recalculate_header(&mut self) {
+        self.header.qd_count = self.questions.len() as u16;
+        self.header.an_count = self.sections[0].rr_count() as u16;
+        self.header.ns_count = self.sections[1].rr_count() as u16;
+        self.header.ar_count = self.sections[2].rr_count() as u16;
+    }
+
+    pub fn section_mut(&mut self, section: SectionType) -> Option<&mut Vec<RRset>> {
+        self.sections[section as usize].0.as_mut()
+    }
+
+    pub fn section(&self, section: SectionType) -> Option<&Vec<RRset>> {
+        self.sections[section as usize].0.as_ref()
+    }
+
+    pub fn section_rrset_count(&self, section: SectionType) -> usize {
+        self.section(section).map_or(0, |rrsets| rrsets.len())
+    }
+
+    pub fn take_section(&mut self, section: SectionType) -> Option<Vec<RRset>> {
+        self.sections[section as usize].0.take()
+    }
+
+    pub fn clear_section(&mut self, section: SectionType) {
+        self.sections[section as usize] = Section(None)
+    }
+
+    fn take_single_question(&mut self) -> Result<Question> {
+        ensure!(
+            self.questions.len() == 1,
+            "message has {} questions",
+            self.questions.len()
+        );
+        Ok(self.questions.pop().unwrap())
+    }
+
+    pub fn into_request(mut self) -> Result<Request> {
+        if self.header.opcode != Opcode::Query {
+            bail!("request with opcode {}", self.header.opcode);
+        }
+        if self.section(SectionType::Answer).is_some() {
+            bail!("request has answer");
+        }
+        if self.section(SectionType::Authority).is_some() {
+            bail!("request has auth");
+        }
+        let question = self.take_single_question()?;
+        Ok(Request {
+            header: self.header,
+            question,
+        })
+    }
+
+    pub fn into_response(mut self) -> Result<Response> {
+        let question = self.take_single_question()?;
+        Ok(Response {
+            header: self.header,
+            question,
+            sections: self.sections,
+        })
+    }
+
+    pub fn into_notify_request(mut self) -> Result<NotifyRequest> {
+        if self.header.opcode != Opcode::Notify {
+            bail!("notify with opcode {}", self.header.opcode);
+        }
+        let question = self.take_single_question()?;
+        ensure!(
+            question.typ == RRType::SOA,
+            "question of notify should be soa"
+        );
+        let soa = match self.take_section(SectionType::Answer) {
+            None => None,
+            Some(mut rrsets) => {
+                if rrsets.len() != 1
+                    || rrsets[0].typ != RRType::SOA
+                    || !rrsets[0].name.eq(&question.name)
+                {
+                    bail!("answer section of notify should be soa of the zone");
+                }
+                rrsets.pop()
+            }
+        };
+        Ok(NotifyRequest {
+            header: self.header,
+            question,
+            soa,
+        })
+    }
+
+    pub fn into_update(mut self) -> Result<UpdateMessage> {
+        if self.header.opcode != Opcode::Update {
+            bail!("update with opcode {}", self.header.opcode);
+        }
+        let zone = self.take_single_question()?;
+        ensure!(
+            zone.typ == RRType::SOA,
+            "zone section of update should be soa"
+        );
+        let prerequisites = self
+            .take_section(SectionType::Answer)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|rrset| Prerequisite::from_rrset(rrset, zone.class))
+            .collect::<Result<Vec<_>>>()?;
+        let updates = self
+            .take_section(SectionType::Authority)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|rrset| Update::from_rrset(rrset, zone.class))
+            .collect::<Result<Vec<_>>>()?;
+        let additional = self
+            .take_section(SectionType::Additional)
+            .unwrap_or_default();
+        Ok(UpdateMessage {
+            header: self.header,
+            zone,
+            prerequisites,
+            updates,
+            additional,
+        })
+    }
+
+    pub fn into_notify_response(mut self) -> Result<NotifyResponse> {
+        if self.header.opcode != Opcode::Notify {
+            bail!("notify response with opcode {}", self.header.opcode);
+        }
+        let question = self.take_single_question()?;
+        ensure!(
+            question.typ == RRType::SOA,
+            "question of notify response should be soa"
+        );
+        Ok(NotifyResponse {
+            header: self.header,
+            question,
+        })
+    }
+}
+
+impl From<Request> for Message {
+    fn from(req: Request) -> Self {
+        let mut msg = Message {
+            header: req.header,
+            questions: vec![req.question],
+            sections: [Section(None), Section(None), Section(None)],
+        };
+        msg.recalculate_header();
+        msg
+    }
+}
+
+impl From<Response> for Message {
+    fn from(resp: Response) -> Self {
+        Message {
+            header: resp.header,
+            questions: vec![resp.question],
+            sections: resp.sections,
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.header)?;
+        if let Some(edns) = self.get_edns() {
+            writeln!(f, ";; OPT PSEUDOSECTION:\n{}", edns)?;
+        }
+
+        let names = if self.header.opcode == Opcode::Update {
+            ["ZONE", "PREREQUISITE", "UPDATE", "ADDITIONAL"]
+        } else {
+            ["QUESTION", "ANSWER", "AUTHORITY", "ADDITIONAL"]
+        };
+
+        if !self.questions.is_empty() {
+            writeln!(f, ";; {} SECTION:", names[0])?;
+            for question in &self.questions {
+                writeln!(f, "{}", question)?;
+            }
+            writeln!(f)?;
+        }
+
+        for (i, section) in self.sections.iter().enumerate() {
+            if section.rr_count() > 0 {
+                write!(f, ";; {} SECTION:\n{}\n", names[i + 1], section)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message_builder::MessageBuilder;
+    use crate::rr_class::RRClass;
+    use crate::rrset::RRTtl;
+    use std::str::FromStr;
+
+    fn render(msg: &Message) -> Vec<u8> {
+        let mut buf = [0; 1024];
+        let mut render = MessageRender::new(&mut buf);
+        let len = msg.to_wire(&mut render).unwrap();
+        buf[0..len].to_vec()
+    }
+
+    #[test]
+    fn test_update_message_from_wire() {
+        let zone = Name::new("example.com.").unwrap();
+        let mut msg = Message::with_query(zone.clone(), RRType::SOA);
+        let mut builder = MessageBuilder::new(&mut msg);
+        builder
+            .opcode(Opcode::Update)
+            .clear_flag(HeaderFlag::RecursionDesired)
+            .add_rrset(
+                SectionType::Answer,
+                RRset {
+                    name: Name::new("www.example.com.").unwrap(),
+                    typ: RRType::A,
+                    class: RRClass::ANY,
+                    ttl: RRTtl(0),
+                    rdatas: Vec::new(),
+                },
+            )
+            .add_rrset(
+                SectionType::Authority,
+                RRset {
+                    name: Name::new("www.example.com.").unwrap(),
+                    typ: RRType::ANY,
+                    class: RRClass::ANY,
+                    ttl: RRTtl(0),
+                    rdatas: Vec::new(),
+                },
+            )
+            .add_rrset(
+                SectionType::Authority,
+                RRset::from_str("www.example.com. 300 IN A 1.1.1.1").unwrap(),
+            )
+            .done();
+
+        let raw = render(&msg);
+        let decoded = Message::from_wire(raw.as_slice()).unwrap();
+        assert_eq!(decoded, msg);
+        assert_eq!(decoded.header.opcode, Opcode::Update);
+        assert_eq!(decoded.header.an_count, 1);
+        assert_eq!(decoded.header.ns_count, 2);
+        assert_eq!(decoded.section_rrset_count(SectionType::Authority), 2);
+        assert!(decoded.to_string().contains(";; UPDATE SECTION:"));
+        assert!(decoded.clone().into_request().is_err());
+        //rr without rdata is only valid in update message
+        assert!(Response::from_wire(raw.as_slice()).is_err());
+        let mut raw = raw;
+        raw[2] &= !0x78;
+        assert!(Message::from_wire(raw.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_message_questions() {
+        let mut msg = Message::with_query(Name::new("a.cn.").unwrap(), RRType::A);
+        let mut builder = MessageBuilder::new(&mut msg);
+        builder
+            .add_question(Question::new(Name::new("b.cn.").unwrap(), RRType::AAAA))
+            .done();
+        let decoded = Message::from_wire(render(&msg).as_slice()).unwrap();
+        assert_eq!(decoded.header.qd_count, 2);
+        assert_eq!(decoded.questions.len(), 2);
+        assert_eq!(decoded.questions[1].typ, RRType::AAAA);
+        assert!(decoded.clone().into_response().is_err());
+
+        let empty = Message::from_wire(&[0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert!(empty.question().is_none());
+        assert!(empty.into_request().is_err());
+    }
+
+    #[test]
+    fn test_message_conversion() {
+        let req = Request::new(Name::new("knet.cn.").unwrap(), RRType::NS);
+        let msg = Message::from(req.clone());
+        let decoded = Message::from_wire(render(&msg).as_slice()).unwrap();
+        assert_eq!(decoded.into_request().unwrap(), req);
+
+        let soa = RRset::from_str(
+            "knet.cn. 3600 IN SOA ns1.knet.cn. root.knet.cn. 2019 3600 900 86400 300",
+        )
+        .unwrap();
+        let notify = NotifyRequest::new(Name::new("knet.cn.").unwrap(), Some(soa));
+        let mut buf = [0; 512];
+        let mut render = MessageRender::new(&mut buf);
+        let len = notify.to_wire(&mut render).unwrap();
+        let msg = Message::from_wire(&buf[0..len]).unwrap();
+        assert_eq!(msg.clone().into_notify_request().unwrap(), notify);
+        assert!(msg.clone().into_request().is_err());
+        assert_eq!(
+            msg.into_notify_response().unwrap().question,
+            notify.question
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use crate::edns::Edns;
+use crate::header_flag::HeaderFlag;
+use crate::message::Message;
+use crate::opcode::Opcode;
+use crate::question::Question;
+use crate::rcode::Rcode;
+use crate::response::{Section, SectionType};
+use crate::rrset::RRset;
+
+pub struct MessageBuilder<'a> {
+    msg: &'a mut Message,
+}
+
+impl<'a> MessageBuilder<'a> {
+    pub fn new(msg: &'a mut Message) -> Self {
+        MessageBuilder { msg }
+    }
+
+    pub fn id(&mut self, id: u16) -> &mut Self {
+        self.msg.header.id = id;
+        self
+    }
+
+    pub fn set_flag(&mut self, flag: HeaderFlag) -> &mut Self {
+        self.msg.header.set_flag(flag, true);
+        self
+    }
+
+    pub fn clear_flag(&mut self, flag: HeaderFlag) -> &mut Self {
+        self.msg.header.set_flag(flag, false);
+        self
+    }
+
+    pub fn opcode(&mut self, op: Opcode) -> &mut Self {
+        self.msg.header.opcode = op;
+        self
+    }
+
+    pub fn rcode(&mut self, rcode: Rcode) -> &mut Self {
+        self.msg.header.rcode = rcode;
+        self
+    }
+
+    //replace all the questions with one
+    pub fn question(&mut self, question: Question) -> &mut Self {
+        self.msg.questions = vec![question];
+        self
+    }
+
+    pub fn add_question(&mut self, question: Question) -> &mut Self {
+        self.msg.questions.push(question);
+        self
+    }
+
+    pub fn edns(&mut self, ed: Edns) -> &mut Self {
+        self.add_rrset(SectionType::Additional, ed.to_rrset());
+        self
+    }
+
+    pub fn make_response(&mut self) -> &mut Self {
+        self.set_flag(HeaderFlag::QueryRespone)
+    }
+
+    pub fn add_rrset(&mut self, section: SectionType, mut rrset: RRset) -> &mut Self {
+        if let Some(ref mut rrsets) = self.msg.section_mut(section) {
+            if let Some(index) = rrsets
+                .iter()
+                .position(|old| old.is_same_rrset(&rrset) && old.class == rrset.class)
+            {
+                rrsets[index].rdatas.append(&mut rrset.rdatas);
+            } else {
+                rrsets.push(rrset);
+            }
+        } else {
+            self.msg.sections[section as usize] = Section(Some(vec![rrset]));
+        }
+        self
+    }
+
+    pub fn remove_rrset_by<F: FnMut(&RRset) -> bool>(
+        &mut self,
+        section: SectionType,
+        mut f: F,
+    ) -> &mut Self {
+        if let Some(rrsets) = self.msg.section_mut(section) {
+            rrsets.retain(|rrset| !f(rrset));
+        }
+        self
+    }
+
+    pub fn clear_section(&mut self, section: SectionType) -> &mut Self {
+        self.msg.clear_section(section);
+        self
+    }
+
+    pub fn with_section<F: FnOnce(Option<Vec<RRset>>) -> Option<Vec<RRset>>>(
+        &mut self,
+        section: SectionType,
+        f: F,
+    ) -> &mut Self {
+        let rrsets = self.msg.take_section(section);
+        self.msg.sections[section as usize] = Section(f(rrsets));
+        self
+    }
+
+    pub fn done(&mut self) {
+        self.msg.recalculate_header();
+    }
+}
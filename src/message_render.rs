@@ -1,4 +1,5 @@
 use crate::name::{Name, COMPRESS_POINTER_MARK16, COMPRESS_POINTER_MARK8, MAX_LABEL_COUNT};
+use crate::tsig::TsigSigner;
 use crate::util::{InputBuffer, OutputBuffer};
 
 use anyhow::Result;
@@ -107,7 +108,7 @@ impl<'a, 'b> NameComparator<'a, 'b> {
         let mut b = self.buffer.at(next_pos)?;
         while b & COMPRESS_POINTER_MARK8 == COMPRESS_POINTER_MARK8 {
             let nb = u16::from(self.buffer.at(next_pos + 1)?);
-            next_pos = (u16::from(b & !(COMPRESS_POINTER_MARK8 as u8)) * 256 + nb) as usize;
+            next_pos = (u16::from(b & !COMPRESS_POINTER_MARK8) * 256 + nb) as usize;
             b = self.buffer.at(next_pos)?;
         }
         Ok((b, (next_pos + 1) as u16))
@@ -123,6 +124,9 @@ pub struct MessageRender<'a> {
     table: Vec<Vec<OffSetItem>>,
     item_counts: [usize; BUCKETS],
     label_hashes: [u32; MAX_LABEL_COUNT as usize],
+    compress: bool,
+    lowercase: bool,
+    tsig: Option<TsigSigner>,
 }
 
 impl<'a> MessageRender<'a> {
@@ -132,6 +136,50 @@ impl<'a> MessageRender<'a> {
             table: vec![vec![OffSetItem::default(); RESERVED_ITEMS]; BUCKETS],
             item_counts: [0; BUCKETS],
             label_hashes: [0; MAX_LABEL_COUNT as usize],
+            compress: true,
+            lowercase: false,
+            tsig: None,
+        }
+    }
+
+    //dnssec canonical form requires names uncompressed, and lowercased
+    //for some rdata types
+    pub fn set_compress(&mut self, compress: bool) {
+        self.compress = compress;
+    }
+
+    pub fn set_lowercase(&mut self, lowercase: bool) {
+        self.lowercase = lowercase;
+    }
+
+    //messages rendered after this are signed, the signer is kept by reset
+    //so each message of a stream is signed in turn
+    pub fn set_tsig(&mut self, signer: TsigSigner) {
+        self.tsig = Some(signer);
+    }
+
+    pub fn tsig(&self) -> Option<&TsigSigner> {
+        self.tsig.as_ref()
+    }
+
+    pub fn tsig_mut(&mut self) -> Option<&mut TsigSigner> {
+        self.tsig.as_mut()
+    }
+
+    pub fn take_tsig(&mut self) -> Option<TsigSigner> {
+        self.tsig.take()
+    }
+
+    //append tsig rr, called by to_wire of message after all sections
+    //are rendered
+    pub(crate) fn sign(&mut self) -> Result<()> {
+        match self.tsig.take() {
+            Some(mut signer) => {
+                let result = signer.sign(self);
+                self.tsig = Some(signer);
+                result
+            }
+            None => Ok(()),
         }
     }
 
@@ -163,6 +211,17 @@ impl<'a> MessageRender<'a> {
     }
 
     pub fn write_name(&mut self, name: &Name, compress: bool) -> Result<()> {
+        let compress = compress && self.compress;
+        if self.lowercase {
+            let mut name = name.clone();
+            name.as_lowercase();
+            self.do_write_name(&name, compress)
+        } else {
+            self.do_write_name(name, compress)
+        }
+    }
+
+    fn do_write_name(&mut self, name: &Name, compress: bool) -> Result<()> {
         let label_count = name.label_count();
         let mut label_uncompressed = 0;
         let mut offset = NO_OFFSET;
@@ -194,7 +253,7 @@ impl<'a> MessageRender<'a> {
         if !compress || label_uncompressed == label_count {
             self.buffer.write_bytes(name.raw_data())?;
         } else if label_uncompressed > 0 {
-            let pos = name.offsets()[label_uncompressed as usize];
+            let pos = name.offsets()[label_uncompressed];
             self.buffer
                 .write_bytes(&name.raw_data()[0..(pos as usize)])?;
         }
@@ -259,8 +318,19 @@ impl<'a> MessageRender<'a> {
         self.buffer.write_bytes(data)
     }
 
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    //names after pos can't be used to compress later names
     pub fn truncate(&mut self, pos: usize) -> Result<()> {
-        self.buffer.truncate(pos)
+        self.buffer.truncate(pos)?;
+        for (items, count) in self.table.iter().zip(self.item_counts.iter_mut()) {
+            while *count > 0 && items[*count - 1].pos() as usize >= pos {
+                *count -= 1;
+            }
+        }
+        Ok(())
     }
 
     //reset the buffer
@@ -64,7 +64,7 @@ enum FwStat {
 
 #[inline]
 fn is_digit(c: char) -> bool {
-    c >= '0' && c <= '9'
+    c.is_ascii_digit()
 }
 
 static DIGITAL_VALUES: &[i8] = &[
@@ -158,6 +158,7 @@ pub fn string_parse(
                 count = 0;
                 if c == '\\' {
                     state = FtStat::Initialescape;
+                    break 'inner;
                 } else {
                     state = FtStat::Ordinary;
                 }
@@ -189,6 +190,7 @@ pub fn string_parse(
                     ensure!(count <= MAX_LABEL_LEN, "label len exceed limit");
                     data.push(c as u8);
                     state = FtStat::Ordinary;
+                    break 'inner;
                 } else {
                     digits = 0;
                     value = 0;
@@ -203,7 +205,7 @@ pub fn string_parse(
                     ensure!(value <= 255, "invalid decimal format");
                     count += 1;
                     ensure!(count <= MAX_LABEL_LEN, "label len exceed limit");
-                    data.push(c as u8);
+                    data.push(value as u8);
                     state = FtStat::Ordinary;
                 }
                 break 'inner;
@@ -218,7 +220,7 @@ pub fn string_parse(
         assert!(start == end);
         ensure!(state == FtStat::Ordinary, "name isn't complete");
         assert!(count != 0);
-        data[offsets[offsets.len() - 1] as usize] = count as u8;
+        data[offsets[offsets.len() - 1] as usize] = count;
         if as_absolute {
             offsets.push(data.len() as u8);
             data.push(0);
@@ -250,7 +252,7 @@ impl Name {
         let mut offsets: Vec<u8> = Vec::with_capacity(5);
         let mut seen_pointer: bool = false;
         let mut state = FwStat::Start;
-        let mut current = buf.position() as usize;
+        let mut current = buf.position();
         let pos_beg = current;
         let mut biggest_pointer = current;
         let mut new_current: usize = 0;
@@ -309,7 +311,7 @@ impl Name {
 
     #[inline]
     pub fn len(&self) -> usize {
-        self.raw.len() as usize
+        self.raw.len()
     }
 
     #[inline]
@@ -319,7 +321,7 @@ impl Name {
 
     #[inline]
     pub fn label_count(&self) -> usize {
-        self.offsets.len() as usize
+        self.offsets.len()
     }
 
     pub fn to_wire(&self, render: &mut MessageRender) -> Result<()> {
@@ -365,21 +367,21 @@ impl Name {
             "label count exceed limit"
         );
 
-        let mut raw = Vec::with_capacity(final_length as usize);
-        raw.extend_from_slice(&self.raw[..(self.len() as usize - 1)]);
-        for suffix in &suffixes[..(suffix_count as usize - 1)] {
-            raw.extend_from_slice(&suffix.raw[..(suffix.len() as usize - 1)])
+        let mut raw = Vec::with_capacity(final_length);
+        raw.extend_from_slice(&self.raw[..(self.len() - 1)]);
+        for suffix in &suffixes[..(suffix_count - 1)] {
+            raw.extend_from_slice(&suffix.raw[..(suffix.len() - 1)])
         }
         raw.extend_from_slice(&(suffixes[suffix_count - 1].raw[..]));
 
-        let mut offsets = Vec::with_capacity(final_label_count as usize);
+        let mut offsets = Vec::with_capacity(final_label_count);
         offsets.extend_from_slice(&self.offsets[..]);
         let mut copied_len = self.label_count();
         for suffix in suffixes {
-            let last_offset = offsets[copied_len as usize - 1];
-            offsets.extend_from_slice(&suffix.offsets[1..(suffix.label_count() as usize)]);
-            for i in copied_len..(copied_len + suffix.label_count() - 1) {
-                offsets[i as usize] += last_offset as u8
+            let last_offset = offsets[copied_len - 1];
+            offsets.extend_from_slice(&suffix.offsets[1..suffix.label_count()]);
+            for offset in &mut offsets[copied_len..(copied_len + suffix.label_count() - 1)] {
+                *offset += last_offset
             }
             copied_len += suffix.label_count() - 1;
         }
@@ -396,8 +398,8 @@ impl Name {
             return self.clone();
         }
 
-        let mut raw = Vec::with_capacity(self.len() as usize);
-        let mut offsets = Vec::with_capacity(self.label_count() as usize);
+        let mut raw = Vec::with_capacity(self.len());
+        let mut offsets = Vec::with_capacity(self.label_count());
         let mut label_len = 0;
         let mut i = (self.label_count() - 2) as i8;
         while i >= 0 {
@@ -415,19 +417,19 @@ impl Name {
     }
 
     pub fn split(&self, start_label: usize, label_count_: usize) -> Result<Name> {
-        let max_label_count = self.label_count() as usize;
+        let max_label_count = self.label_count();
         ensure!(start_label < max_label_count, "invalid split index");
         let mut label_count = label_count_;
         if start_label + label_count > max_label_count {
             label_count = max_label_count - start_label;
         }
 
-        if start_label + label_count == (self.label_count() as usize) {
+        if start_label + label_count == self.label_count() {
             let mut offsets = Vec::with_capacity(label_count);
             let first_offset = self.offsets[start_label];
             offsets.extend_from_slice(&self.offsets[start_label..]);
             let start_pos = offsets[0] as usize;
-            let mut raw = Vec::with_capacity(self.len() as usize - start_pos);
+            let mut raw = Vec::with_capacity(self.len() - start_pos);
             raw.extend_from_slice(&self.raw[start_pos..]);
             for offset in offsets.iter_mut().take(label_count) {
                 *offset -= first_offset;
@@ -451,7 +453,7 @@ impl Name {
     }
 
     pub fn parent(&self, level: usize) -> Result<Name> {
-        self.split(level, self.label_count() as usize - level)
+        self.split(level, self.label_count() - level)
     }
 
     pub fn as_lowercase(&mut self) {
@@ -470,33 +472,33 @@ impl Name {
     }
 
     pub fn strip_left(&self, label_count: usize) -> Name {
-        assert!(label_count < (self.label_count() as usize));
+        assert!(label_count < self.label_count());
 
         if label_count == 0 {
             return self.clone();
         }
 
-        let new_label_count = (self.label_count() as usize) - label_count;
+        let new_label_count = self.label_count() - label_count;
         let mut offsets = Vec::with_capacity(new_label_count);
         offsets.extend_from_slice(&self.offsets[label_count..]);
         let start_pos = self.offsets[label_count] as usize;
         for offset in offsets.iter_mut().take(new_label_count) {
             *offset -= start_pos as u8;
         }
-        let new_length = self.len() as usize - start_pos;
+        let new_length = self.len() - start_pos;
         let mut raw = Vec::with_capacity(new_length);
         raw.extend_from_slice(&self.raw[start_pos..]);
         Name { raw, offsets }
     }
 
     pub fn into_ancestor(mut self, label_count: usize) -> Name {
-        assert!(label_count < (self.label_count() as usize));
+        assert!(label_count < self.label_count());
 
         if label_count == 0 {
             return self;
         }
 
-        let new_label_count = (self.label_count() as usize) - label_count;
+        let new_label_count = self.label_count() - label_count;
         let start_pos = self.offsets[label_count] as usize;
         self.offsets = self.offsets.split_off(label_count);
         for i in 0..new_label_count {
@@ -507,13 +509,13 @@ impl Name {
     }
 
     pub fn strip_right(&self, label_count: usize) -> Name {
-        assert!(label_count < self.label_count() as usize);
+        assert!(label_count < self.label_count());
 
         if label_count == 0 {
             return self.clone();
         }
 
-        let new_label_count = self.label_count() as usize - label_count;
+        let new_label_count = self.label_count() - label_count;
         let end_label = new_label_count - 1;
         let end_pos = self.offsets[end_label] as usize;
         let mut raw = Vec::with_capacity(end_pos + 1);
@@ -526,13 +528,13 @@ impl Name {
     }
 
     pub fn into_child(mut self, label_count: usize) -> Name {
-        assert!(label_count < self.label_count() as usize);
+        assert!(label_count < self.label_count());
 
         if label_count == 0 {
             return self;
         }
 
-        let new_label_count = self.label_count() as usize - label_count;
+        let new_label_count = self.label_count() - label_count;
         let end_label = new_label_count - 1;
         let end_pos = self.offsets[end_label] as usize;
         self.raw.truncate(end_pos + 1);
@@ -546,18 +548,15 @@ impl Name {
             return false;
         }
 
-        let mut i = self.len() - 1;
-        let mut j = parent.len() - 1;
-        while j > 0 {
-            if lower_case(parent.raw[j as usize] as usize)
-                != lower_case(self.raw[i as usize] as usize)
-            {
-                return false;
-            }
-            j -= 1;
-            i -= 1;
+        //parent must start at a label boundary of self
+        let start = self.len() - parent.len();
+        if !self.offsets.contains(&(start as u8)) {
+            return false;
         }
-        true
+        self.raw[start..]
+            .iter()
+            .zip(parent.raw.iter())
+            .all(|(&a, &b)| lower_case(a as usize) == lower_case(b as usize))
     }
 
     pub fn is_wildcard(&self) -> bool {
@@ -593,7 +592,7 @@ impl FromStr for Name {
 
 impl fmt::Debug for Name {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.to_string())
+        write!(f, "{}", self)
     }
 }
 
@@ -666,6 +665,11 @@ mod test {
     fn test_name_parse() {
         let name: Name = "www.000.\\231\\167\\187\\229\\138\\168.".parse().unwrap();
         assert_eq!(name.label_count(), 4);
+        assert_eq!(&name.raw_data()[9..11], &[231, 167]);
+
+        let name = Name::new("a\\.b.cn").unwrap();
+        assert_eq!(name.label_count(), 3);
+        assert_eq!(name.to_string(), "a\\.b.cn.");
     }
 
     #[test]
@@ -751,6 +755,25 @@ mod test {
         let relation = range1.get_relation(&range2);
         assert!(relation.order > 0);
         assert!(range1 > range2);
+
+        //rfc4034 6.1
+        let names = [
+            "example.",
+            "a.example.",
+            "yljkjljk.a.example.",
+            "Z.a.example.",
+            "zABC.a.EXAMPLE.",
+            "z.example.",
+            "\\001.z.example.",
+            "*.z.example.",
+            "\\200.z.example.",
+        ]
+        .iter()
+        .map(|name| Name::new(name).unwrap())
+        .collect::<Vec<_>>();
+        for pair in names.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
     }
 
     #[test]
@@ -768,16 +791,16 @@ mod test {
 
         let mut name = www_knet_cn_mix_case.clone();
         let ancestors = ["KNET.cN.", "cN.", "."];
-        for i in 0..3 {
+        for ancestor in &ancestors {
             name = name.into_ancestor(1);
-            assert_eq!(name.to_string(), ancestors[i]);
+            assert_eq!(&name.to_string(), ancestor);
         }
 
         let mut name = www_knet_cn_mix_case.clone();
         let children = ["www.KNET.", "www.", "."];
-        for i in 0..3 {
+        for child in &children {
             name = name.into_child(1);
-            assert_eq!(name.to_string(), children[i]);
+            assert_eq!(&name.to_string(), child);
         }
     }
 
@@ -823,10 +846,11 @@ mod test {
                 && root.is_subdomain(&root)
         );
         assert!(
-            knet.is_subdomain(&knet_cn) == false
-                && knet.is_subdomain(&cn) == false
-                && root.is_subdomain(&cn) == false
-                && www_knet.is_subdomain(&www_knet_cn) == false
+            !knet.is_subdomain(&knet_cn)
+                && !knet.is_subdomain(&cn)
+                && !root.is_subdomain(&cn)
+                && !www_knet.is_subdomain(&www_knet_cn)
+                && !Name::new("aknet.cn").unwrap().is_subdomain(&knet_cn)
         );
     }
 
@@ -840,7 +864,7 @@ mod test {
         }
         for name_str in not_wildcard_names {
             let name = Name::new(name_str).unwrap();
-            assert!(name.is_wildcard() == false);
+            assert!(!name.is_wildcard());
         }
     }
 
@@ -854,7 +878,7 @@ mod test {
         }
         for name_str in not_root_names {
             let name = Name::new(name_str).unwrap();
-            assert!(name.is_root() == false);
+            assert!(!name.is_root());
         }
         let name = Name::new("a.a.a").unwrap();
         assert!(name.parent(3).unwrap().is_root());
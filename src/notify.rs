@@ -1,15 +1,23 @@
 use crate::header::Header;
 use crate::header_flag::HeaderFlag;
+use crate::message::Message;
 use crate::message_render::MessageRender;
 use crate::name::Name;
 use crate::opcode::Opcode;
 use crate::question::Question;
+use crate::rcode::Rcode;
 use crate::rr_class::RRClass;
 use crate::rr_type::RRType;
 use crate::rrset::RRset;
-use crate::util::InputBuffer;
+use crate::util::udp::bind_for;
 use anyhow::{bail, Result};
 use std::fmt;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+const DEFAULT_RETRIES: usize = 3;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct NotifyRequest {
@@ -41,24 +49,12 @@ impl NotifyRequest {
         }
     }
 
-    fn from_wire(
-        header: Header,
-        question: Question,
-        raw_after_question: &mut InputBuffer,
-    ) -> Result<Self> {
-        let mut soa = None;
-        if header.an_count == 1 {
-            let rrset = RRset::from_wire(raw_after_question)?;
-            if rrset.typ != RRType::SOA {
-                bail!("answer section of notify should be soa");
-            }
-            soa = Some(rrset);
+    pub fn from_wire(raw: &[u8]) -> Result<Self> {
+        let msg = Message::from_wire(raw)?;
+        if msg.header.is_flag_set(HeaderFlag::QueryRespone) {
+            bail!("notify request has qr flag");
         }
-        Ok(NotifyRequest {
-            header,
-            question,
-            soa,
-        })
+        msg.into_notify_request()
     }
 
     pub fn to_wire(&self, render: &mut MessageRender) -> Result<usize> {
@@ -67,6 +63,7 @@ impl NotifyRequest {
         if let Some(ref soa) = self.soa {
             soa.to_wire(render)?;
         }
+        render.sign()?;
         Ok(render.len())
     }
 }
@@ -90,23 +87,220 @@ pub struct NotifyResponse {
 impl NotifyResponse {
     pub fn new(mut req: NotifyRequest) -> Self {
         req.header.set_flag(HeaderFlag::QueryRespone, true);
+        //response only has the question
+        req.header.an_count = 0;
+        req.header.ns_count = 0;
+        req.header.ar_count = 0;
         NotifyResponse {
             header: req.header,
             question: req.question,
         }
     }
 
-    fn from_wire(
-        header: Header,
-        question: Question,
-        _raw_after_question: &mut InputBuffer,
-    ) -> Result<Self> {
-        Ok(NotifyResponse { header, question })
+    pub fn from_wire(raw: &[u8]) -> Result<Self> {
+        let msg = Message::from_wire(raw)?;
+        if !msg.header.is_flag_set(HeaderFlag::QueryRespone) {
+            bail!("notify response has no qr flag");
+        }
+        msg.into_notify_response()
+    }
+
+    pub fn is_response_of(&self, req: &NotifyRequest) -> bool {
+        self.header.id == req.header.id && self.question == req.question
     }
 
     pub fn to_wire(&self, render: &mut MessageRender) -> Result<usize> {
         self.header.to_wire(render)?;
         self.question.to_wire(render)?;
+        render.sign()?;
         Ok(render.len())
     }
 }
+
+//send notify to secondaries over udp, rfc1996 3.6, the notify to one
+//secondary is resent until a matching response is received
+pub struct Notifier {
+    zone: Name,
+    soa: Option<RRset>,
+    secondaries: Vec<SocketAddr>,
+    timeout: Duration,
+    retries: usize,
+}
+
+impl Notifier {
+    pub fn new(zone: Name, secondaries: Vec<SocketAddr>) -> Self {
+        Notifier {
+            zone,
+            soa: None,
+            secondaries,
+            timeout: DEFAULT_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+        }
+    }
+
+    pub fn soa(mut self, soa: RRset) -> Self {
+        self.soa = Some(soa);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    //return the secondaries which fail to respond
+    pub fn notify(&self) -> Vec<SocketAddr> {
+        self.secondaries
+            .iter()
+            .filter(|addr| self.notify_secondary(**addr).is_err())
+            .cloned()
+            .collect()
+    }
+
+    pub fn notify_secondary(&self, addr: SocketAddr) -> Result<NotifyResponse> {
+        let socket = bind_for(addr)?;
+        let mut req = NotifyRequest::new(self.zone.clone(), self.soa.clone());
+        req.header.id = rand::random::<u16>();
+        let mut buf = [0; 512];
+        let mut render = MessageRender::new(&mut buf);
+        let len = req.to_wire(&mut render)?;
+        let data = buf[..len].to_vec();
+
+        for _ in 0..=self.retries {
+            socket.send_to(&data, addr)?;
+            if let Some(resp) = self.wait_response(&socket, addr, &req)? {
+                if resp.header.rcode != Rcode::NoError {
+                    bail!("notify to {} failed with {}", addr, resp.header.rcode);
+                }
+                return Ok(resp);
+            }
+        }
+        bail!("no response from {}", addr);
+    }
+
+    //unmatched packets are ignored, return none on timeout
+    fn wait_response(
+        &self,
+        socket: &UdpSocket,
+        addr: SocketAddr,
+        req: &NotifyRequest,
+    ) -> Result<Option<NotifyResponse>> {
+        let deadline = Instant::now() + self.timeout;
+        let mut buf = [0; 512];
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            socket.set_read_timeout(Some(deadline - now))?;
+            let (len, from) = match socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return Ok(None);
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if from != addr {
+                continue;
+            }
+            if let Ok(resp) = NotifyResponse::from_wire(&buf[..len]) {
+                if resp.is_response_of(req) {
+                    return Ok(Some(resp));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+    use std::thread;
+
+    fn soa() -> RRset {
+        RRset::from_str("knet.cn. 3600 IN SOA ns1.knet.cn. root.knet.cn. 2019 3600 900 86400 300")
+            .unwrap()
+    }
+
+    fn render(resp: &NotifyResponse) -> Vec<u8> {
+        let mut buf = [0; 512];
+        let mut render = MessageRender::new(&mut buf);
+        let len = resp.to_wire(&mut render).unwrap();
+        buf[..len].to_vec()
+    }
+
+    #[test]
+    fn test_notify_from_wire() {
+        let req = NotifyRequest::new(Name::new("knet.cn.").unwrap(), Some(soa()));
+        let mut buf = [0; 512];
+        let mut render_buf = MessageRender::new(&mut buf);
+        let len = req.to_wire(&mut render_buf).unwrap();
+        let raw = buf[..len].to_vec();
+        assert_eq!(NotifyRequest::from_wire(&raw).unwrap(), req);
+        assert!(NotifyResponse::from_wire(&raw).is_err());
+
+        let resp = NotifyResponse::new(req.clone());
+        let raw = render(&resp);
+        assert_eq!(NotifyResponse::from_wire(&raw).unwrap(), resp);
+        assert!(resp.is_response_of(&req));
+        assert!(NotifyRequest::from_wire(&raw).is_err());
+
+        let mut other = NotifyRequest::new(Name::new("knet.com.").unwrap(), None);
+        assert!(!resp.is_response_of(&other));
+        other.question.typ = RRType::A;
+        let mut render_buf = MessageRender::new(&mut buf);
+        let len = other.to_wire(&mut render_buf).unwrap();
+        assert!(NotifyRequest::from_wire(&buf[..len]).is_err());
+
+        //soa of another zone
+        let other = NotifyRequest::new(Name::new("knet.com.").unwrap(), Some(soa()));
+        let mut render_buf = MessageRender::new(&mut buf);
+        let len = other.to_wire(&mut render_buf).unwrap();
+        assert!(NotifyRequest::from_wire(&buf[..len]).is_err());
+    }
+
+    #[test]
+    fn test_notifier() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let secondary = thread::spawn(move || {
+            let mut buf = [0; 512];
+            //the first notify is dropped to trigger resending
+            let (len, _) = socket.recv_from(&mut buf).unwrap();
+            let first = NotifyRequest::from_wire(&buf[..len]).unwrap();
+            let (len, from) = socket.recv_from(&mut buf).unwrap();
+            let req = NotifyRequest::from_wire(&buf[..len]).unwrap();
+            assert_eq!(req, first);
+            //response with unmatched id is ignored
+            let mut resp = NotifyResponse::new(req.clone());
+            resp.header.id = resp.header.id.wrapping_add(1);
+            socket.send_to(&render(&resp), from).unwrap();
+            socket
+                .send_to(&render(&NotifyResponse::new(req.clone())), from)
+                .unwrap();
+            req
+        });
+
+        let notifier = Notifier::new(Name::new("knet.cn.").unwrap(), vec![addr])
+            .soa(soa())
+            .timeout(Duration::from_millis(200))
+            .retries(2);
+        assert!(notifier.notify().is_empty());
+        let req = secondary.join().unwrap();
+        assert_eq!(req.soa, Some(soa()));
+
+        //nobody answers
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let notifier = Notifier::new(Name::new("knet.cn.").unwrap(), vec![addr])
+            .timeout(Duration::from_millis(50))
+            .retries(1);
+        assert_eq!(notifier.notify(), vec![addr]);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::message_render::MessageRender;
+use crate::name::Name;
 use crate::rdatas;
 use crate::rr_type::RRType;
 use crate::util::{InputBuffer, StringBuffer};
@@ -29,6 +30,19 @@ pub enum RData {
     NSEC3Param(Box<rdatas::NSEC3Param>),
     URI(Box<rdatas::URI>),
     CAA(Box<rdatas::CAA>),
+    TSig(Box<rdatas::TSig>),
+}
+
+impl RData {
+    //name whose address should be added to additional section, rfc1035 3.3
+    pub fn target_name(&self) -> Option<&Name> {
+        match self {
+            RData::NS(ns) => Some(&ns.name),
+            RData::MX(mx) => Some(&mx.name),
+            RData::SRV(srv) => Some(&srv.target),
+            _ => None,
+        }
+    }
 }
 
 macro_rules! impl_coder_for_rdata {
@@ -102,7 +116,7 @@ impl_coder_for_rdata!(
     [RRType::DNSKEY, rdatas::DNSKey, RData::DNSKey],
     [RRType::NSEC3, rdatas::NSEC3, RData::NSEC3],
     [RRType::NSEC3PARAM, rdatas::NSEC3Param, RData::NSEC3Param],
-    [RRType::NSEC3PARAM, rdatas::NSEC3Param, RData::NSEC3Param],
     [RRType::URI, rdatas::URI, RData::URI],
-    [RRType::CAA, rdatas::CAA, RData::CAA]
+    [RRType::CAA, rdatas::CAA, RData::CAA],
+    [RRType::TSIG, rdatas::TSig, RData::TSig]
 );
@@ -1,9 +1,11 @@
 mod string_decode;
 mod string_encode;
+mod type_bitmap;
 mod wire_decode;
 mod wire_encode;
 
 pub use string_decode::*;
 pub use string_encode::*;
+pub use type_bitmap::TypeBitmap;
 pub use wire_decode::*;
 pub use wire_encode::*;
@@ -1,12 +1,14 @@
 use crate::name::Name;
+use crate::rdatafield::TypeBitmap;
 use crate::rr_type::RRType;
-use crate::util::{hex::from_hex, StringBuffer};
+use crate::rrset::RRTtl;
+use crate::util::{base32::from_base32hex, hex::from_hex, StringBuffer};
 use anyhow::{anyhow, bail, Result};
 use std::net::{Ipv4Addr, Ipv6Addr};
 use time::{Date, Time};
 
 pub fn name_from_str(buf: &mut StringBuffer) -> Result<Name> {
-    buf.read::<Name>()
+    buf.read_name()
 }
 
 pub fn ipv4_from_str(buf: &mut StringBuffer) -> Result<Ipv4Addr> {
@@ -33,6 +35,19 @@ pub fn u32_from_str(buf: &mut StringBuffer) -> Result<u32> {
     buf.read::<u32>()
 }
 
+pub fn u48_from_str(buf: &mut StringBuffer) -> Result<u64> {
+    let num = buf.read::<u64>()?;
+    if num >> 48 != 0 {
+        bail!("{} is out of u48 range", num);
+    }
+    Ok(num)
+}
+
+//time in seconds, units like 1h30m are accepted
+pub fn ttl_from_str(buf: &mut StringBuffer) -> Result<u32> {
+    buf.read::<RRTtl>().map(|ttl| ttl.0)
+}
+
 pub fn text_from_str(buf: &mut StringBuffer) -> Result<Vec<Vec<u8>>> {
     buf.read_text()
 }
@@ -61,10 +76,28 @@ pub fn timestamp_from_str(buf: &mut StringBuffer) -> Result<u32> {
 
 pub fn binary_from_str(buf: &mut StringBuffer) -> Result<Vec<u8>> {
     buf.read_str()
-        .and_then(|s| from_hex(s))
+        .and_then(from_hex)
         .ok_or(anyhow!("invalid hex"))
 }
 
+pub fn salt_from_str(buf: &mut StringBuffer) -> Result<Vec<u8>> {
+    buf.read_str()
+        .and_then(|s| {
+            if s == "-" {
+                Some(Vec::new())
+            } else {
+                from_hex(s)
+            }
+        })
+        .ok_or(anyhow!("invalid salt"))
+}
+
+pub fn base32hex_from_str(buf: &mut StringBuffer) -> Result<Vec<u8>> {
+    buf.read_str()
+        .and_then(from_base32hex)
+        .ok_or(anyhow!("invalid base32hex"))
+}
+
 pub fn base64_from_str(buf: &mut StringBuffer) -> Result<Vec<u8>> {
     buf.read_left()
         .and_then(|s| {
@@ -75,3 +108,24 @@ pub fn base64_from_str(buf: &mut StringBuffer) -> Result<Vec<u8>> {
         })
         .ok_or(anyhow!("invalid base64"))
 }
+
+//length followed by base64 data, and data is omitted if length is zero
+pub fn short_base64_from_str(buf: &mut StringBuffer) -> Result<Vec<u8>> {
+    let len = buf.read::<u16>()?;
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    let data = buf
+        .read_str()
+        .and_then(|s| base64::decode(s).ok())
+        .ok_or(anyhow!("invalid base64"))?;
+    if data.len() != len as usize {
+        bail!("data len isn't {}", len);
+    }
+    Ok(data)
+}
+
+//all the left types, bitmap could be empty
+pub fn type_bitmap_from_str(buf: &mut StringBuffer) -> Result<TypeBitmap> {
+    buf.read_left().unwrap_or("").parse()
+}
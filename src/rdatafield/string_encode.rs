@@ -1,6 +1,7 @@
 use crate::name::Name;
+use crate::rdatafield::TypeBitmap;
 use crate::rr_type::RRType;
-use crate::util::hex::to_hex;
+use crate::util::{base32::to_base32hex, hex::to_hex};
 use std::fmt;
 use std::net::{Ipv4Addr, Ipv6Addr};
 use time::OffsetDateTime;
@@ -33,8 +34,19 @@ pub fn u32_to_str(f: &mut fmt::Formatter, num: u32) -> fmt::Result {
     write!(f, "{}", num)
 }
 
+pub fn u48_to_str(f: &mut fmt::Formatter, num: u64) -> fmt::Result {
+    write!(f, "{}", num)
+}
+
+pub fn ttl_to_str(f: &mut fmt::Formatter, secs: u32) -> fmt::Result {
+    write!(f, "{}", secs)
+}
+
 pub fn text_to_str(f: &mut fmt::Formatter, data: &[Vec<u8>]) -> fmt::Result {
-    for d in data {
+    for (i, d) in data.iter().enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
         string_to_str(f, d)?;
     }
     Ok(())
@@ -52,7 +64,7 @@ pub fn string_to_str(f: &mut fmt::Formatter, data: &[u8]) -> fmt::Result {
     let mut buf = Vec::new();
     for c in data {
         let ch = *c;
-        if (ch < 0x20) || (ch >= 0x7f) {
+        if !(0x20..0x7f).contains(&ch) {
             buf.push(b'\\');
             buf.push(0x30 + ((ch / 100) % 10));
             buf.push(0x30 + ((ch / 10) % 10));
@@ -70,6 +82,31 @@ pub fn binary_to_str(f: &mut fmt::Formatter, data: &[u8]) -> fmt::Result {
     write!(f, "{}", to_hex(data))
 }
 
+//empty salt of nsec3 is shown as "-", rfc5155 3.3
+pub fn salt_to_str(f: &mut fmt::Formatter, data: &[u8]) -> fmt::Result {
+    if data.is_empty() {
+        write!(f, "-")
+    } else {
+        binary_to_str(f, data)
+    }
+}
+
+pub fn base32hex_to_str(f: &mut fmt::Formatter, data: &[u8]) -> fmt::Result {
+    write!(f, "{}", to_base32hex(data))
+}
+
 pub fn base64_to_str(f: &mut fmt::Formatter, data: &[u8]) -> fmt::Result {
     write!(f, "{}", base64::encode(data))
 }
+
+pub fn short_base64_to_str(f: &mut fmt::Formatter, data: &[u8]) -> fmt::Result {
+    if data.is_empty() {
+        write!(f, "0")
+    } else {
+        write!(f, "{} {}", data.len(), base64::encode(data))
+    }
+}
+
+pub fn type_bitmap_to_str(f: &mut fmt::Formatter, bitmap: &TypeBitmap) -> fmt::Result {
+    write!(f, "{}", bitmap)
+}
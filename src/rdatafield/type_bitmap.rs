@@ -0,0 +1,167 @@
+use crate::rr_type::RRType;
+use crate::util::InputBuffer;
+use anyhow::{bail, ensure, Result};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::iter::FromIterator;
+use std::str::FromStr;
+
+//types present at an owner name, used by nsec and nsec3, rfc4034 4.1.2
+#[derive(Debug, Clone, Default, Eq, PartialEq, PartialOrd, Ord)]
+pub struct TypeBitmap(BTreeSet<RRType>);
+
+impl TypeBitmap {
+    pub fn new() -> Self {
+        TypeBitmap(BTreeSet::new())
+    }
+
+    pub fn has_type(&self, typ: RRType) -> bool {
+        self.0.contains(&typ)
+    }
+
+    pub fn insert(&mut self, typ: RRType) -> bool {
+        self.0.insert(typ)
+    }
+
+    pub fn remove(&mut self, typ: RRType) -> bool {
+        self.0.remove(&typ)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    //types in ascending order of type code
+    pub fn types(&self) -> impl Iterator<Item = RRType> + '_ {
+        self.0.iter().copied()
+    }
+
+    //window block number, bitmap length and bitmap for each window in use
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut window_start = 0;
+        for typ in self.types() {
+            let (window, offset) = ((typ.as_u16() >> 8) as u8, (typ.as_u16() & 0xff) as usize);
+            if data.len() == window_start || data[window_start] != window {
+                window_start = data.len();
+                data.extend_from_slice(&[window, 0]);
+            }
+            let len = offset / 8 + 1;
+            if (data[window_start + 1] as usize) < len {
+                data.resize(window_start + 2 + len, 0);
+                data[window_start + 1] = len as u8;
+            }
+            data[window_start + 2 + offset / 8] |= 0x80 >> (offset % 8);
+        }
+        data
+    }
+
+    pub fn from_bytes(mut data: &[u8]) -> Result<Self> {
+        let mut types = BTreeSet::new();
+        let mut last_window = None;
+        while !data.is_empty() {
+            ensure!(data.len() >= 2, "type bitmap window is truncated");
+            let (window, len) = (data[0], data[1] as usize);
+            ensure!(
+                last_window.map_or(true, |last| last < window),
+                "type bitmap windows aren't in ascending order"
+            );
+            ensure!(
+                (1..=32).contains(&len) && data.len() >= len + 2,
+                "invalid type bitmap length {}",
+                len
+            );
+            for (i, &byte) in data[2..len + 2].iter().enumerate() {
+                for bit in 0..8 {
+                    if byte & (0x80 >> bit) != 0 {
+                        let code = (window as u16) << 8 | (i * 8 + bit) as u16;
+                        types.insert(RRType::new(code));
+                    }
+                }
+            }
+            last_window = Some(window);
+            data = &data[len + 2..];
+        }
+        Ok(TypeBitmap(types))
+    }
+
+    pub fn from_wire(buf: &mut InputBuffer, len: u16) -> Result<Self> {
+        TypeBitmap::from_bytes(buf.read_bytes(len as usize)?)
+    }
+}
+
+impl FromIterator<RRType> for TypeBitmap {
+    fn from_iter<I: IntoIterator<Item = RRType>>(iter: I) -> Self {
+        TypeBitmap(iter.into_iter().collect())
+    }
+}
+
+impl fmt::Display for TypeBitmap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, typ) in self.types().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            match typ {
+                //rfc3597 5, generic type mnemonic
+                RRType::Unknown(code) => write!(f, "TYPE{}", code)?,
+                _ => write!(f, "{}", typ)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for TypeBitmap {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        let mut types = BTreeSet::new();
+        for typ in s.split_whitespace() {
+            let typ = match typ.parse::<RRType>() {
+                Ok(typ) => typ,
+                Err(e) => match typ.to_uppercase().strip_prefix("TYPE") {
+                    Some(code) => RRType::new(code.parse::<u16>()?),
+                    None => bail!(e),
+                },
+            };
+            types.insert(typ);
+        }
+        Ok(TypeBitmap(types))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_type_bitmap() {
+        //rfc4034 4.3
+        let bitmap: TypeBitmap = "A MX RRSIG NSEC TYPE1234".parse().unwrap();
+        let mut wire = vec![0x00, 0x06, 0x40, 0x01, 0x00, 0x00, 0x00, 0x03, 0x04, 0x1b];
+        wire.extend_from_slice(&[0; 26]);
+        wire.push(0x20);
+        assert_eq!(bitmap.to_bytes(), wire);
+        assert_eq!(TypeBitmap::from_bytes(&wire).unwrap(), bitmap);
+        assert_eq!(bitmap.to_string(), "A MX RRSIG NSEC TYPE1234");
+        assert!(bitmap.has_type(RRType::MX));
+        assert!(bitmap.has_type(RRType::Unknown(1234)));
+        assert!(!bitmap.has_type(RRType::NS));
+
+        assert!(TypeBitmap::new().to_bytes().is_empty());
+        assert!(TypeBitmap::from_bytes(&[]).unwrap().is_empty());
+        for invalid in [
+            &[0x00][..],
+            &[0x00, 0x00],
+            &[0x00, 0x02, 0x40],
+            &[0x01, 0x01, 0x40, 0x00, 0x01, 0x40],
+        ] {
+            assert!(TypeBitmap::from_bytes(invalid).is_err());
+        }
+        assert!("A BOGUS".parse::<TypeBitmap>().is_err());
+    }
+}
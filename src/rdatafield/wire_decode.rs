@@ -1,4 +1,5 @@
 use crate::name::Name;
+use crate::rdatafield::TypeBitmap;
 use crate::rr_type::RRType;
 use crate::util::InputBuffer;
 use anyhow::{ensure, Result};
@@ -58,6 +59,13 @@ pub fn u32_from_wire(buf: &mut InputBuffer, len: u16) -> Result<(u32, u16)> {
     Ok((buf.read_u32()?, len - 4))
 }
 
+pub fn u48_from_wire(buf: &mut InputBuffer, len: u16) -> Result<(u64, u16)> {
+    ensure!(len >= 6, "wire is too short for u48");
+    let high = buf.read_u16()? as u64;
+    let low = buf.read_u32()? as u64;
+    Ok(((high << 32) | low, len - 6))
+}
+
 pub fn text_from_wire(buf: &mut InputBuffer, len: u16) -> Result<(Vec<Vec<u8>>, u16)> {
     let mut data = Vec::new();
     let mut left_len = len;
@@ -80,3 +88,15 @@ pub fn binary_from_wire(buf: &mut InputBuffer, len: u16) -> Result<(Vec<u8>, u16
     let data = buf.read_bytes(len as usize)?;
     Ok((data.to_vec(), 0))
 }
+
+pub fn short_binary_from_wire(buf: &mut InputBuffer, len: u16) -> Result<(Vec<u8>, u16)> {
+    ensure!(len >= 2, "wire is too short for short binary");
+    let dl = buf.read_u16()?;
+    ensure!(len - 2 >= dl, "wire is too short for short binary");
+    let data = buf.read_bytes(dl as usize)?;
+    Ok((data.to_vec(), len - dl - 2))
+}
+
+pub fn type_bitmap_from_wire(buf: &mut InputBuffer, len: u16) -> Result<(TypeBitmap, u16)> {
+    Ok((TypeBitmap::from_wire(buf, len)?, 0))
+}
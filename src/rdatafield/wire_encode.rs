@@ -1,5 +1,6 @@
 use crate::message_render::MessageRender;
 use crate::name::Name;
+use crate::rdatafield::TypeBitmap;
 use crate::rr_type::RRType;
 use std::net::{Ipv4Addr, Ipv6Addr};
 
@@ -43,6 +44,11 @@ pub fn u32_to_wire(render: &mut MessageRender, num: u32) -> Result<()> {
     render.write_u32(num)
 }
 
+pub fn u48_to_wire(render: &mut MessageRender, num: u64) -> Result<()> {
+    render.write_u16((num >> 32) as u16)?;
+    render.write_u32(num as u32)
+}
+
 pub fn text_to_wire(render: &mut MessageRender, data: &[Vec<u8>]) -> Result<()> {
     for d in data {
         byte_binary_to_wire(render, d)?;
@@ -58,3 +64,12 @@ pub fn byte_binary_to_wire(render: &mut MessageRender, data: &[u8]) -> Result<()
 pub fn binary_to_wire(render: &mut MessageRender, data: &[u8]) -> Result<()> {
     render.write_bytes(data)
 }
+
+pub fn short_binary_to_wire(render: &mut MessageRender, data: &[u8]) -> Result<()> {
+    render.write_u16(data.len() as u16)?;
+    render.write_bytes(data)
+}
+
+pub fn type_bitmap_to_wire(render: &mut MessageRender, bitmap: &TypeBitmap) -> Result<()> {
+    render.write_bytes(&bitmap.to_bytes())
+}
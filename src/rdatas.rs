@@ -28,13 +28,13 @@ pub struct SOA {
     pub rname: Name,
     #[field(codec = "u32", display = "u32")]
     pub serial: u32,
-    #[field(codec = "u32", display = "u32")]
+    #[field(codec = "u32", display = "ttl")]
     pub refresh: u32,
-    #[field(codec = "u32", display = "u32")]
+    #[field(codec = "u32", display = "ttl")]
     pub retry: u32,
-    #[field(codec = "u32", display = "u32")]
+    #[field(codec = "u32", display = "ttl")]
     pub expire: u32,
-    #[field(codec = "u32", display = "u32")]
+    #[field(codec = "u32", display = "ttl")]
     pub minimum: u32,
 }
 
@@ -156,8 +156,8 @@ pub struct RRSig {
 pub struct NSEC {
     #[field(codec = "name", display = "name")]
     pub next_domain_name: Name,
-    #[field(codec = "binary", display = "binary")]
-    pub type_bit_map: Vec<u8>,
+    #[field(codec = "type_bitmap", display = "type_bitmap")]
+    pub type_bit_map: TypeBitmap,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord, Rdata)]
@@ -180,12 +180,12 @@ pub struct NSEC3 {
     pub flags: u8,
     #[field(codec = "u16", display = "u16")]
     pub iterations: u16,
-    #[field(codec = "byte_binary", display = "binary")]
+    #[field(codec = "byte_binary", display = "salt")]
     pub salt: Vec<u8>,
-    #[field(codec = "byte_binary", display = "binary")]
+    #[field(codec = "byte_binary", display = "base32hex")]
     pub next_hash: Vec<u8>,
-    #[field(codec = "binary", display = "binary")]
-    pub types: Vec<u8>,
+    #[field(codec = "type_bitmap", display = "type_bitmap")]
+    pub types: TypeBitmap,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord, Rdata)]
@@ -196,7 +196,7 @@ pub struct NSEC3Param {
     pub flags: u8,
     #[field(codec = "u16", display = "u16")]
     pub iterations: u16,
-    #[field(codec = "byte_binary", display = "binary")]
+    #[field(codec = "byte_binary", display = "salt")]
     pub salt: Vec<u8>,
 }
 
@@ -232,6 +232,24 @@ pub struct URI {
     pub target: Vec<u8>,
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord, Rdata)]
+pub struct TSig {
+    #[field(codec = "name_uncompressed", display = "name")]
+    pub algorithm: Name,
+    #[field(codec = "u48", display = "u48")]
+    pub time_signed: u64,
+    #[field(codec = "u16", display = "u16")]
+    pub fudge: u16,
+    #[field(codec = "short_binary", display = "short_base64")]
+    pub mac: Vec<u8>,
+    #[field(codec = "u16", display = "u16")]
+    pub original_id: u16,
+    #[field(codec = "u16", display = "u16")]
+    pub error: u16,
+    #[field(codec = "short_binary", display = "short_base64")]
+    pub other: Vec<u8>,
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -304,8 +322,8 @@ mod test {
         assert_eq!(str::from_utf8(&txt2.data[0]).unwrap(), "foo bar");
 
         //txt3
-        let txt3 = TXT::from_str(&mut StringBuffer::new("\"foo\010bar\"")).unwrap();
-        assert_eq!(str::from_utf8(&txt3.data[0]).unwrap(), "foo\010bar");
+        let txt3 = TXT::from_str(&mut StringBuffer::new("\"foo\x0010bar\"")).unwrap();
+        assert_eq!(str::from_utf8(&txt3.data[0]).unwrap(), "foo\x0010bar");
 
         //txt4
         let txt4 = TXT::from_str(&mut StringBuffer::new(r#""foo\"xx\" bar""#)).unwrap();
@@ -325,6 +343,15 @@ mod test {
             "DS 8 1 86400 20210702050000 20210619040000 14631 . CxMDaySHQUAVlRNtZc2ynPSV70KoOvDLE48Q8aCIDryM5wcV9v/hzhvG +b3FxqO5ajZqS8E9+O1S2D2hLcoRykvCtGvRZVLiQHbIoXb84umloJax o/4h62siUR9Nm/Ihe9xH9k4X3StN3lk900LKPDC590zyG97Pglixph4V JbFNb18piuhM6nwqAuc7gAKoguNhqM4huj1CNv8vzzRrIhkd9RgN7lm+ 33A2K4nIEw4hclUFpEKrCSHyZ82xnSXrHExixsRrDLvk7iSN9dA0oaD9 Xdl+F23JmFej1A+wjQSFZ+UAts+ORNox8sgpVCR4yfgHXW4qvmEJwBlC 9imCcg==",
         )).unwrap();
         assert_eq!(rrsig.covered, RRType::DS);
+
+        //tsig
+        let tsig_str = "hmac-sha256. 1600000000 300 4 AQIDBA== 4660 0 0";
+        let tsig = TSig::from_str(&mut StringBuffer::new(tsig_str)).unwrap();
+        assert_eq!(tsig.time_signed, 1600000000);
+        assert_eq!(tsig.mac, vec![1, 2, 3, 4]);
+        assert_eq!(tsig.original_id, 4660);
+        assert!(tsig.other.is_empty());
+        assert_eq!(tsig.to_string(), tsig_str);
     }
 
     #[test]
@@ -339,5 +366,30 @@ mod test {
         let a_str = "1.1.1.1";
         let a = A::from_str(&mut StringBuffer::new(a_str)).unwrap();
         assert_eq!(a.to_string(), a_str);
+
+        //nsec and nsec3 from rfc5155 appendix a
+        let nsec_str = "host.example.com. A MX RRSIG NSEC TYPE1234";
+        let nsec = NSEC::from_str(&mut StringBuffer::new(nsec_str)).unwrap();
+        assert!(nsec.type_bit_map.has_type(RRType::Unknown(1234)));
+        assert_eq!(nsec.to_string(), nsec_str);
+
+        let nsec3_str =
+            "1 1 12 aabbccdd 2t7b4g4vsa5smi47k61mv5bv1a22bojr NS SOA MX RRSIG DNSKEY NSEC3PARAM";
+        let nsec3 = NSEC3::from_str(&mut StringBuffer::new(nsec3_str)).unwrap();
+        assert_eq!(nsec3.next_hash.len(), 20);
+        assert!(nsec3.types.has_type(RRType::NSEC3PARAM));
+        assert_eq!(nsec3.to_string(), nsec3_str);
+
+        let mut buf = [0; 512];
+        let mut render = MessageRender::new(&mut buf);
+        nsec3.to_wire(&mut render).unwrap();
+        let len = render.len() as u16;
+        let mut buf = InputBuffer::new(render.data());
+        assert_eq!(NSEC3::from_wire(&mut buf, len).unwrap(), nsec3);
+
+        //empty non-terminal has no type
+        let nsec3_str = "1 0 0 ab 2t7b4g4vsa5smi47k61mv5bv1a22bojr";
+        let nsec3 = NSEC3::from_str(&mut StringBuffer::new(nsec3_str)).unwrap();
+        assert!(nsec3.types.is_empty());
     }
 }
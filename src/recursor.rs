@@ -0,0 +1,566 @@
+use crate::client::Client;
+use crate::error::Error;
+use crate::header_flag::HeaderFlag;
+use crate::name::{self, Name};
+use crate::rcode::Rcode;
+use crate::rdata::RData;
+use crate::request::Request;
+use crate::response::{Response, SectionType, MAX_CNAME_CHAIN};
+use crate::response_builder::ResponseBuilder;
+use crate::rr_type::RRType;
+use crate::rrset::RRset;
+use crate::zone::{cname_target, synthesize_cname};
+use anyhow::{bail, Result};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+const DEFAULT_MAX_QUERIES: usize = 64;
+const DEFAULT_MAX_DEPTH: usize = 4;
+
+//send one query to one server, tests replay responses without network
+pub trait Transport {
+    fn query(&mut self, server: IpAddr, req: &Request) -> Result<Response>;
+}
+
+impl Transport for Client {
+    fn query(&mut self, server: IpAddr, req: &Request) -> Result<Response> {
+        self.query_server(req, SocketAddr::new(server, 53))
+    }
+}
+
+//ipv4 addresses of root servers
+pub fn root_hints() -> Vec<IpAddr> {
+    [
+        [198, 41, 0, 4],
+        [199, 9, 14, 201],
+        [192, 33, 4, 12],
+        [199, 7, 91, 13],
+        [192, 203, 230, 10],
+        [192, 5, 5, 241],
+        [192, 112, 36, 4],
+        [198, 97, 190, 53],
+        [192, 36, 148, 17],
+        [192, 58, 128, 30],
+        [193, 0, 14, 129],
+        [199, 7, 83, 42],
+        [202, 12, 27, 33],
+    ]
+    .iter()
+    .map(|ip| IpAddr::V4(Ipv4Addr::from(*ip)))
+    .collect()
+}
+
+//state shared by one resolving and the sub resolvings of name servers
+struct Context {
+    queries: usize,
+    resolving: Vec<(Name, RRType)>,
+}
+
+//iterative resolver, starts from root servers and follows referrals
+pub struct Recursor<T> {
+    transport: T,
+    root_servers: Vec<IpAddr>,
+    max_queries: usize,
+    max_depth: usize,
+}
+
+impl<T: Transport> Recursor<T> {
+    pub fn new(transport: T, root_servers: Vec<IpAddr>) -> Self {
+        Recursor {
+            transport,
+            root_servers,
+            max_queries: DEFAULT_MAX_QUERIES,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    //queries sent to servers for one resolving
+    pub fn max_queries(mut self, max_queries: usize) -> Self {
+        self.max_queries = max_queries;
+        self
+    }
+
+    //nested resolving of name servers without glue
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn resolve(&mut self, name: &Name, typ: RRType) -> Result<Response> {
+        let mut ctx = Context {
+            queries: 0,
+            resolving: Vec::new(),
+        };
+        self.resolve_in(&mut ctx, name, typ, 0)
+    }
+
+    fn resolve_in(
+        &mut self,
+        ctx: &mut Context,
+        qname: &Name,
+        qtype: RRType,
+        depth: usize,
+    ) -> Result<Response> {
+        if depth > self.max_depth {
+            bail!(Error::TooDeep);
+        }
+        if ctx
+            .resolving
+            .iter()
+            .any(|(name, typ)| *typ == qtype && name.eq(qname))
+        {
+            bail!(Error::Loop);
+        }
+        ctx.resolving.push((qname.clone(), qtype));
+        let result = self.resolve_chain(ctx, qname, qtype, depth);
+        ctx.resolving.pop();
+        result
+    }
+
+    //each name in cname chain is resolved from root again, since it may
+    //be in another zone
+    fn resolve_chain(
+        &mut self,
+        ctx: &mut Context,
+        qname: &Name,
+        qtype: RRType,
+        depth: usize,
+    ) -> Result<Response> {
+        let mut chain = Vec::new();
+        let mut visited = vec![qname.clone()];
+        let mut current = qname.clone();
+        loop {
+            let (mut resp, zone) = self.iterate(ctx, &current, qtype, depth)?;
+            let answer = resp
+                .take_section(SectionType::Answer)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|rrset| rrset.name.is_subdomain(&zone))
+                .collect::<Vec<_>>();
+            let last = current.clone();
+            let found = follow_answer(&answer, &mut current, qtype, &mut chain, &mut visited)?;
+            if found.is_none() && resp.header.rcode == Rcode::NoError && !current.eq(&last) {
+                continue;
+            }
+
+            let authority = if found.is_none() {
+                resp.take_section(SectionType::Authority)
+            } else {
+                None
+            };
+            let mut result = Response::with_question(qname.clone(), qtype);
+            let mut builder = ResponseBuilder::new(&mut result);
+            builder
+                .make_response()
+                .set_flag(HeaderFlag::RecursionAvailable)
+                .rcode(resp.header.rcode);
+            for rrset in chain.into_iter().chain(found.unwrap_or_default()) {
+                builder.add_rrset(SectionType::Answer, rrset);
+            }
+            for rrset in authority.unwrap_or_default() {
+                builder.add_rrset(SectionType::Authority, rrset);
+            }
+            builder.done();
+            return Ok(result);
+        }
+    }
+
+    //follow referrals until the authoritative server of qname answers,
+    //return the response and the zone of the server
+    fn iterate(
+        &mut self,
+        ctx: &mut Context,
+        qname: &Name,
+        qtype: RRType,
+        depth: usize,
+    ) -> Result<(Response, Name)> {
+        let mut zone = name::root();
+        let mut servers = self.root_servers.clone();
+        loop {
+            let resp = self.query_servers(ctx, &servers, qname, qtype)?;
+            let child = match referral(&resp, qname) {
+                Some(ns) => ns,
+                None => return Ok((resp, zone)),
+            };
+            //zone only goes down, which ends the loop
+            if !child.name.is_subdomain(&zone) || child.name.eq(&zone) {
+                bail!("bad referral to {} from servers of {}", child.name, zone);
+            }
+            servers = glue_addresses(&resp, child, &zone);
+            if servers.is_empty() {
+                servers = self.resolve_name_servers(ctx, child, depth)?;
+            }
+            if servers.is_empty() {
+                bail!("no address for name servers of {}", child.name);
+            }
+            zone = child.name.clone();
+        }
+    }
+
+    fn resolve_name_servers(
+        &mut self,
+        ctx: &mut Context,
+        ns: &RRset,
+        depth: usize,
+    ) -> Result<Vec<IpAddr>> {
+        for rdata in &ns.rdatas {
+            let name = match rdata {
+                RData::NS(ns) => &ns.name,
+                _ => continue,
+            };
+            match self.resolve_in(ctx, name, RRType::A, depth + 1) {
+                Ok(resp) => {
+                    //addresses are owned by the last name of cname chain
+                    let answer = resp.section(SectionType::Answer);
+                    let owner = match answer.and_then(|rrsets| rrsets.last()) {
+                        Some(rrset) => &rrset.name,
+                        None => continue,
+                    };
+                    let addrs = addresses(answer, owner);
+                    if !addrs.is_empty() {
+                        return Ok(addrs);
+                    }
+                }
+                Err(e)
+                    if matches!(
+                        e.downcast_ref::<Error>(),
+                        Some(Error::TooManyQueries | Error::TooDeep | Error::Loop)
+                    ) =>
+                {
+                    return Err(e)
+                }
+                Err(_) => {}
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    fn query_servers(
+        &mut self,
+        ctx: &mut Context,
+        servers: &[IpAddr],
+        qname: &Name,
+        qtype: RRType,
+    ) -> Result<Response> {
+        for server in servers {
+            if ctx.queries >= self.max_queries {
+                bail!(Error::TooManyQueries);
+            }
+            ctx.queries += 1;
+            let mut req = Request::new(qname.clone(), qtype);
+            req.header.set_flag(HeaderFlag::RecursionDesired, false);
+            if let Ok(resp) = self.transport.query(*server, &req) {
+                if matches!(resp.header.rcode, Rcode::NoError | Rcode::NXDomain) {
+                    return Ok(resp);
+                }
+            }
+        }
+        bail!("no server answers {} {}", qname, qtype);
+    }
+}
+
+//ns in authority of non-authoritative response without answer
+fn referral<'a>(resp: &'a Response, qname: &Name) -> Option<&'a RRset> {
+    if resp.header.is_flag_set(HeaderFlag::AuthAnswer)
+        || resp.header.rcode != Rcode::NoError
+        || resp.section_rrset_count(SectionType::Answer) > 0
+    {
+        return None;
+    }
+    resp.section(SectionType::Authority)?
+        .iter()
+        .find(|rrset| rrset.typ == RRType::NS && qname.is_subdomain(&rrset.name))
+}
+
+//glue out of the zone of the server isn't trusted
+fn glue_addresses(resp: &Response, ns: &RRset, zone: &Name) -> Vec<IpAddr> {
+    let mut addrs = Vec::new();
+    for rdata in &ns.rdatas {
+        if let RData::NS(ns) = rdata {
+            if ns.name.is_subdomain(zone) {
+                addrs.extend(addresses(resp.section(SectionType::Additional), &ns.name));
+            }
+        }
+    }
+    addrs
+}
+
+fn addresses(rrsets: Option<&Vec<RRset>>, name: &Name) -> Vec<IpAddr> {
+    rrsets
+        .into_iter()
+        .flatten()
+        .filter(|rrset| rrset.name.eq(name))
+        .flat_map(|rrset| rrset.rdatas.iter())
+        .filter_map(|rdata| match rdata {
+            RData::A(a) => Some(IpAddr::V4(a.host)),
+            RData::AAAA(aaaa) => Some(IpAddr::V6(aaaa.host)),
+            _ => None,
+        })
+        .collect()
+}
+
+//follow cname and dname in answer from current name, return the rrset of
+//qtype if it's reached
+fn follow_answer(
+    answer: &[RRset],
+    current: &mut Name,
+    qtype: RRType,
+    chain: &mut Vec<RRset>,
+    visited: &mut Vec<Name>,
+) -> Result<Option<Vec<RRset>>> {
+    loop {
+        if let Some(rrset) = answer
+            .iter()
+            .find(|rrset| rrset.typ == qtype && rrset.name.eq(current))
+        {
+            return Ok(Some(vec![rrset.clone()]));
+        }
+
+        let dname = answer.iter().find(|rrset| {
+            rrset.typ == RRType::DNAME
+                && current.is_subdomain(&rrset.name)
+                && !rrset.name.eq(current)
+        });
+        let cname = match dname {
+            Some(dname) => {
+                if !chain.contains(dname) {
+                    chain.push(dname.clone());
+                }
+                match synthesize_cname(current, dname) {
+                    Some(cname) => cname,
+                    None => bail!("no cname can be synthesized from dname {}", dname.name),
+                }
+            }
+            None => match answer
+                .iter()
+                .find(|rrset| rrset.typ == RRType::CNAME && rrset.name.eq(current))
+            {
+                Some(cname) => cname.clone(),
+                None => return Ok(None),
+            },
+        };
+        let target = match cname_target(&cname) {
+            Some(target) => target.clone(),
+            None => bail!("cname of {} has no target", cname.name),
+        };
+        chain.push(cname);
+        if visited.iter().any(|name| name.eq(&target)) {
+            bail!(Error::Loop);
+        }
+        if visited.len() > MAX_CNAME_CHAIN {
+            bail!(Error::TooDeep);
+        }
+        visited.push(target.clone());
+        *current = target;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::fixture::{build_zone, name, rrset};
+    use crate::zone::Zone;
+    use std::cell::Cell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    const ROOT: &str = r#"
+$ORIGIN .
+$TTL 3600
+.                   IN SOA   a.root-servers.net. hostmaster.root. 1 3600 900 604800 300
+                    IN NS    a.root-servers.net.
+a.root-servers.net. IN A     198.41.0.4
+com.                IN NS    a.gtld-servers.net.
+net.                IN NS    a.gtld-servers.net.
+a.gtld-servers.net. IN A     192.5.6.30
+"#;
+
+    const COM: &str = r#"
+$ORIGIN com.
+$TTL 3600
+@                   IN SOA   a.gtld-servers.net. hostmaster 1 3600 900 604800 300
+                    IN NS    a.gtld-servers.net.
+example             IN NS    ns1.example
+ns1.example         IN A     10.0.0.1
+other               IN NS    ns.example.net.
+"#;
+
+    const NET: &str = r#"
+$ORIGIN net.
+$TTL 3600
+@                   IN SOA   a.gtld-servers.net. hostmaster 1 3600 900 604800 300
+                    IN NS    a.gtld-servers.net.
+example             IN NS    ns.example
+ns.example          IN A     10.0.0.2
+"#;
+
+    const EXAMPLE_COM: &str = r#"
+$ORIGIN example.com.
+$TTL 3600
+@                   IN SOA   ns1 hostmaster 1 3600 900 604800 300
+                    IN NS    ns1
+ns1                 IN A     10.0.0.1
+www                 IN A     1.2.3.4
+alias               IN CNAME www
+ext                 IN CNAME www.other.com.
+loop1               IN CNAME loop2
+loop2               IN CNAME loop1
+legacy              IN DNAME example.com.
+"#;
+
+    const EXAMPLE_NET: &str = r#"
+$ORIGIN example.net.
+$TTL 3600
+@                   IN SOA   ns hostmaster 1 3600 900 604800 300
+                    IN NS    ns
+ns                  IN A     10.0.0.3
+"#;
+
+    const OTHER_COM: &str = r#"
+$ORIGIN other.com.
+$TTL 3600
+@                   IN SOA   ns.example.net. hostmaster 1 3600 900 604800 300
+                    IN NS    ns.example.net.
+www                 IN A     5.6.7.8
+"#;
+
+    //servers answer from their zones, the deepest zone is used
+    struct ZoneTransport {
+        servers: HashMap<IpAddr, Vec<Zone>>,
+        queries: Rc<Cell<usize>>,
+    }
+
+    impl Transport for ZoneTransport {
+        fn query(&mut self, server: IpAddr, req: &Request) -> Result<Response> {
+            self.queries.set(self.queries.get() + 1);
+            let zone = self
+                .servers
+                .get(&server)
+                .into_iter()
+                .flatten()
+                .filter(|zone| req.question.name.is_subdomain(zone.origin()))
+                .max_by_key(|zone| zone.origin().label_count());
+            match zone {
+                Some(zone) => Ok(zone.query(req, None)),
+                None => bail!("server {} doesn't respond", server),
+            }
+        }
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    fn build_recursor() -> (Recursor<ZoneTransport>, Rc<Cell<usize>>) {
+        let mut servers = HashMap::new();
+        servers.insert(ip("198.41.0.4"), vec![build_zone(".", ROOT)]);
+        servers.insert(
+            ip("192.5.6.30"),
+            vec![build_zone("com.", COM), build_zone("net.", NET)],
+        );
+        servers.insert(
+            ip("10.0.0.1"),
+            vec![build_zone("example.com.", EXAMPLE_COM)],
+        );
+        servers.insert(
+            ip("10.0.0.2"),
+            vec![build_zone("example.net.", EXAMPLE_NET)],
+        );
+        servers.insert(ip("10.0.0.3"), vec![build_zone("other.com.", OTHER_COM)]);
+        let queries = Rc::new(Cell::new(0));
+        let transport = ZoneTransport {
+            servers,
+            queries: queries.clone(),
+        };
+        //the first root server is down
+        let recursor = Recursor::new(transport, vec![ip("192.0.2.1"), ip("198.41.0.4")]);
+        (recursor, queries)
+    }
+
+    fn answer(resp: &Response) -> Vec<String> {
+        resp.section(SectionType::Answer)
+            .into_iter()
+            .flatten()
+            .map(|rrset| rrset.to_string().trim().to_string())
+            .collect()
+    }
+
+    fn resolve(recursor: &mut Recursor<ZoneTransport>, name: &str) -> Result<Response> {
+        recursor.resolve(&Name::new(name).unwrap(), RRType::A)
+    }
+
+    #[test]
+    fn test_resolve() {
+        let (mut recursor, queries) = build_recursor();
+        let resp = resolve(&mut recursor, "www.example.com.").unwrap();
+        assert_eq!(resp.header.rcode, Rcode::NoError);
+        assert!(resp.header.is_flag_set(HeaderFlag::RecursionAvailable));
+        assert_eq!(
+            answer(&resp),
+            vec!["www.example.com.\t3600\tIN\tA\t1.2.3.4"]
+        );
+        assert_eq!(queries.get(), 4);
+
+        let resp = resolve(&mut recursor, "alias.example.com.").unwrap();
+        assert_eq!(
+            answer(&resp),
+            vec![
+                "alias.example.com.\t3600\tIN\tCNAME\twww.example.com.",
+                "www.example.com.\t3600\tIN\tA\t1.2.3.4",
+            ]
+        );
+
+        //name server of other.com. has no glue
+        let resp = resolve(&mut recursor, "ext.example.com.").unwrap();
+        assert_eq!(
+            answer(&resp),
+            vec![
+                "ext.example.com.\t3600\tIN\tCNAME\twww.other.com.",
+                "www.other.com.\t3600\tIN\tA\t5.6.7.8",
+            ]
+        );
+
+        let resp = resolve(&mut recursor, "www.legacy.example.com.").unwrap();
+        assert_eq!(
+            answer(&resp),
+            vec![
+                "legacy.example.com.\t3600\tIN\tDNAME\texample.com.",
+                "www.legacy.example.com.\t3600\tIN\tCNAME\twww.example.com.",
+                "www.example.com.\t3600\tIN\tA\t1.2.3.4",
+            ]
+        );
+
+        let resp = resolve(&mut recursor, "nonexist.example.com.").unwrap();
+        assert_eq!(resp.header.rcode, Rcode::NXDomain);
+        assert!(answer(&resp).is_empty());
+        assert_eq!(
+            resp.section(SectionType::Authority).unwrap()[0].typ,
+            RRType::SOA
+        );
+    }
+
+    #[test]
+    fn test_resolve_limits() {
+        let (mut recursor, _) = build_recursor();
+        let err = resolve(&mut recursor, "loop1.example.com.").unwrap_err();
+        assert_eq!(err.downcast_ref::<Error>(), Some(&Error::Loop));
+
+        let mut recursor = build_recursor().0.max_queries(3);
+        let err = resolve(&mut recursor, "www.example.com.").unwrap_err();
+        assert_eq!(err.downcast_ref::<Error>(), Some(&Error::TooManyQueries));
+
+        let mut recursor = build_recursor().0.max_depth(0);
+        assert!(resolve(&mut recursor, "www.example.com.").is_ok());
+        let err = resolve(&mut recursor, "ext.example.com.").unwrap_err();
+        assert_eq!(err.downcast_ref::<Error>(), Some(&Error::TooDeep));
+    }
+
+    #[test]
+    fn test_follow_empty_cname() {
+        let mut cname = rrset("www.example.com. 3600 IN CNAME web.example.com.");
+        cname.rdatas.clear();
+        let mut current = name("www.example.com.");
+        let (mut chain, mut visited) = (Vec::new(), Vec::new());
+        let answer = vec![cname];
+        assert!(follow_answer(&answer, &mut current, RRType::A, &mut chain, &mut visited).is_err());
+    }
+}
@@ -20,6 +20,7 @@ impl Request {
         let mut header: Header = Default::default();
         header.set_flag(HeaderFlag::RecursionDesired, true);
         header.id = rand::random::<u16>();
+        header.qd_count = 1;
         Request {
             header,
             question: Question {
@@ -43,7 +44,7 @@ impl Request {
             bail!("request has answer");
         }
 
-        if header.an_count != 0 {
+        if header.ns_count != 0 {
             bail!("request has auth");
         }
 
@@ -53,6 +54,7 @@ impl Request {
     pub fn to_wire(&self, render: &mut MessageRender) -> Result<usize> {
         self.header.to_wire(render)?;
         self.question.to_wire(render)?;
+        render.sign()?;
         Ok(render.len())
     }
 }
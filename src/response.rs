@@ -1,4 +1,4 @@
-use crate::edns::Edns;
+use crate::edns::{Edns, MIN_UDP_SIZE};
 use crate::header::Header;
 use crate::header_flag::HeaderFlag;
 use crate::message_render::MessageRender;
@@ -11,6 +11,9 @@ use crate::util::InputBuffer;
 use anyhow::{bail, Result};
 use std::fmt;
 
+//cname or dname records followed for one query before giving up
+pub(crate) const MAX_CNAME_CHAIN: usize = 16;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum SectionType {
     Answer = 0,
@@ -28,9 +31,9 @@ pub const ALL_SECTIONS: [SectionType; 3] = [
 ];
 
 impl Section {
-    fn rr_count(&self) -> usize {
+    pub(crate) fn rr_count(&self) -> usize {
         self.0.as_ref().map_or(0, |rrsets| {
-            rrsets.iter().fold(0, |count, ref rrset| {
+            rrsets.iter().fold(0, |count, rrset| {
                 let rr_count = rrset.rr_count();
                 //for rrset has no rdata, count it as 1
                 if rr_count == 0 {
@@ -43,19 +46,37 @@ impl Section {
     }
 
     pub fn from_wire(buf: &mut InputBuffer, rr_count: u16, typ: SectionType) -> Result<Self> {
+        Self::do_from_wire(buf, rr_count, typ, RRset::from_wire)
+    }
+
+    //prerequisite and update section of update message
+    pub(crate) fn from_update_wire(
+        buf: &mut InputBuffer,
+        rr_count: u16,
+        typ: SectionType,
+    ) -> Result<Self> {
+        Self::do_from_wire(buf, rr_count, typ, RRset::from_update_wire)
+    }
+
+    fn do_from_wire(
+        buf: &mut InputBuffer,
+        rr_count: u16,
+        typ: SectionType,
+        rrset_from_wire: fn(&mut InputBuffer) -> Result<RRset>,
+    ) -> Result<Self> {
         if rr_count == 0 {
             return Ok(Section(None));
         }
 
         let mut rrsets = Vec::with_capacity(rr_count as usize);
-        let mut last_rrset = RRset::from_wire(buf)?;
+        let mut last_rrset = rrset_from_wire(buf)?;
         if last_rrset.typ == RRType::OPT && typ != SectionType::Additional {
             bail!("opt record must resides in addtional section")
         }
 
         for _ in 1..rr_count {
-            let mut rrset = RRset::from_wire(buf)?;
-            if rrset.is_same_rrset(&last_rrset) {
+            let mut rrset = rrset_from_wire(buf)?;
+            if rrset.is_same_rrset(&last_rrset) && rrset.class == last_rrset.class {
                 if rrset.typ == RRType::OPT {
                     bail!("opt rrset can only have one rr");
                 }
@@ -82,16 +103,13 @@ impl Section {
 impl fmt::Display for Section {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if let Some(rrsets) = self.0.as_ref() {
-            rrsets
-                .iter()
-                .map(|ref rrset| {
-                    if rrset.typ != RRType::OPT {
-                        write!(f, "{}", rrset)
-                    } else {
-                        Ok(())
-                    }
-                })
-                .collect()
+            rrsets.iter().try_for_each(|ref rrset| {
+                if rrset.typ != RRType::OPT {
+                    write!(f, "{}", rrset)
+                } else {
+                    Ok(())
+                }
+            })
         } else {
             Ok(())
         }
@@ -128,6 +146,11 @@ impl Response {
         let answer = Section::from_wire(buf, header.an_count, SectionType::Answer)?;
         let auth = Section::from_wire(buf, header.ns_count, SectionType::Authority)?;
         let additional = Section::from_wire(buf, header.ar_count, SectionType::Additional)?;
+        if let Some(rrset) = additional.0.as_ref().and_then(|rrsets| rrsets.last()) {
+            if rrset.typ == RRType::OPT {
+                Edns::from_rrset(rrset)?;
+            }
+        }
 
         Ok(Response {
             header,
@@ -137,18 +160,14 @@ impl Response {
     }
 
     pub fn get_edns(&self) -> Option<Edns> {
-        self.sections[2]
-            .0
-            .as_ref()
-            .map(|rrsets| {
-                if let Some(rrset) = rrsets.last() {
-                    if rrset.typ == RRType::OPT {
-                        return Some(Edns::from_rrset(rrset));
-                    }
+        self.sections[2].0.as_ref().and_then(|rrsets| {
+            if let Some(rrset) = rrsets.last() {
+                if rrset.typ == RRType::OPT {
+                    return Edns::from_rrset(rrset).ok();
                 }
-                None
-            })
-            .flatten()
+            }
+            None
+        })
     }
 
     pub fn recalculate_header(&mut self) {
@@ -159,29 +178,119 @@ impl Response {
     }
 
     pub fn to_wire(&self, render: &mut MessageRender) -> Result<usize> {
+        self.to_wire_with_limit(render, render.capacity())
+    }
+
+    //udp response is limited by the udp size of the query edns, rfc6891
+    //6.2.5
+    pub fn to_udp_wire(&self, render: &mut MessageRender, query: Option<&Edns>) -> Result<usize> {
+        let max_len = query.map_or(MIN_UDP_SIZE, |edns| edns.udp_size.max(MIN_UDP_SIZE));
+        self.to_wire_with_limit(render, max_len as usize)
+    }
+
+    //rrsets which don't fit are dropped as a whole, glue of referral goes
+    //before other additional data, tc is only set when answer, authority or
+    //glue is lost, rfc2181 9 and rfc9471, opt and tsig are always kept at the
+    //end, except tsig of the parsed response which is stale once data is
+    //dropped
+    pub fn to_wire_with_limit(&self, render: &mut MessageRender, max_len: usize) -> Result<usize> {
         self.header.to_wire(render)?;
         self.question.to_wire(render)?;
 
-        let pos_after_question = render.len();
-        //if has truncate, only keep question
-        for section in &self.sections {
-            if let Err(_) = section.to_wire(render) {
-                self.truncate(render, pos_after_question);
-                return Ok(pos_after_question);
+        let (tail, additionals): (Vec<&RRset>, Vec<&RRset>) = self
+            .section(SectionType::Additional)
+            .into_iter()
+            .flatten()
+            .partition(|rrset| matches!(rrset.typ, RRType::OPT | RRType::TSIG));
+        let (glues, additionals): (Vec<&RRset>, Vec<&RRset>) = additionals
+            .into_iter()
+            .partition(|rrset| self.is_glue(rrset));
+
+        //tsig rr of the parsed response is replaced when signing
+        let tail: Vec<&RRset> = tail
+            .into_iter()
+            .filter(|rrset| rrset.typ != RRType::TSIG || render.tsig().is_none())
+            .collect();
+        //space of the tail records is reserved by rendering them first
+        let pos = render.len();
+        for rrset in &tail {
+            rrset.to_wire(render)?;
+        }
+        let limit = max_len
+            .min(render.capacity())
+            .saturating_sub(render.len() - pos)
+            .saturating_sub(render.tsig().map_or(0, |signer| signer.tsig_len()));
+        render.truncate(pos)?;
+
+        let mut counts = [0; 3];
+        let mut truncated = false;
+        let mut dropped = false;
+        //the last one is whether tc is set when the section is lost
+        let sections = [
+            (
+                SectionType::Answer,
+                self.section(SectionType::Answer)
+                    .into_iter()
+                    .flatten()
+                    .collect(),
+                true,
+            ),
+            (
+                SectionType::Authority,
+                self.section(SectionType::Authority)
+                    .into_iter()
+                    .flatten()
+                    .collect(),
+                true,
+            ),
+            (SectionType::Additional, glues, true),
+            (SectionType::Additional, additionals, false),
+        ];
+        'render: for (section, rrsets, required) in sections {
+            for rrset in rrsets {
+                let pos = render.len();
+                if rrset.to_wire(render).is_err() || render.len() > limit {
+                    render.truncate(pos)?;
+                    dropped = true;
+                    truncated = required;
+                    break 'render;
+                }
+                counts[section as usize] += rrset.rr_count().max(1);
             }
         }
+        for rrset in tail {
+            if dropped && rrset.typ == RRType::TSIG {
+                continue;
+            }
+            rrset.to_wire(render)?;
+            counts[SectionType::Additional as usize] += rrset.rr_count().max(1);
+        }
+
+        if truncated {
+            let mut header = self.header.clone();
+            header.set_flag(HeaderFlag::Truncation, true);
+            render.write_u16_at(2, header.header_flag())?;
+        }
+        //skip question section count
+        render.write_u16_at(6, counts[0] as u16)?;
+        render.write_u16_at(8, counts[1] as u16)?;
+        render.write_u16_at(10, counts[2] as u16)?;
+        render.sign()?;
         Ok(render.len())
     }
 
-    fn truncate(&self, render: &mut MessageRender, pos: usize) {
-        let mut header = self.header.clone();
-        header.set_flag(HeaderFlag::Truncation, true);
-        render.write_u16_at(2, header.header_flag()).unwrap();
-        //skip question section count
-        render.write_u16_at(6, 0).unwrap();
-        render.write_u16_at(8, 0).unwrap();
-        render.write_u16_at(10, 0).unwrap();
-        render.truncate(pos).unwrap();
+    //address of name server in referral
+    fn is_glue(&self, rrset: &RRset) -> bool {
+        matches!(rrset.typ, RRType::A | RRType::AAAA)
+            && self.section_rrset_count(SectionType::Answer) == 0
+            && self
+                .section(SectionType::Authority)
+                .into_iter()
+                .flatten()
+                .filter(|ns| ns.typ == RRType::NS)
+                .flat_map(|ns| ns.rdatas.iter())
+                .filter_map(|rdata| rdata.target_name())
+                .any(|name| name.eq(&rrset.name))
     }
 
     pub fn section_mut(&mut self, section: SectionType) -> Option<&mut Vec<RRset>> {
@@ -205,7 +314,7 @@ impl Response {
     }
 
     pub fn iter(&self) -> ResponseIter<'_> {
-        ResponseIter::new(&self)
+        ResponseIter::new(self)
     }
 }
 
@@ -213,7 +322,7 @@ impl fmt::Display for Response {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "{}", self.header)?;
         if let Some(edns) = self.get_edns() {
-            write!(f, ";; OPT PSEUDOSECTION:\n{}", edns)?;
+            writeln!(f, ";; OPT PSEUDOSECTION:\n{}", edns)?;
         }
 
         writeln!(f, ";; QUESTION SECTION:\n{}\n", self.question)?;
@@ -245,7 +354,10 @@ impl<'a> IntoIterator for &'a Response {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::response_builder::build;
     use crate::rrset;
+    use crate::tsig::TsigSigner;
+    use crate::util::fixture::tsig_key;
     use std::str::FromStr;
     #[test]
     fn test_message_from_wire() {
@@ -299,7 +411,7 @@ mod test {
 
         let answers = msg.section(SectionType::Answer).unwrap();
         for (i, rrset_str) in rrset_strs.iter().enumerate() {
-            let rrset = rrset::RRset::from_str(*rrset_str).unwrap();
+            let rrset = rrset::RRset::from_str(rrset_str).unwrap();
             assert_eq!(answers[i], rrset);
         }
     }
@@ -354,7 +466,9 @@ mod test {
         assert!(truncated_msg.header.is_flag_set(HeaderFlag::Truncation));
         assert!(truncated_msg.header.an_count == 0);
         assert!(truncated_msg.header.ns_count == 0);
-        assert!(truncated_msg.header.ar_count == 0);
+        //opt is kept
+        assert!(truncated_msg.header.ar_count == 1);
+        assert_eq!(truncated_msg.get_edns(), msg.get_edns());
 
         let mut buf = [0; 1024];
         let mut render = MessageRender::new(&mut buf);
@@ -362,6 +476,90 @@ mod test {
         assert!(render.len() == raw.len());
     }
 
+    #[test]
+    fn test_partial_truncate() {
+        let mut msg = build(
+            "www.example.com.",
+            RRType::A,
+            vec![],
+            vec![vec![
+                "example.com. 3600 IN NS ns1.example.com.",
+                "example.com. 3600 IN NS ns2.example.com.",
+            ]],
+            vec![
+                vec!["ns1.example.com. 3600 IN A 1.1.1.1"],
+                vec!["ns2.example.com. 3600 IN A 2.2.2.2"],
+                vec![
+                    &format!("www.example.net. 3600 IN TXT \"{}\"", "a".repeat(250)),
+                    &format!("www.example.net. 3600 IN TXT \"{}\"", "b".repeat(250)),
+                ],
+            ],
+            Some(1232),
+        )
+        .unwrap();
+        //other additional data goes after glue
+        msg.section_mut(SectionType::Additional).unwrap().swap(0, 2);
+        let render_with_limit = |msg: &Response, max_len: usize| {
+            let mut buf = [0; 1024];
+            let mut render = MessageRender::new(&mut buf);
+            let len = msg.to_wire_with_limit(&mut render, max_len).unwrap();
+            assert!(len <= max_len);
+            Response::from_wire(&buf[..len]).unwrap()
+        };
+
+        let rendered = render_with_limit(&msg, 1024);
+        assert_eq!(rendered.header.ar_count, 5);
+        let rendered = render_with_limit(&msg, 200);
+        assert!(!rendered.header.is_flag_set(HeaderFlag::Truncation));
+        assert_eq!(rendered.header.ns_count, 2);
+        let additionals = rendered.section(SectionType::Additional).unwrap();
+        let types = additionals
+            .iter()
+            .map(|rrset| rrset.typ)
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec![RRType::A, RRType::A, RRType::OPT]);
+        let rendered = render_with_limit(&msg, 60);
+        assert!(rendered.header.is_flag_set(HeaderFlag::Truncation));
+        assert_eq!(rendered.header.ns_count, 0);
+        assert_eq!(rendered.header.ar_count, 1);
+        assert!(rendered.get_edns().is_some());
+
+        let mut buf = [0; 2048];
+        let mut render = MessageRender::new(&mut buf);
+        let len = msg
+            .to_udp_wire(&mut render, msg.get_edns().as_ref())
+            .unwrap();
+        assert_eq!(Response::from_wire(&buf[..len]).unwrap().header.ar_count, 5);
+        //limited to 512 when the query has no edns
+        let mut render = MessageRender::new(&mut buf);
+        let len = msg.to_udp_wire(&mut render, None).unwrap();
+        assert!(len <= 512);
+        let rendered = Response::from_wire(&buf[..len]).unwrap();
+        assert!(!rendered.header.is_flag_set(HeaderFlag::Truncation));
+        assert_eq!(rendered.header.ar_count, 3);
+
+        //losing glue of referral sets tc
+        let rendered = render_with_limit(&msg, 100);
+        assert!(rendered.header.is_flag_set(HeaderFlag::Truncation));
+        assert_eq!(rendered.header.ns_count, 2);
+        assert_eq!(rendered.header.ar_count, 2);
+
+        //tsig of parsed response is dropped with other data
+        let mut render = MessageRender::new(&mut buf);
+        render.set_tsig(TsigSigner::new(tsig_key(), None, 0));
+        let len = msg.to_wire(&mut render).unwrap();
+        let signed = Response::from_wire(&buf[..len]).unwrap();
+        let has_tsig = |resp: &Response| {
+            resp.section(SectionType::Additional)
+                .into_iter()
+                .flatten()
+                .any(|rrset| rrset.typ == RRType::TSIG)
+        };
+        assert!(has_tsig(&signed));
+        assert!(has_tsig(&render_with_limit(&signed, 1024)));
+        assert!(!has_tsig(&render_with_limit(&signed, 200)));
+    }
+
     #[test]
     fn test_big_message() {
         let raw = vec![
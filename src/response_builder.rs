@@ -1,9 +1,10 @@
-use crate::edns::Edns;
+use crate::edns::{Edns, MIN_UDP_SIZE};
 use crate::header_flag::HeaderFlag;
 use crate::name::Name;
 use crate::opcode::Opcode;
 use crate::question::Question;
 use crate::rcode::Rcode;
+use crate::rdata::RData;
 use crate::response::{Response, Section, SectionType};
 use crate::rr_type::RRType;
 use crate::rrset::RRset;
@@ -54,6 +55,20 @@ impl<'a> ResponseBuilder<'a> {
         self
     }
 
+    //response has edns only when the query has it, rfc6891 7
+    pub fn query_edns(&mut self, query: Option<&Edns>) -> &mut Self {
+        match query {
+            Some(query) => self.edns(Edns {
+                version: 0,
+                extened_rcode: 0,
+                udp_size: query.udp_size.max(MIN_UDP_SIZE),
+                dnssec_aware: false,
+                options: None,
+            }),
+            None => self,
+        }
+    }
+
     pub fn make_response(&mut self) -> &mut Self {
         self.set_flag(HeaderFlag::QueryRespone)
     }
@@ -97,11 +112,110 @@ impl<'a> ResponseBuilder<'a> {
         self
     }
 
+    //add address of the names in ns, mx and srv rdata of answer and
+    //authority section, lookup returns the rrset of the name and type if
+    //it's known
+    pub fn add_additional<F: FnMut(&Name, RRType) -> Option<RRset>>(
+        &mut self,
+        mut lookup: F,
+    ) -> &mut Self {
+        let mut names: Vec<Name> = Vec::new();
+        for section in [SectionType::Answer, SectionType::Authority] {
+            for rrset in self.resp.section(section).into_iter().flatten() {
+                for name in rrset.rdatas.iter().filter_map(|rdata| rdata.target_name()) {
+                    if !names.contains(name) {
+                        names.push(name.clone());
+                    }
+                }
+            }
+        }
+
+        let mut additionals = self
+            .resp
+            .take_section(SectionType::Additional)
+            .unwrap_or_default();
+        //opt and tsig have to be the last ones
+        let mut pos = additionals
+            .iter()
+            .position(|rrset| matches!(rrset.typ, RRType::OPT | RRType::TSIG))
+            .unwrap_or(additionals.len());
+        for name in &names {
+            for typ in [RRType::A, RRType::AAAA] {
+                if additionals
+                    .iter()
+                    .any(|rrset| rrset.typ == typ && rrset.name.eq(name))
+                {
+                    continue;
+                }
+                if let Some(rrset) = lookup(name, typ) {
+                    additionals.insert(pos, rrset);
+                    pos += 1;
+                }
+            }
+        }
+        if !additionals.is_empty() {
+            self.resp.sections[SectionType::Additional as usize] = Section(Some(additionals));
+        }
+        self
+    }
+
+    //add ns of the zone to authority section of positive answer, which is
+    //optional and skipped in minimal responses
+    pub fn add_authority_ns(&mut self, ns: RRset) -> &mut Self {
+        if self.resp.section_rrset_count(SectionType::Answer) > 0
+            && self.resp.section_rrset_count(SectionType::Authority) == 0
+        {
+            self.add_rrset(SectionType::Authority, ns);
+        }
+        self
+    }
+
+    //keep only the data required by the response, ns and glue of referral,
+    //soa of negative answer and the proofs of wildcard answer if dnssec_ok
+    //is set, opt and tsig are always kept
+    pub fn minimize(&mut self, dnssec_ok: bool) -> &mut Self {
+        //signed referral has ds or nsec proof and their rrsigs besides ns
+        let authority = self.resp.section(SectionType::Authority);
+        let has_type =
+            |typ| authority.is_some_and(|rrsets| rrsets.iter().any(|rrset| rrset.typ == typ));
+        let referral = self.resp.section_rrset_count(SectionType::Answer) == 0
+            && has_type(RRType::NS)
+            && !has_type(RRType::SOA);
+        if referral {
+            return self;
+        }
+        if self.resp.section_rrset_count(SectionType::Answer) > 0 {
+            if dnssec_ok {
+                self.remove_rrset_by(SectionType::Authority, |rrset| !is_denial(rrset));
+            }
+            if !dnssec_ok || self.resp.section_rrset_count(SectionType::Authority) == 0 {
+                self.clear_section(SectionType::Authority);
+            }
+        }
+        self.remove_rrset_by(SectionType::Additional, |rrset| {
+            !matches!(rrset.typ, RRType::OPT | RRType::TSIG)
+        });
+        if self.resp.section_rrset_count(SectionType::Additional) == 0 {
+            self.clear_section(SectionType::Additional);
+        }
+        self
+    }
+
     pub fn done(&mut self) {
         self.resp.recalculate_header();
     }
 }
 
+//nsec, nsec3 and their signatures
+fn is_denial(rrset: &RRset) -> bool {
+    let denial = |typ| matches!(typ, RRType::NSEC | RRType::NSEC3);
+    denial(rrset.typ)
+        || rrset
+            .rdatas
+            .iter()
+            .any(|rdata| matches!(rdata, RData::RRSig(sig) if denial(sig.covered)))
+}
+
 pub fn build(
     name: &str,
     typ: RRType,
@@ -300,4 +414,97 @@ mod test {
             .set_flag(HeaderFlag::RecursionDesired, false);
         assert_eq!(target, build_msg);
     }
+
+    #[test]
+    fn test_additional_and_minimize() {
+        let mut resp = build(
+            "example.com.",
+            RRType::MX,
+            vec![vec![
+                "example.com. 300 IN MX 10 mail.example.com.",
+                "example.com. 300 IN MX 20 mail.example.net.",
+            ]],
+            vec![],
+            vec![],
+            Some(1232),
+        )
+        .unwrap();
+        let ns = RRset::from_str("example.com. 3600 IN NS ns1.example.com.").unwrap();
+        let lookup = |name: &Name, typ: RRType| match (name.to_string().as_str(), typ) {
+            ("mail.example.com.", RRType::A) => {
+                Some(RRset::from_str("mail.example.com. 300 IN A 1.1.1.1").unwrap())
+            }
+            ("ns1.example.com.", RRType::AAAA) => {
+                Some(RRset::from_str("ns1.example.com. 300 IN AAAA ::1").unwrap())
+            }
+            _ => None,
+        };
+        ResponseBuilder::new(&mut resp)
+            .add_authority_ns(ns.clone())
+            .add_additional(lookup)
+            .add_additional(lookup)
+            .done();
+        assert_eq!(resp.section(SectionType::Authority).unwrap(), &vec![ns]);
+        let additionals = resp.section(SectionType::Additional).unwrap();
+        let types = additionals
+            .iter()
+            .map(|rrset| rrset.typ)
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec![RRType::A, RRType::AAAA, RRType::OPT]);
+        assert_eq!(additionals[0].rdatas.len(), 1);
+        assert_eq!(resp.get_edns().unwrap().udp_size, 1232);
+
+        let mut signed = resp.clone();
+        ResponseBuilder::new(&mut resp).minimize(false).done();
+        assert_eq!(resp.header.ns_count, 0);
+        assert_eq!(resp.header.ar_count, 1);
+        assert!(resp.get_edns().is_some());
+
+        //proofs of wildcard answer are kept for dnssec
+        let nsec =
+            RRset::from_str("a.example.com. 300 IN NSEC z.example.com. A RRSIG NSEC").unwrap();
+        let sig = RRset::from_str(
+            "a.example.com. 300 IN RRSIG NSEC 13 3 300 20300101000000 20200101000000 12345 example.com. AAAA",
+        )
+        .unwrap();
+        ResponseBuilder::new(&mut signed)
+            .add_rrset(SectionType::Authority, nsec.clone())
+            .add_rrset(SectionType::Authority, sig.clone())
+            .minimize(true)
+            .done();
+        assert_eq!(
+            signed.section(SectionType::Authority).unwrap(),
+            &vec![nsec, sig]
+        );
+
+        //glue of referral is kept
+        let mut resp = build(
+            "www.sub.example.com.",
+            RRType::A,
+            vec![],
+            vec![vec!["sub.example.com. 3600 IN NS ns.sub.example.com."]],
+            vec![vec!["ns.sub.example.com. 3600 IN A 2.2.2.2"]],
+            None,
+        )
+        .unwrap();
+        ResponseBuilder::new(&mut resp).minimize(false).done();
+        assert_eq!(resp.header.ar_count, 1);
+
+        //so is glue of signed referral
+        let mut resp = build(
+            "www.sub.example.com.",
+            RRType::A,
+            vec![],
+            vec![
+                vec!["sub.example.com. 3600 IN NS ns.sub.example.com."],
+                vec!["sub.example.com. 3600 IN DS 12345 13 2 abcd"],
+            ],
+            vec![vec!["ns.sub.example.com. 3600 IN A 2.2.2.2"]],
+            None,
+        )
+        .unwrap();
+        ResponseBuilder::new(&mut resp).minimize(true).done();
+        assert_eq!(resp.header.ns_count, 2);
+        assert_eq!(resp.header.ar_count, 1);
+    }
 }
@@ -169,7 +169,7 @@ mod test {
                     assert_eq!(section, SectionType::Additional);
                 }
                 _ => {
-                    assert!(false);
+                    unreachable!();
                 }
             });
 
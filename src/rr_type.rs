@@ -5,7 +5,7 @@ use std::cmp::Ordering;
 use std::fmt;
 use std::str::FromStr;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[allow(dead_code)]
 pub enum RRType {
     A,
@@ -115,7 +115,7 @@ impl RRType {
             RRType::AAAA => "AAAA",
             RRType::SRV => "SRV",
             RRType::NAPTR => "NAPTR",
-            RRType::CERT => "URI",
+            RRType::CERT => "CERT",
             RRType::DNAME => "DNAME",
             RRType::OPT => "OPT",
             RRType::DS => "DS",
@@ -186,7 +186,7 @@ impl FromStr for RRType {
 
 impl PartialOrd for RRType {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.as_u16().cmp(&other.as_u16()))
+        Some(self.cmp(other))
     }
 }
 
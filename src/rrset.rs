@@ -4,7 +4,7 @@ use crate::rdata::RData;
 use crate::rr_class::RRClass;
 use crate::rr_type::RRType;
 use crate::util::{InputBuffer, StringBuffer};
-use anyhow::{self, bail, Result};
+use anyhow::{anyhow, bail, ensure, Result};
 use std::fmt;
 use std::str::FromStr;
 
@@ -24,10 +24,43 @@ impl RRTtl {
 impl FromStr for RRTtl {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
-        match s.parse::<u32>() {
-            Ok(num) => Ok(RRTtl(num)),
-            Err(e) => bail!("ttl isn't a valid number:{}", e),
+        if let Ok(num) = s.parse::<u32>() {
+            return Ok(RRTtl(num));
         }
+
+        //ttl with units like 1h30m, trailing digits without unit are seconds
+        let too_large = || anyhow!("ttl {} is too large", s);
+        let mut ttl: u32 = 0;
+        let mut num: Option<u32> = None;
+        for c in s.chars() {
+            if let Some(d) = c.to_digit(10) {
+                let n = num
+                    .unwrap_or(0)
+                    .checked_mul(10)
+                    .and_then(|n| n.checked_add(d));
+                num = Some(n.ok_or_else(too_large)?);
+            } else {
+                let unit = match c.to_ascii_lowercase() {
+                    's' => 1,
+                    'm' => 60,
+                    'h' => 3600,
+                    'd' => 86400,
+                    'w' => 604800,
+                    _ => bail!("ttl isn't a valid number:{}", s),
+                };
+                let n = num
+                    .take()
+                    .ok_or(anyhow!("ttl isn't a valid number:{}", s))?;
+                ttl = n
+                    .checked_mul(unit)
+                    .and_then(|n| ttl.checked_add(n))
+                    .ok_or_else(too_large)?;
+            }
+        }
+        ensure!(!s.is_empty(), "ttl is empty");
+        ttl.checked_add(num.unwrap_or(0))
+            .map(RRTtl)
+            .ok_or_else(too_large)
     }
 }
 
@@ -48,6 +81,15 @@ pub struct RRset {
 
 impl RRset {
     pub fn from_wire(buf: &mut InputBuffer) -> Result<Self> {
+        Self::do_from_wire(buf, false)
+    }
+
+    //update message use class any/none rr without rdata, rfc2136 2.4, 2.5
+    pub(crate) fn from_update_wire(buf: &mut InputBuffer) -> Result<Self> {
+        Self::do_from_wire(buf, true)
+    }
+
+    fn do_from_wire(buf: &mut InputBuffer, is_update: bool) -> Result<Self> {
         let name = Name::from_wire(buf)?;
         let typ = RRType::from_wire(buf)?;
         let class = RRClass::from_wire(buf)?;
@@ -55,7 +97,8 @@ impl RRset {
         let rdlen = buf.read_u16()?;
         let mut rdatas = Vec::with_capacity(1);
         if rdlen == 0 {
-            if typ != RRType::OPT {
+            let update_rr = is_update && (class == RRClass::ANY || class == RRClass::NONE);
+            if typ != RRType::OPT && !update_rr {
                 bail!("only opt record could has zero rdata");
             }
         } else {
@@ -105,7 +148,7 @@ impl RRset {
                 self.name.to_wire(render)?;
                 self.typ.to_wire(render)?;
                 self.class.to_wire(render)?;
-                self.ttl.to_wire(render)?;
+                self.rr_ttl(rdata).to_wire(render)?;
                 let pos = render.len();
                 render.skip(2)?;
                 rdata.to_wire(render)?;
@@ -116,10 +159,19 @@ impl RRset {
         Ok(())
     }
 
-    fn header(&self) -> String {
+    //rrsigs of one owner share one rrset, each of them takes the ttl of the
+    //rrset it covers which is kept in original ttl, rfc4034 3
+    pub fn rr_ttl(&self, rdata: &RData) -> RRTtl {
+        match rdata {
+            RData::RRSig(sig) => RRTtl(self.ttl.0.min(sig.original_ttl)),
+            _ => self.ttl,
+        }
+    }
+
+    fn header(&self, ttl: RRTtl) -> String {
         [
             self.name.to_string(),
-            self.ttl.to_string(),
+            ttl.to_string(),
             self.class.to_string(),
             self.typ.to_string(),
         ]
@@ -222,16 +274,30 @@ impl FromStr for RRset {
 
 impl fmt::Display for RRset {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.rdatas.is_empty() {
+            return writeln!(f, "{}", self.header(self.ttl));
+        }
         self.rdatas
             .iter()
-            .map(|rdata| writeln!(f, "{}\t{}", self.header(), rdata))
-            .collect()
+            .try_for_each(|rdata| writeln!(f, "{}\t{}", self.header(self.rr_ttl(rdata)), rdata))
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    #[test]
+    fn test_ttl_from_str() {
+        assert_eq!(RRTtl::from_str("3600").unwrap(), RRTtl(3600));
+        assert_eq!(RRTtl::from_str("1h").unwrap(), RRTtl(3600));
+        assert_eq!(RRTtl::from_str("1H30m").unwrap(), RRTtl(5400));
+        assert_eq!(RRTtl::from_str("1w2d10").unwrap(), RRTtl(777610));
+        assert!(RRTtl::from_str("h").is_err());
+        assert!(RRTtl::from_str("A").is_err());
+        assert!(RRTtl::from_str("").is_err());
+        assert!(RRTtl::from_str("10000w").is_err());
+    }
+
     #[test]
     fn test_rrset_eq() {
         let rrset1_str = vec![
@@ -0,0 +1,742 @@
+use crate::error::Error;
+use crate::header::Header;
+use crate::message_render::MessageRender;
+use crate::name::Name;
+use crate::opcode::Opcode;
+use crate::question::Question;
+use crate::rdata::RData;
+use crate::rdatas::TSig;
+use crate::rr_class::RRClass;
+use crate::rr_type::RRType;
+use crate::rrset::{RRTtl, RRset};
+use crate::util::InputBuffer;
+use anyhow::{bail, Result};
+use ring::{constant_time, hmac};
+use std::fmt;
+use std::str::FromStr;
+
+pub const DEFAULT_FUDGE: u16 = 300;
+//rfc8945 5.3.1, client may accept at most 99 unsigned messages in a row
+const MAX_UNSIGNED_MESSAGES: usize = 99;
+const MIN_MAC_LEN: usize = 10;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TsigAlgorithm {
+    HmacSha256,
+    HmacSha512,
+}
+
+impl TsigAlgorithm {
+    pub fn from_name(name: &Name) -> Option<Self> {
+        [TsigAlgorithm::HmacSha256, TsigAlgorithm::HmacSha512]
+            .iter()
+            .find(|alg| alg.to_name().eq(name))
+            .copied()
+    }
+
+    pub fn to_name(self) -> Name {
+        Name::new(self.to_str()).unwrap()
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            TsigAlgorithm::HmacSha256 => "hmac-sha256.",
+            TsigAlgorithm::HmacSha512 => "hmac-sha512.",
+        }
+    }
+
+    pub fn mac_len(self) -> usize {
+        match self {
+            TsigAlgorithm::HmacSha256 => 32,
+            TsigAlgorithm::HmacSha512 => 64,
+        }
+    }
+
+    fn hmac_algorithm(self) -> hmac::Algorithm {
+        match self {
+            TsigAlgorithm::HmacSha256 => hmac::HMAC_SHA256,
+            TsigAlgorithm::HmacSha512 => hmac::HMAC_SHA512,
+        }
+    }
+}
+
+impl FromStr for TsigAlgorithm {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match Name::new(s).ok().and_then(|name| Self::from_name(&name)) {
+            Some(alg) => Ok(alg),
+            None => bail!("tsig algorithm {} doesn't support", s),
+        }
+    }
+}
+
+impl fmt::Display for TsigAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.to_str())
+    }
+}
+
+//errors carried in the error field of tsig rdata
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TsigError {
+    BadSig,
+    BadKey,
+    BadTime,
+    BadTrunc,
+}
+
+impl TsigError {
+    pub fn new(value: u16) -> Option<Self> {
+        match value {
+            16 => Some(TsigError::BadSig),
+            17 => Some(TsigError::BadKey),
+            18 => Some(TsigError::BadTime),
+            22 => Some(TsigError::BadTrunc),
+            _ => None,
+        }
+    }
+
+    pub fn to_u16(self) -> u16 {
+        match self {
+            TsigError::BadSig => 16,
+            TsigError::BadKey => 17,
+            TsigError::BadTime => 18,
+            TsigError::BadTrunc => 22,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            TsigError::BadSig => "BADSIG",
+            TsigError::BadKey => "BADKEY",
+            TsigError::BadTime => "BADTIME",
+            TsigError::BadTrunc => "BADTRUNC",
+        }
+    }
+}
+
+impl fmt::Display for TsigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.to_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TsigKey {
+    pub name: Name,
+    pub algorithm: TsigAlgorithm,
+    key: hmac::Key,
+}
+
+impl TsigKey {
+    pub fn new(name: Name, algorithm: TsigAlgorithm, secret: &[u8]) -> Self {
+        TsigKey {
+            name,
+            algorithm,
+            key: hmac::Key::new(algorithm.hmac_algorithm(), secret),
+        }
+    }
+
+    pub fn from_base64(name: Name, algorithm: TsigAlgorithm, secret: &str) -> Result<Self> {
+        match base64::decode(secret) {
+            Ok(secret) => Ok(Self::new(name, algorithm, &secret)),
+            Err(e) => bail!("tsig secret isn't valid base64: {}", e),
+        }
+    }
+
+    //verify the tsig rr at the end of the message, and return it
+    pub fn verify(&self, raw: &[u8], request_mac: Option<&[u8]>, now: u64) -> Result<TSig> {
+        match split_tsig(raw)? {
+            Some((data, key_name, tsig)) => {
+                self.verify_tsig(&[&data], request_mac, &key_name, tsig, false, now)
+            }
+            None => bail!("message isn't signed"),
+        }
+    }
+
+    fn new_tsig(
+        &self,
+        render: &MessageRender,
+        time_signed: u64,
+        error: u16,
+        other: Vec<u8>,
+    ) -> Result<TSig> {
+        if render.len() < 12 {
+            bail!("message header isn't rendered");
+        }
+        let data = render.data();
+        Ok(TSig {
+            algorithm: self.algorithm.to_name(),
+            time_signed,
+            fudge: DEFAULT_FUDGE,
+            mac: Vec::new(),
+            original_id: u16::from_be_bytes([data[0], data[1]]),
+            error,
+            other,
+        })
+    }
+
+    fn sign_tsig(
+        &self,
+        render: &mut MessageRender,
+        prior_mac: Option<&[u8]>,
+        unsigned: &[&[u8]],
+        mut tsig: TSig,
+        timers_only: bool,
+    ) -> Result<Vec<u8>> {
+        let mut data = unsigned.to_vec();
+        data.push(render.data());
+        let mac = self.digest(prior_mac, &data, &tsig, timers_only);
+        tsig.mac = mac.clone();
+        append_tsig(render, &self.name, tsig)?;
+        Ok(mac)
+    }
+
+    fn verify_tsig(
+        &self,
+        data: &[&[u8]],
+        prior_mac: Option<&[u8]>,
+        key_name: &Name,
+        tsig: TSig,
+        timers_only: bool,
+        now: u64,
+    ) -> Result<TSig> {
+        if !key_name.eq(&self.name) || !tsig.algorithm.eq(&self.algorithm.to_name()) {
+            bail!(Error::Tsig(TsigError::BadKey));
+        }
+
+        if tsig.mac.is_empty() {
+            if let Some(err) = TsigError::new(tsig.error) {
+                bail!(Error::Tsig(err));
+            }
+            bail!(Error::Tsig(TsigError::BadSig));
+        }
+
+        //mac is verified before the truncation is checked, rfc8945 5.2.2
+        let full_len = self.algorithm.mac_len();
+        if tsig.mac.len() > full_len {
+            bail!(Error::Tsig(TsigError::BadSig));
+        }
+        let mac = self.digest(prior_mac, data, &tsig, timers_only);
+        if constant_time::verify_slices_are_equal(&mac[..tsig.mac.len()], &tsig.mac).is_err() {
+            bail!(Error::Tsig(TsigError::BadSig));
+        }
+        if tsig.mac.len() < MIN_MAC_LEN.max(full_len / 2) {
+            bail!(Error::Tsig(TsigError::BadTrunc));
+        }
+
+        if let Some(err) = TsigError::new(tsig.error) {
+            bail!(Error::Tsig(err));
+        }
+
+        let diff = now.abs_diff(tsig.time_signed);
+        if diff > tsig.fudge as u64 {
+            bail!(Error::Tsig(TsigError::BadTime));
+        }
+        Ok(tsig)
+    }
+
+    fn digest(
+        &self,
+        prior_mac: Option<&[u8]>,
+        data: &[&[u8]],
+        tsig: &TSig,
+        timers_only: bool,
+    ) -> Vec<u8> {
+        let mut ctx = hmac::Context::with_key(&self.key);
+        if let Some(mac) = prior_mac {
+            ctx.update(&(mac.len() as u16).to_be_bytes());
+            ctx.update(mac);
+        }
+        for d in data {
+            ctx.update(d);
+        }
+
+        if !timers_only {
+            let mut name = self.name.clone();
+            name.as_lowercase();
+            ctx.update(name.raw_data());
+            ctx.update(&RRClass::ANY.to_u16().to_be_bytes());
+            ctx.update(&0u32.to_be_bytes());
+            let mut algorithm = tsig.algorithm.clone();
+            algorithm.as_lowercase();
+            ctx.update(algorithm.raw_data());
+        }
+        ctx.update(&tsig.time_signed.to_be_bytes()[2..]);
+        ctx.update(&tsig.fudge.to_be_bytes());
+        if !timers_only {
+            ctx.update(&tsig.error.to_be_bytes());
+            ctx.update(&(tsig.other.len() as u16).to_be_bytes());
+            ctx.update(&tsig.other);
+        }
+        ctx.sign().as_ref().to_vec()
+    }
+}
+
+fn append_tsig(render: &mut MessageRender, key_name: &Name, tsig: TSig) -> Result<()> {
+    let ar_count = {
+        let data = render.data();
+        u16::from_be_bytes([data[10], data[11]])
+    };
+    RRset {
+        name: key_name.clone(),
+        typ: RRType::TSIG,
+        class: RRClass::ANY,
+        ttl: RRTtl(0),
+        rdatas: vec![RData::TSig(Box::new(tsig))],
+    }
+    .to_wire(render)?;
+    render.write_u16_at(10, ar_count + 1)
+}
+
+//return the message without tsig rr which is used to calculate the mac,
+//with original id restored and arcount decreased
+fn split_tsig(raw: &[u8]) -> Result<Option<(Vec<u8>, Name, TSig)>> {
+    let buf = &mut InputBuffer::new(raw);
+    let header = Header::from_wire(buf)?;
+    for _ in 0..header.qd_count {
+        Question::from_wire(buf)?;
+    }
+
+    let rr_count = header.an_count as usize + header.ns_count as usize + header.ar_count as usize;
+    let rrset_from_wire = if header.opcode == Opcode::Update {
+        RRset::from_update_wire
+    } else {
+        RRset::from_wire
+    };
+    let mut last_rr_pos = buf.position();
+    let mut last_rrset = None;
+    for i in 0..rr_count {
+        if let Some(RRset {
+            typ: RRType::TSIG, ..
+        }) = last_rrset
+        {
+            bail!("tsig rr isn't the last rr");
+        }
+        last_rr_pos = buf.position();
+        let rrset = rrset_from_wire(buf)?;
+        if rrset.typ == RRType::TSIG && i < rr_count - header.ar_count as usize {
+            bail!("tsig rr isn't in additional section");
+        }
+        last_rrset = Some(rrset);
+    }
+
+    match last_rrset {
+        Some(mut rrset) if rrset.typ == RRType::TSIG => {
+            if rrset.class != RRClass::ANY || rrset.rdatas.len() != 1 {
+                bail!("invalid tsig rr");
+            }
+            let tsig = match rrset.rdatas.pop() {
+                Some(RData::TSig(tsig)) => *tsig,
+                _ => bail!("invalid tsig rr"),
+            };
+            let mut data = raw[0..last_rr_pos].to_vec();
+            data[0..2].copy_from_slice(&tsig.original_id.to_be_bytes());
+            data[10..12].copy_from_slice(&(header.ar_count - 1).to_be_bytes());
+            Ok(Some((data, rrset.name, tsig)))
+        }
+        _ => Ok(None),
+    }
+}
+
+//signing state attached to MessageRender, the tsig rr is appended when the
+//message is rendered. messages rendered later with the same signer form a
+//stream like zone transfer, the first one covers all tsig variables and the
+//rest only timers, rfc8945 5.3.1
+#[derive(Debug, Clone)]
+pub struct TsigSigner {
+    key: TsigKey,
+    prior_mac: Option<Vec<u8>>,
+    time_signed: u64,
+    error: Option<TsigError>,
+    unsigned: Vec<Vec<u8>>,
+    signed_count: usize,
+    skip_next: bool,
+}
+
+impl TsigSigner {
+    //request_mac is the mac of the verified request when signing response
+    pub fn new(key: TsigKey, request_mac: Option<&[u8]>, time_signed: u64) -> Self {
+        TsigSigner {
+            key,
+            prior_mac: request_mac.map(|mac| mac.to_vec()),
+            time_signed,
+            error: None,
+            unsigned: Vec::new(),
+            signed_count: 0,
+            skip_next: false,
+        }
+    }
+
+    //sign response with error, BADSIG and BADKEY response isn't signed
+    //BADTIME response carries server time in other data
+    pub fn error(mut self, error: TsigError) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    //mac of the last signed message, which is needed to verify the response
+    pub fn mac(&self) -> &[u8] {
+        match self.prior_mac {
+            Some(ref mac) if self.signed_count > 0 => mac,
+            _ => &[],
+        }
+    }
+
+    //leave the next rendered message unsigned, it will be covered by the
+    //next signed message
+    pub fn skip_next(&mut self) -> Result<()> {
+        if self.signed_count == 0 {
+            bail!("first message must be signed");
+        }
+        if self.unsigned.len() == MAX_UNSIGNED_MESSAGES {
+            bail!("too many unsigned messages");
+        }
+        self.skip_next = true;
+        Ok(())
+    }
+
+    //space reserved for the tsig rr when rendering is limited
+    pub fn tsig_len(&self) -> usize {
+        //type, class, ttl and rdlen
+        let rr_header_len = 10;
+        //time signed, fudge, mac size, original id, error and other len
+        let fixed_len = 16;
+        let other_len = if self.error == Some(TsigError::BadTime) {
+            6
+        } else {
+            0
+        };
+        self.key.name.len()
+            + rr_header_len
+            + self.key.algorithm.to_name().len()
+            + fixed_len
+            + self.key.algorithm.mac_len()
+            + other_len
+    }
+
+    pub(crate) fn sign(&mut self, render: &mut MessageRender) -> Result<()> {
+        if self.skip_next {
+            self.skip_next = false;
+            self.unsigned.push(render.data().to_vec());
+            return Ok(());
+        }
+
+        let error = self.error.map_or(0, |err| err.to_u16());
+        let other = if self.error == Some(TsigError::BadTime) {
+            self.time_signed.to_be_bytes()[2..].to_vec()
+        } else {
+            Vec::new()
+        };
+        let tsig = self.key.new_tsig(render, self.time_signed, error, other)?;
+        let mac = match self.error {
+            Some(TsigError::BadSig) | Some(TsigError::BadKey) => {
+                append_tsig(render, &self.key.name, tsig)?;
+                Vec::new()
+            }
+            _ => {
+                let unsigned: Vec<&[u8]> = self.unsigned.iter().map(|msg| msg.as_slice()).collect();
+                self.key.sign_tsig(
+                    render,
+                    self.prior_mac.as_deref(),
+                    &unsigned,
+                    tsig,
+                    self.signed_count > 0,
+                )?
+            }
+        };
+        self.prior_mac = Some(mac);
+        self.unsigned.clear();
+        self.signed_count += 1;
+        Ok(())
+    }
+}
+
+//verify a multi-message response, the first message must be signed,
+//unsigned messages in between are covered by the mac of next signed one
+pub struct TsigStreamVerifier<'a> {
+    key: &'a TsigKey,
+    prior_mac: Vec<u8>,
+    unsigned: Vec<Vec<u8>>,
+    signed_count: usize,
+}
+
+impl<'a> TsigStreamVerifier<'a> {
+    pub fn new(key: &'a TsigKey, request_mac: &[u8]) -> Self {
+        TsigStreamVerifier {
+            key,
+            prior_mac: request_mac.to_vec(),
+            unsigned: Vec::new(),
+            signed_count: 0,
+        }
+    }
+
+    pub fn verify(&mut self, raw: &[u8], now: u64) -> Result<()> {
+        match split_tsig(raw)? {
+            None => {
+                if self.signed_count == 0 {
+                    bail!("first message isn't signed");
+                }
+                if self.unsigned.len() == MAX_UNSIGNED_MESSAGES {
+                    bail!("too many unsigned messages");
+                }
+                self.unsigned.push(raw.to_vec());
+                Ok(())
+            }
+            Some((data, key_name, tsig)) => {
+                let mut messages: Vec<&[u8]> =
+                    self.unsigned.iter().map(|msg| msg.as_slice()).collect();
+                messages.push(&data);
+                let first = self.signed_count == 0;
+                let tsig = self.key.verify_tsig(
+                    &messages,
+                    Some(&self.prior_mac),
+                    &key_name,
+                    tsig,
+                    !first,
+                    now,
+                )?;
+                self.prior_mac = tsig.mac;
+                self.unsigned.clear();
+                self.signed_count += 1;
+                Ok(())
+            }
+        }
+    }
+
+    //the last message of the stream must be signed
+    pub fn finish(&self) -> Result<()> {
+        if self.signed_count == 0 || !self.unsigned.is_empty() {
+            bail!("last message isn't signed");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::header_flag::HeaderFlag;
+    use crate::message::Message;
+    use crate::request::Request;
+    use crate::response::Response;
+    use crate::response::SectionType;
+    use crate::response_builder::ResponseBuilder;
+    use crate::util::fixture::tsig_key;
+    use crate::util::hex::to_hex;
+
+    fn render_request(key: &TsigKey, time_signed: u64) -> (Vec<u8>, Vec<u8>) {
+        let mut req = Request::new(Name::new("example.com.").unwrap(), RRType::SOA);
+        req.header.id = 0x1234;
+        let mut buf = [0; 512];
+        let mut render = MessageRender::new(&mut buf);
+        render.set_tsig(TsigSigner::new(key.clone(), None, time_signed));
+        req.to_wire(&mut render).unwrap();
+        let mac = render.tsig().unwrap().mac().to_vec();
+        let len = render.len();
+        (buf[0..len].to_vec(), mac)
+    }
+
+    fn render_response(req: &[u8], answer: &str) -> Response {
+        let req = Message::from_wire(req).unwrap();
+        let mut resp = Response::with_question(req.questions[0].name.clone(), req.questions[0].typ);
+        let mut builder = ResponseBuilder::new(&mut resp);
+        builder
+            .id(req.header.id)
+            .make_response()
+            .set_flag(HeaderFlag::AuthAnswer)
+            .add_rrset(SectionType::Answer, RRset::from_str(answer).unwrap())
+            .done();
+        resp
+    }
+
+    fn to_wire(resp: &Response, signer: TsigSigner) -> Vec<u8> {
+        let mut buf = [0; 512];
+        let mut render = MessageRender::new(&mut buf);
+        render.set_tsig(signer);
+        resp.to_wire(&mut render).unwrap();
+        let len = render.len();
+        buf[0..len].to_vec()
+    }
+
+    #[test]
+    fn test_sign_request() {
+        let key = tsig_key();
+        let (raw, mac) = render_request(&key, 1600000000);
+        //mac is calculated with hmac-sha256 over the request and tsig variables
+        assert_eq!(
+            to_hex(&mac),
+            "ea9f82c9ad362c1495df8249618d3f660e9abe83a8129cf30914d8d66917a808"
+        );
+
+        let msg = Message::from_wire(&raw).unwrap();
+        assert_eq!(msg.header.ar_count, 1);
+        let tsig = msg.section(SectionType::Additional).unwrap()[0].clone();
+        assert_eq!(tsig.typ, RRType::TSIG);
+        assert_eq!(tsig.class, RRClass::ANY);
+
+        let tsig = key.verify(&raw, None, 1600000100).unwrap();
+        assert_eq!(tsig.mac, mac);
+        assert_eq!(tsig.original_id, 0x1234);
+
+        let err = key.verify(&raw, None, 1600000301).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Tsig(TsigError::BadTime))
+        );
+
+        let mut tampered = raw.clone();
+        tampered[13] = b'E';
+        let err = key.verify(&tampered, None, 1600000000).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Tsig(TsigError::BadSig))
+        );
+
+        let other_key = TsigKey::new(
+            Name::new("other-key.").unwrap(),
+            TsigAlgorithm::HmacSha256,
+            b"secret",
+        );
+        let err = other_key.verify(&raw, None, 1600000000).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Tsig(TsigError::BadKey))
+        );
+
+        let other_alg = TsigKey::new(
+            Name::new("tsig-key.").unwrap(),
+            TsigAlgorithm::HmacSha512,
+            b"secret-for-tsig-test",
+        );
+        let err = other_alg.verify(&raw, None, 1600000000).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Tsig(TsigError::BadKey))
+        );
+    }
+
+    #[test]
+    fn test_truncated_mac() {
+        let key = tsig_key();
+        let now = 1600000000;
+        let (raw, _) = render_request(&key, now);
+        let truncate_mac = |raw: &[u8], len: usize, flip: bool| {
+            let (data, key_name, mut tsig) = split_tsig(raw).unwrap().unwrap();
+            tsig.mac.truncate(len);
+            if flip {
+                tsig.mac[0] ^= 0xff;
+            }
+            let mut buf = [0; 512];
+            let mut render = MessageRender::new(&mut buf);
+            render.write_bytes(&data).unwrap();
+            append_tsig(&mut render, &key_name, tsig).unwrap();
+            render.data().to_vec()
+        };
+
+        assert!(key
+            .verify(&truncate_mac(&raw, 16, false), None, now)
+            .is_ok());
+        //mac is checked before the length of it
+        let err = key
+            .verify(&truncate_mac(&raw, 8, true), None, now)
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Tsig(TsigError::BadSig))
+        );
+        let err = key
+            .verify(&truncate_mac(&raw, 8, false), None, now)
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Tsig(TsigError::BadTrunc))
+        );
+    }
+
+    #[test]
+    fn test_sign_response() {
+        let key = tsig_key();
+        let now = 1600000000;
+        let (req, req_mac) = render_request(&key, now);
+        key.verify(&req, None, now).unwrap();
+
+        let resp = render_response(&req, "example.com. 3600 IN A 1.1.1.1");
+        let raw = to_wire(&resp, TsigSigner::new(key.clone(), Some(&req_mac), now + 1));
+        let parsed = Response::from_wire(&raw).unwrap();
+        assert_eq!(parsed.header.id, 0x1234);
+        assert!(key.verify(&raw, Some(&req_mac), now + 1).is_ok());
+        let err = key.verify(&raw, None, now + 1).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Tsig(TsigError::BadSig))
+        );
+
+        let signer = TsigSigner::new(key.clone(), Some(&req_mac), now).error(TsigError::BadKey);
+        let raw = to_wire(&resp, signer);
+        let err = key.verify(&raw, Some(&req_mac), now).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Tsig(TsigError::BadKey))
+        );
+
+        let signer = TsigSigner::new(key.clone(), Some(&req_mac), now).error(TsigError::BadTime);
+        let raw = to_wire(&resp, signer);
+        let err = key.verify(&raw, Some(&req_mac), now).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Tsig(TsigError::BadTime))
+        );
+    }
+
+    #[test]
+    fn test_sign_stream() {
+        let key = tsig_key();
+        let now = 1600000000;
+        let (req, req_mac) = render_request(&key, now);
+
+        let answers = [
+            "example.com. 3600 IN SOA ns.example.com. root.example.com. 1 3600 900 86400 300",
+            "a.example.com. 3600 IN A 1.1.1.1",
+            "b.example.com. 3600 IN A 2.2.2.2",
+            "example.com. 3600 IN SOA ns.example.com. root.example.com. 1 3600 900 86400 300",
+        ];
+        let mut buf = [0; 512];
+        let mut render = MessageRender::new(&mut buf);
+        render.set_tsig(TsigSigner::new(key.clone(), Some(&req_mac), now));
+        let messages: Vec<Vec<u8>> = answers
+            .iter()
+            .enumerate()
+            .map(|(i, answer)| {
+                //the third message is left unsigned
+                if i == 2 {
+                    render.tsig_mut().unwrap().skip_next().unwrap();
+                }
+                render.reset();
+                render_response(&req, answer).to_wire(&mut render).unwrap();
+                render.data().to_vec()
+            })
+            .collect();
+
+        let mut verifier = TsigStreamVerifier::new(&key, &req_mac);
+        for msg in &messages {
+            verifier.verify(msg, now).unwrap();
+        }
+        verifier.finish().unwrap();
+
+        let mut verifier = TsigStreamVerifier::new(&key, &req_mac);
+        verifier.verify(&messages[0], now).unwrap();
+        let err = verifier.verify(&messages[3], now).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::Tsig(TsigError::BadSig))
+        );
+
+        let mut verifier = TsigStreamVerifier::new(&key, &req_mac);
+        assert!(verifier.verify(&messages[2], now).is_err());
+        verifier.verify(&messages[0], now).unwrap();
+        verifier.verify(&messages[1], now).unwrap();
+        verifier.verify(&messages[2], now).unwrap();
+        assert!(verifier.finish().is_err());
+    }
+}
@@ -0,0 +1,459 @@
+use crate::header::Header;
+use crate::message::Message;
+use crate::message_render::MessageRender;
+use crate::name::Name;
+use crate::opcode::Opcode;
+use crate::question::Question;
+use crate::response::Section;
+use crate::rr_class::RRClass;
+use crate::rr_type::RRType;
+use crate::rrset::{RRTtl, RRset};
+use anyhow::{bail, ensure, Result};
+use std::fmt;
+
+//prerequisite section of update, rfc2136 2.4
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Prerequisite {
+    //rrset exists, value independent
+    RRsetExists(Name, RRType),
+    //rrset exists with exactly the same rdatas
+    RRsetExistsWithValue(RRset),
+    RRsetNotExists(Name, RRType),
+    NameInUse(Name),
+    NameNotInUse(Name),
+}
+
+impl Prerequisite {
+    //class is the class of the zone
+    pub fn from_rrset(rrset: RRset, class: RRClass) -> Result<Self> {
+        ensure!(rrset.ttl.0 == 0, "ttl of prerequisite isn't zero");
+        match rrset.class {
+            RRClass::ANY | RRClass::NONE => {
+                ensure!(rrset.rdatas.is_empty(), "prerequisite has rdata");
+                let exists = rrset.class == RRClass::ANY;
+                Ok(match (rrset.typ, exists) {
+                    (RRType::ANY, true) => Prerequisite::NameInUse(rrset.name),
+                    (RRType::ANY, false) => Prerequisite::NameNotInUse(rrset.name),
+                    (typ, true) => Prerequisite::RRsetExists(rrset.name, typ),
+                    (typ, false) => Prerequisite::RRsetNotExists(rrset.name, typ),
+                })
+            }
+            c if c == class => {
+                ensure!(!rrset.rdatas.is_empty(), "prerequisite has no rdata");
+                Ok(Prerequisite::RRsetExistsWithValue(rrset))
+            }
+            c => bail!("prerequisite with class {}", c),
+        }
+    }
+
+    pub fn to_rrset(&self, class: RRClass) -> RRset {
+        let (name, typ, class) = match self {
+            Prerequisite::RRsetExistsWithValue(rrset) => {
+                return RRset {
+                    class,
+                    ttl: RRTtl(0),
+                    ..rrset.clone()
+                }
+            }
+            Prerequisite::RRsetExists(name, typ) => (name, *typ, RRClass::ANY),
+            Prerequisite::RRsetNotExists(name, typ) => (name, *typ, RRClass::NONE),
+            Prerequisite::NameInUse(name) => (name, RRType::ANY, RRClass::ANY),
+            Prerequisite::NameNotInUse(name) => (name, RRType::ANY, RRClass::NONE),
+        };
+        empty_rrset(name, typ, class)
+    }
+
+    pub fn name(&self) -> &Name {
+        match self {
+            Prerequisite::RRsetExistsWithValue(rrset) => &rrset.name,
+            Prerequisite::RRsetExists(name, _)
+            | Prerequisite::RRsetNotExists(name, _)
+            | Prerequisite::NameInUse(name)
+            | Prerequisite::NameNotInUse(name) => name,
+        }
+    }
+}
+
+//update section of update, rfc2136 2.5
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Update {
+    Add(RRset),
+    DeleteRRset(Name, RRType),
+    DeleteName(Name),
+    //delete the rrs with the same rdata
+    DeleteRR(RRset),
+}
+
+impl Update {
+    //class is the class of the zone
+    pub fn from_rrset(rrset: RRset, class: RRClass) -> Result<Self> {
+        match rrset.class {
+            RRClass::ANY => {
+                ensure!(
+                    rrset.ttl.0 == 0 && rrset.rdatas.is_empty(),
+                    "delete rrset with ttl or rdata"
+                );
+                if rrset.typ == RRType::ANY {
+                    Ok(Update::DeleteName(rrset.name))
+                } else {
+                    ensure!(!is_meta_type(rrset.typ), "delete rrset of {}", rrset.typ);
+                    Ok(Update::DeleteRRset(rrset.name, rrset.typ))
+                }
+            }
+            RRClass::NONE => {
+                ensure!(rrset.ttl.0 == 0, "delete rr with ttl");
+                ensure!(!rrset.rdatas.is_empty(), "delete rr without rdata");
+                ensure!(
+                    !is_meta_type(rrset.typ) && rrset.typ != RRType::ANY,
+                    "delete rr of {}",
+                    rrset.typ
+                );
+                Ok(Update::DeleteRR(rrset))
+            }
+            c if c == class => {
+                ensure!(!rrset.rdatas.is_empty(), "add rrset without rdata");
+                ensure!(
+                    !is_meta_type(rrset.typ) && rrset.typ != RRType::ANY,
+                    "add rrset of {}",
+                    rrset.typ
+                );
+                Ok(Update::Add(rrset))
+            }
+            c => bail!("update with class {}", c),
+        }
+    }
+
+    pub fn to_rrset(&self, class: RRClass) -> RRset {
+        match self {
+            Update::Add(rrset) => RRset {
+                class,
+                ..rrset.clone()
+            },
+            Update::DeleteRR(rrset) => RRset {
+                class: RRClass::NONE,
+                ttl: RRTtl(0),
+                ..rrset.clone()
+            },
+            Update::DeleteRRset(name, typ) => empty_rrset(name, *typ, RRClass::ANY),
+            Update::DeleteName(name) => empty_rrset(name, RRType::ANY, RRClass::ANY),
+        }
+    }
+
+    pub fn name(&self) -> &Name {
+        match self {
+            Update::Add(rrset) | Update::DeleteRR(rrset) => &rrset.name,
+            Update::DeleteRRset(name, _) | Update::DeleteName(name) => name,
+        }
+    }
+}
+
+fn empty_rrset(name: &Name, typ: RRType, class: RRClass) -> RRset {
+    RRset {
+        name: name.clone(),
+        typ,
+        class,
+        ttl: RRTtl(0),
+        rdatas: Vec::new(),
+    }
+}
+
+//types only used in query, rfc2136 3.4.1.3
+fn is_meta_type(typ: RRType) -> bool {
+    matches!(
+        typ,
+        RRType::OPT | RRType::TSIG | RRType::IXFR | RRType::AXFR | RRType::ANY
+    )
+}
+
+//zone section holds the zone to update and its class, prerequisite and update
+//section are encoded in answer and authority section of the message
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UpdateMessage {
+    pub header: Header,
+    pub zone: Question,
+    pub prerequisites: Vec<Prerequisite>,
+    pub updates: Vec<Update>,
+    pub additional: Vec<RRset>,
+}
+
+impl UpdateMessage {
+    pub fn new(zone: Name) -> Self {
+        let mut header: Header = Header {
+            opcode: Opcode::Update,
+            ..Default::default()
+        };
+        header.id = rand::random::<u16>();
+        let mut msg = UpdateMessage {
+            header,
+            zone: Question::new(zone, RRType::SOA),
+            prerequisites: Vec::new(),
+            updates: Vec::new(),
+            additional: Vec::new(),
+        };
+        msg.recalculate_header();
+        msg
+    }
+
+    pub fn from_wire(raw: &[u8]) -> Result<Self> {
+        Message::from_wire(raw)?.into_update()
+    }
+
+    fn sections(&self) -> [Section; 3] {
+        let class = self.zone.class;
+        let section = |rrsets: Vec<RRset>| {
+            if rrsets.is_empty() {
+                Section(None)
+            } else {
+                Section(Some(rrsets))
+            }
+        };
+        [
+            section(
+                self.prerequisites
+                    .iter()
+                    .map(|prerequisite| prerequisite.to_rrset(class))
+                    .collect(),
+            ),
+            section(
+                self.updates
+                    .iter()
+                    .map(|update| update.to_rrset(class))
+                    .collect(),
+            ),
+            section(self.additional.clone()),
+        ]
+    }
+
+    pub fn recalculate_header(&mut self) {
+        let sections = self.sections();
+        self.header.qd_count = 1;
+        self.header.an_count = sections[0].rr_count() as u16;
+        self.header.ns_count = sections[1].rr_count() as u16;
+        self.header.ar_count = sections[2].rr_count() as u16;
+    }
+
+    pub fn to_wire(&self, render: &mut MessageRender) -> Result<usize> {
+        self.header.to_wire(render)?;
+        self.zone.to_wire(render)?;
+        for section in &self.sections() {
+            section.to_wire(render)?;
+        }
+        render.sign()?;
+        Ok(render.len())
+    }
+}
+
+impl From<UpdateMessage> for Message {
+    fn from(msg: UpdateMessage) -> Self {
+        Message {
+            sections: msg.sections(),
+            header: msg.header,
+            questions: vec![msg.zone],
+        }
+    }
+}
+
+impl fmt::Display for UpdateMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Message::from(self.clone()))
+    }
+}
+
+pub struct UpdateBuilder<'a> {
+    msg: &'a mut UpdateMessage,
+}
+
+impl<'a> UpdateBuilder<'a> {
+    pub fn new(msg: &'a mut UpdateMessage) -> Self {
+        UpdateBuilder { msg }
+    }
+
+    pub fn id(&mut self, id: u16) -> &mut Self {
+        self.msg.header.id = id;
+        self
+    }
+
+    pub fn prerequisite(&mut self, prerequisite: Prerequisite) -> &mut Self {
+        self.msg.prerequisites.push(prerequisite);
+        self
+    }
+
+    pub fn name_in_use(&mut self, name: Name) -> &mut Self {
+        self.prerequisite(Prerequisite::NameInUse(name))
+    }
+
+    pub fn name_not_in_use(&mut self, name: Name) -> &mut Self {
+        self.prerequisite(Prerequisite::NameNotInUse(name))
+    }
+
+    pub fn rrset_exists(&mut self, name: Name, typ: RRType) -> &mut Self {
+        self.prerequisite(Prerequisite::RRsetExists(name, typ))
+    }
+
+    pub fn rrset_exists_with_value(&mut self, rrset: RRset) -> &mut Self {
+        self.prerequisite(Prerequisite::RRsetExistsWithValue(rrset))
+    }
+
+    pub fn rrset_not_exists(&mut self, name: Name, typ: RRType) -> &mut Self {
+        self.prerequisite(Prerequisite::RRsetNotExists(name, typ))
+    }
+
+    pub fn update(&mut self, update: Update) -> &mut Self {
+        self.msg.updates.push(update);
+        self
+    }
+
+    pub fn add_rrset(&mut self, rrset: RRset) -> &mut Self {
+        self.update(Update::Add(rrset))
+    }
+
+    pub fn delete_rrset(&mut self, name: Name, typ: RRType) -> &mut Self {
+        self.update(Update::DeleteRRset(name, typ))
+    }
+
+    pub fn delete_name(&mut self, name: Name) -> &mut Self {
+        self.update(Update::DeleteName(name))
+    }
+
+    pub fn delete_rr(&mut self, rrset: RRset) -> &mut Self {
+        self.update(Update::DeleteRR(rrset))
+    }
+
+    pub fn add_additional(&mut self, rrset: RRset) -> &mut Self {
+        self.msg.additional.push(rrset);
+        self
+    }
+
+    pub fn done(&mut self) {
+        self.msg.recalculate_header();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::response::SectionType;
+    use crate::tsig::TsigSigner;
+    use crate::util::fixture::{name, tsig_key};
+    use std::str::FromStr;
+
+    fn update_message() -> UpdateMessage {
+        let mut msg = UpdateMessage::new(name("example.com."));
+        UpdateBuilder::new(&mut msg)
+            .id(0x1234)
+            .name_in_use(name("www.example.com."))
+            .name_not_in_use(name("new.example.com."))
+            .rrset_exists(name("www.example.com."), RRType::A)
+            .rrset_not_exists(name("www.example.com."), RRType::AAAA)
+            .rrset_exists_with_value(
+                RRset::from_strs(&[
+                    "www.example.com. 3600 IN A 1.1.1.1",
+                    "www.example.com. 3600 IN A 2.2.2.2",
+                ])
+                .unwrap(),
+            )
+            .add_rrset(RRset::from_str("new.example.com. 300 IN A 3.3.3.3").unwrap())
+            .delete_rrset(name("www.example.com."), RRType::A)
+            .delete_name(name("old.example.com."))
+            .delete_rr(RRset::from_str("mail.example.com. 300 IN MX 10 mx.example.com.").unwrap())
+            .done();
+        msg
+    }
+
+    #[test]
+    fn test_update_message() {
+        let msg = update_message();
+        assert_eq!(msg.header.qd_count, 1);
+        assert_eq!(msg.header.an_count, 6);
+        assert_eq!(msg.header.ns_count, 4);
+
+        let mut buf = [0; 1024];
+        let mut render = MessageRender::new(&mut buf);
+        let len = msg.to_wire(&mut render).unwrap();
+        let decoded = UpdateMessage::from_wire(&buf[..len]).unwrap();
+        assert_eq!(decoded, msg);
+        assert_eq!(
+            decoded.prerequisites[4],
+            Prerequisite::RRsetExistsWithValue(
+                RRset::from_strs(&[
+                    "www.example.com. 0 IN A 1.1.1.1",
+                    "www.example.com. 0 IN A 2.2.2.2",
+                ])
+                .unwrap()
+            )
+        );
+
+        let raw = Message::from_wire(&buf[..len]).unwrap();
+        let updates = raw.section(SectionType::Authority).unwrap();
+        assert_eq!(updates[1].class, RRClass::ANY);
+        assert_eq!(updates[2].typ, RRType::ANY);
+        assert_eq!(updates[3].class, RRClass::NONE);
+        assert_eq!(updates[3].ttl, RRTtl(0));
+        assert!(msg.to_string().contains(";; PREREQUISITE SECTION:"));
+
+        //query message isn't update
+        let query = Message::with_query(name("example.com."), RRType::SOA);
+        assert!(query.into_update().is_err());
+    }
+
+    #[test]
+    fn test_invalid_update() {
+        let class = RRClass::IN;
+        for (rrset, valid) in [
+            ("www.example.com. 0 ANY A", true),
+            ("www.example.com. 300 ANY A", false),
+            ("www.example.com. 0 ANY AXFR", false),
+            ("www.example.com. 0 NONE A 1.1.1.1", true),
+            ("www.example.com. 0 NONE ANY", false),
+            ("www.example.com. 300 IN A 1.1.1.1", true),
+            ("www.example.com. 300 CH A 1.1.1.1", false),
+        ] {
+            let rrset = parse_rrset(rrset);
+            assert_eq!(Update::from_rrset(rrset, class).is_ok(), valid);
+        }
+
+        for (rrset, valid) in [
+            ("www.example.com. 0 ANY ANY", true),
+            ("www.example.com. 0 NONE A", true),
+            ("www.example.com. 300 ANY A", false),
+            ("www.example.com. 0 IN A 1.1.1.1", true),
+            ("www.example.com. 0 CH A 1.1.1.1", false),
+        ] {
+            let rrset = parse_rrset(rrset);
+            assert_eq!(Prerequisite::from_rrset(rrset, class).is_ok(), valid);
+        }
+    }
+
+    //class any and none rr has no rdata in text
+    fn parse_rrset(s: &str) -> RRset {
+        let fields = s.split_whitespace().collect::<Vec<_>>();
+        if fields.len() == 4 {
+            RRset {
+                name: name(fields[0]),
+                typ: fields[3].parse().unwrap(),
+                class: fields[2].parse().unwrap(),
+                ttl: fields[1].parse().unwrap(),
+                rdatas: Vec::new(),
+            }
+        } else {
+            RRset::from_str(s).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_update_with_tsig() {
+        let key = tsig_key();
+        let msg = update_message();
+        let mut buf = [0; 1024];
+        let mut render = MessageRender::new(&mut buf);
+        render.set_tsig(TsigSigner::new(key.clone(), None, 1600000000));
+        msg.to_wire(&mut render).unwrap();
+        let len = render.len();
+
+        key.verify(&buf[..len], None, 1600000000).unwrap();
+        let decoded = UpdateMessage::from_wire(&buf[..len]).unwrap();
+        assert_eq!(decoded.prerequisites, msg.prerequisites);
+        assert_eq!(decoded.updates, msg.updates);
+        assert_eq!(decoded.additional[0].typ, RRType::TSIG);
+    }
+}
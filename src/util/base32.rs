@@ -0,0 +1,78 @@
+//base32 with extended hex alphabet and without padding, rfc4648 7,
+//used by nsec3 hashed owner names
+static CHARS: &[u8] = b"0123456789abcdefghijklmnopqrstuv";
+
+pub fn to_base32hex(data: &[u8]) -> String {
+    let mut v = Vec::with_capacity((data.len() * 8).div_ceil(5));
+    let mut buf = 0u16;
+    let mut bits = 0;
+    for &byte in data {
+        buf = (buf << 8) | byte as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            v.push(CHARS[((buf >> bits) & 0x1f) as usize]);
+        }
+    }
+    if bits > 0 {
+        v.push(CHARS[((buf << (5 - bits)) & 0x1f) as usize]);
+    }
+
+    unsafe { String::from_utf8_unchecked(v) }
+}
+
+pub fn from_base32hex(s: &str) -> Option<Vec<u8>> {
+    let mut b = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buf = 0u16;
+    let mut bits = 0;
+    for byte in s.bytes() {
+        let value = match byte {
+            b'0'..=b'9' => byte - b'0',
+            b'a'..=b'v' => byte - b'a' + 10,
+            b'A'..=b'V' => byte - b'A' + 10,
+            _ => return None,
+        };
+        buf = (buf << 5) | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            b.push((buf >> bits) as u8);
+        }
+    }
+
+    //left bits are padding and must be zero
+    if bits >= 5 || buf & ((1 << bits) - 1) != 0 {
+        None
+    } else {
+        Some(b)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_base32hex() {
+        //rfc4648 10
+        for (raw, encoded) in [
+            ("", ""),
+            ("f", "co"),
+            ("fo", "cpng"),
+            ("foo", "cpnmu"),
+            ("foob", "cpnmuog"),
+            ("fooba", "cpnmuoj1"),
+            ("foobar", "cpnmuoj1e8"),
+        ] {
+            assert_eq!(to_base32hex(raw.as_bytes()), encoded);
+            assert_eq!(from_base32hex(encoded).unwrap(), raw.as_bytes());
+            assert_eq!(
+                from_base32hex(&encoded.to_uppercase()).unwrap(),
+                raw.as_bytes()
+            );
+        }
+        assert!(from_base32hex("cpnmuoj1e9").is_none());
+        assert!(from_base32hex("cpw").is_none());
+        assert!(from_base32hex("c").is_none());
+    }
+}
@@ -0,0 +1,66 @@
+//fixtures shared by tests of different modules
+use crate::name::Name;
+use crate::rcode::Rcode;
+use crate::rrset::RRset;
+use crate::tsig::{TsigAlgorithm, TsigKey};
+use crate::update::{UpdateBuilder, UpdateMessage};
+use crate::zone::{ChangeSet, Zone};
+use crate::zonefile::parse_zone;
+use std::str::FromStr;
+
+//zone which is updated and transferred by tests
+pub(crate) const ZONE: &str = r#"
+$ORIGIN example.com.
+$TTL 3600
+@           IN SOA   ns1 hostmaster 10 3600 900 604800 300
+            IN NS    ns1
+            IN NS    ns2
+ns1         IN A     1.1.1.1
+ns2         IN A     2.2.2.2
+www         IN A     3.3.3.3
+            IN A     4.4.4.4
+web         IN CNAME www
+"#;
+
+pub(crate) fn name(name: &str) -> Name {
+    Name::new(name).unwrap()
+}
+
+pub(crate) fn rrset(rrset: &str) -> RRset {
+    RRset::from_str(rrset).unwrap()
+}
+
+//a rrset of addresses 10.0.0.0 to 10.0.0.{count - 1}, which is truncated
+//over udp when count is big
+pub(crate) fn big_rrset(name: &str, count: u8) -> RRset {
+    let mut big = rrset(&format!("{} 300 IN A 10.0.0.0", name));
+    big.rdatas = (0..count)
+        .flat_map(|i| rrset(&format!("{} 300 IN A 10.0.0.{}", name, i)).rdatas)
+        .collect();
+    big
+}
+
+pub(crate) fn build_zone(origin: &str, content: &str) -> Zone {
+    let origin = name(origin);
+    Zone::from_rrsets(origin.clone(), parse_zone(content, &origin).unwrap()).unwrap()
+}
+
+pub(crate) fn tsig_key() -> TsigKey {
+    TsigKey::from_base64(
+        name("tsig-key."),
+        TsigAlgorithm::HmacSha256,
+        "c2VjcmV0LWZvci10c2lnLXRlc3Q=",
+    )
+    .unwrap()
+}
+
+pub(crate) fn update<F: FnOnce(&mut UpdateBuilder)>(
+    zone: &mut Zone,
+    f: F,
+) -> Result<ChangeSet, Rcode> {
+    let mut msg = UpdateMessage::new(zone.origin().clone());
+    let mut builder = UpdateBuilder::new(&mut msg);
+    f(&mut builder);
+    builder.done();
+    zone.update(&msg)
+}
@@ -3,7 +3,7 @@ pub fn from_hex(hex_str: &str) -> Option<Vec<u8>> {
     let mut modulus = 0;
     let mut buf = 0;
 
-    for (_, byte) in hex_str.bytes().enumerate() {
+    for byte in hex_str.bytes() {
         buf <<= 4;
         match byte {
             b'A'..=b'F' => buf |= byte - b'A' + 10,
@@ -1,7 +1,12 @@
+pub mod base32;
+#[cfg(test)]
+pub(crate) mod fixture;
 pub mod hex;
 mod input_buffer;
 mod output_buffer;
 mod string_buffer;
+pub mod tcp;
+pub mod udp;
 
 pub use self::input_buffer::InputBuffer;
 pub use self::output_buffer::OutputBuffer;
@@ -110,7 +110,7 @@ impl<'a> OutputBuffer<'a> {
     }
 
     pub fn truncate(&mut self, pos: usize) -> Result<()> {
-        if pos < self.len() {
+        if pos <= self.len() {
             self.len = pos;
             Ok(())
         } else {
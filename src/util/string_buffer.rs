@@ -1,3 +1,4 @@
+use crate::name::Name;
 use anyhow::{anyhow, bail, ensure, Result};
 use std::str::from_utf8;
 use std::str::FromStr;
@@ -7,6 +8,7 @@ const MAX_CHARSTRING_LEN: usize = 255;
 pub struct StringBuffer<'a> {
     raw: &'a [u8],
     pos: usize,
+    origin: Option<&'a Name>,
 }
 
 impl<'a> StringBuffer<'a> {
@@ -16,6 +18,24 @@ impl<'a> StringBuffer<'a> {
         StringBuffer {
             raw: raw.as_bytes(),
             pos: 0,
+            origin: None,
+        }
+    }
+
+    //names which don't end with dot are relative to origin,
+    //and @ stands for origin itself
+    pub fn with_origin(raw: &'a str, origin: &'a Name) -> Self {
+        let mut buf = Self::new(raw);
+        buf.origin = Some(origin);
+        buf
+    }
+
+    pub fn read_name(&mut self) -> Result<Name> {
+        let s = self.read_str().ok_or(anyhow!("empty string"))?;
+        match self.origin {
+            Some(origin) if s == "@" => Ok(origin.clone()),
+            Some(origin) if !is_absolute(s) => Name::new(s)?.concat(origin),
+            _ => Name::new(s),
         }
     }
 
@@ -124,6 +144,29 @@ impl<'a> StringBuffer<'a> {
     }
 }
 
+//name is absolute when it ends with a dot which isn't escaped, escape is
+//either \c or \ddd
+fn is_absolute(name: &str) -> bool {
+    let raw = name.as_bytes();
+    let mut i = 0;
+    let mut last_is_dot = false;
+    while i < raw.len() {
+        if raw[i] == b'\\' {
+            let digits = raw[(i + 1)..]
+                .iter()
+                .take(3)
+                .take_while(|c| c.is_ascii_digit())
+                .count();
+            i += if digits == 3 { 4 } else { 2 };
+            last_is_dot = false;
+        } else {
+            last_is_dot = raw[i] == b'.';
+            i += 1;
+        }
+    }
+    last_is_dot
+}
+
 impl<'a> Iterator for StringBuffer<'a> {
     type Item = &'a str;
     fn next(&mut self) -> Option<&'a str> {
@@ -134,19 +177,16 @@ impl<'a> Iterator for StringBuffer<'a> {
 #[cfg(test)]
 mod test {
     use super::StringBuffer;
+    use crate::name::Name;
     #[test]
     fn test_parser_iterator() {
         let s = " example.org. 100 IN SOA xxx.net. ns.example.org. 100 1800 900 604800 86400    ";
         let mut iter = StringBuffer::new(s);
         let mut split_white = s.split_whitespace();
         let mut label_count = 0;
-        loop {
-            if let Some(label) = iter.next() {
-                assert_eq!(label, split_white.next().unwrap());
-                label_count += 1;
-            } else {
-                break;
-            }
+        for label in iter.by_ref() {
+            assert_eq!(label, split_white.next().unwrap());
+            label_count += 1;
         }
         assert_eq!(label_count, 11);
     }
@@ -163,6 +203,28 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_read_name() {
+        let origin = Name::new("example.com.").unwrap();
+        let mut buf = StringBuffer::with_origin(
+            "@ www mail.example.net. a\\.b a\\. a\\\\. a\\\\\\. a\\046",
+            &origin,
+        );
+        assert_eq!(buf.read_name().unwrap(), origin);
+        assert_eq!(buf.read_name().unwrap().to_string(), "www.example.com.");
+        assert_eq!(buf.read_name().unwrap().to_string(), "mail.example.net.");
+        assert_eq!(buf.read_name().unwrap().label_count(), 4);
+        //escaped dot doesn't make name absolute, but escaped backslash does
+        assert_eq!(buf.read_name().unwrap().label_count(), 4);
+        assert_eq!(buf.read_name().unwrap().label_count(), 2);
+        assert_eq!(buf.read_name().unwrap().label_count(), 4);
+        assert_eq!(buf.read_name().unwrap().label_count(), 4);
+        assert!(buf.read_name().is_err());
+
+        let mut buf = StringBuffer::new("www");
+        assert_eq!(buf.read_name().unwrap().to_string(), "www.");
+    }
+
     #[test]
     fn test_read_text() {
         let s = r#" "abc" "edf""#;
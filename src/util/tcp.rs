@@ -0,0 +1,44 @@
+use anyhow::{ensure, Result};
+use std::io::{Read, Write};
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+//message over tcp is prefixed with two bytes length, rfc1035 4.2.2
+pub fn read_message<R: Read>(stream: &mut R) -> Result<Vec<u8>> {
+    let mut len = [0; 2];
+    stream.read_exact(&mut len)?;
+    let mut data = vec![0; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut data)?;
+    Ok(data)
+}
+
+pub fn write_message<W: Write>(stream: &mut W, data: &[u8]) -> Result<()> {
+    stream.write_all(&frame(data)?)?;
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+pub async fn read_message_async<R: AsyncRead + Unpin>(stream: &mut R) -> Result<Vec<u8>> {
+    let len = stream.read_u16().await?;
+    let mut data = vec![0; len as usize];
+    stream.read_exact(&mut data).await?;
+    Ok(data)
+}
+
+#[cfg(feature = "tokio")]
+pub async fn write_message_async<W: AsyncWrite + Unpin>(stream: &mut W, data: &[u8]) -> Result<()> {
+    stream.write_all(&frame(data)?).await?;
+    Ok(())
+}
+
+fn frame(data: &[u8]) -> Result<Vec<u8>> {
+    ensure!(
+        data.len() <= u16::MAX as usize,
+        "message length {} is too large",
+        data.len()
+    );
+    let mut framed = Vec::with_capacity(data.len() + 2);
+    framed.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    framed.extend_from_slice(data);
+    Ok(framed)
+}
@@ -0,0 +1,20 @@
+use anyhow::Result;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+
+//socket on any local address of the same family as server
+pub fn bind_for(server: SocketAddr) -> Result<UdpSocket> {
+    Ok(UdpSocket::bind(local_addr(server))?)
+}
+
+#[cfg(feature = "tokio")]
+pub async fn bind_for_async(server: SocketAddr) -> Result<tokio::net::UdpSocket> {
+    Ok(tokio::net::UdpSocket::bind(local_addr(server)).await?)
+}
+
+fn local_addr(server: SocketAddr) -> SocketAddr {
+    if server.is_ipv4() {
+        SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))
+    } else {
+        SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0))
+    }
+}
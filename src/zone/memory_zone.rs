@@ -0,0 +1,686 @@
+use crate::domaintree::{
+    node::NodePtr,
+    node_chain::NodeChain,
+    tree::{DomainTree, FindResultFlag},
+};
+use crate::edns::Edns;
+use crate::header_flag::HeaderFlag;
+use crate::name::{Name, NameRelation};
+use crate::rcode::Rcode;
+use crate::rdata::RData;
+use crate::rdatas::CName;
+use crate::request::Request;
+use crate::response::{Response, Section, SectionType, MAX_CNAME_CHAIN};
+use crate::response_builder::ResponseBuilder;
+use crate::rr_class::RRClass;
+use crate::rr_type::RRType;
+use crate::rrset::{RRTtl, RRset};
+use anyhow::{bail, ensure, Result};
+use std::collections::HashMap;
+
+type RRsetMap = HashMap<RRType, RRset>;
+
+#[derive(Clone)]
+pub struct Zone {
+    origin: Name,
+    class: RRClass,
+    tree: DomainTree<RRsetMap>,
+}
+
+//result of looking up one name, before cname/dname is followed
+enum LookupResult {
+    Answer(Vec<RRset>),
+    CName(RRset),
+    DName(RRset, RRset),
+    Delegation(RRset),
+    NXRRset,
+    NXDomain,
+    YXDomain(RRset),
+}
+
+impl Zone {
+    pub fn new(origin: Name, class: RRClass) -> Self {
+        Zone {
+            origin,
+            class,
+            tree: DomainTree::new(),
+        }
+    }
+
+    pub fn from_rrsets(origin: Name, rrsets: Vec<RRset>) -> Result<Self> {
+        let class = match rrsets
+            .iter()
+            .find(|rrset| rrset.typ == RRType::SOA && rrset.name.eq(&origin))
+        {
+            Some(soa) => soa.class,
+            None => bail!("zone {} has no soa", origin),
+        };
+        let mut zone = Zone::new(origin, class);
+        for rrset in rrsets {
+            zone.add_rrset(rrset)?;
+        }
+        Ok(zone)
+    }
+
+    pub fn origin(&self) -> &Name {
+        &self.origin
+    }
+
+    pub fn class(&self) -> RRClass {
+        self.class
+    }
+
+    pub fn soa(&self) -> Option<&RRset> {
+        self.get_rrset(&self.origin, RRType::SOA)
+    }
+
+    //rdatas of an existing rrset are merged, ttl is overwritten
+    pub fn add_rrset(&mut self, mut rrset: RRset) -> Result<()> {
+        ensure!(
+            rrset.name.is_subdomain(&self.origin),
+            "{} is out of zone {}",
+            rrset.name,
+            self.origin
+        );
+        ensure!(
+            rrset.class == self.class,
+            "rrset class {} doesn't match zone class {}",
+            rrset.class,
+            self.class
+        );
+        ensure!(
+            !rrset.rdatas.is_empty(),
+            "rrset {} has no rdata",
+            rrset.name
+        );
+        if rrset.typ == RRType::SOA {
+            ensure!(rrset.name.eq(&self.origin), "soa should be at zone apex");
+            ensure!(rrset.rdatas.len() == 1, "zone can only have one soa");
+        }
+
+        if let Some(rrsets) = self.get_rrsets(&rrset.name) {
+            let conflict = if rrset.typ == RRType::CNAME {
+                rrsets
+                    .keys()
+                    .any(|&typ| typ != RRType::CNAME && !is_dnssec_type(typ))
+            } else {
+                rrsets.contains_key(&RRType::CNAME) && !is_dnssec_type(rrset.typ)
+            };
+            ensure!(
+                !conflict,
+                "cname {} can't coexist with other data",
+                rrset.name
+            );
+        }
+
+        let (node, old) = self.tree.insert(rrset.name.clone(), None);
+        let mut rrsets = old.flatten().unwrap_or_default();
+
+        if rrset.typ == RRType::DNAME || (rrset.typ == RRType::NS && !rrset.name.eq(&self.origin)) {
+            node.set_callback(true);
+        }
+        if rrset.name.is_wildcard() {
+            self.set_wildcard_flag(&rrset.name);
+        }
+
+        if let Some(old) = rrsets.get_mut(&rrset.typ) {
+            old.ttl = rrset.ttl;
+            for rdata in rrset.rdatas.drain(..) {
+                if !old.rdatas.contains(&rdata) {
+                    old.rdatas.push(rdata);
+                }
+            }
+            if old.typ == RRType::SOA || old.typ == RRType::CNAME {
+                old.rdatas.drain(0..old.rdatas.len() - 1);
+            }
+        } else {
+            if rrset.typ == RRType::CNAME {
+                rrset.rdatas.truncate(1);
+            }
+            rrsets.insert(rrset.typ, rrset);
+        }
+        node.set_value(Some(rrsets));
+        Ok(())
+    }
+
+    pub fn delete_rrset(&mut self, name: &Name, typ: RRType) -> Option<RRset> {
+        let node = self.find_exact_node(name)?;
+        let mut rrsets = node.set_value(None)?;
+        let deleted = rrsets.remove(&typ);
+        if !rrsets.contains_key(&RRType::DNAME)
+            && (!rrsets.contains_key(&RRType::NS) || name.eq(&self.origin))
+        {
+            node.set_callback(false);
+        }
+        if rrsets.is_empty() {
+            self.tree.remove_node(node);
+        } else {
+            node.set_value(Some(rrsets));
+        }
+        deleted
+    }
+
+    pub fn get_rrset(&self, name: &Name, typ: RRType) -> Option<&RRset> {
+        self.get_rrsets(name).and_then(|rrsets| rrsets.get(&typ))
+    }
+
+    fn get_rrsets(&self, name: &Name) -> Option<&RRsetMap> {
+        let result = self.tree.find(name);
+        if result.flag == FindResultFlag::ExacatMatch {
+            result.get_value()
+        } else {
+            None
+        }
+    }
+
+    //all rrsets in canonical order, soa first
+    pub fn rrsets(&self) -> Vec<&RRset> {
+        let mut all = Vec::new();
+        self.tree.for_each(|_, rrsets| {
+            let start = all.len();
+            all.extend(rrsets.values());
+            all[start..].sort_by_key(|rrset| rrset.typ.as_u16());
+        });
+        if let Some(pos) = all.iter().position(|rrset| rrset.typ == RRType::SOA) {
+            let soa = all.remove(pos);
+            all.insert(0, soa);
+        }
+        all
+    }
+
+    //names which own data in canonical order
+    pub fn names(&self) -> Vec<Name> {
+        let mut names = Vec::new();
+        self.tree.for_each(|name, _| names.push(name));
+        names
+    }
+
+    //rrsets owned by name ordered by type
+    pub fn rrsets_at(&self, name: &Name) -> Vec<&RRset> {
+        let mut rrsets: Vec<&RRset> = self
+            .get_rrsets(name)
+            .map(|rrsets| rrsets.values().collect())
+            .unwrap_or_default();
+        rrsets.sort_by_key(|rrset| rrset.typ.as_u16());
+        rrsets
+    }
+
+    //edns is added to the response only if the query has it
+    pub fn query(&self, req: &Request, edns: Option<&Edns>) -> Response {
+        let mut resp = Response {
+            header: req.header.clone(),
+            question: req.question.clone(),
+            sections: [Section(None), Section(None), Section(None)],
+        };
+        let qname = &req.question.name;
+        let qtype = req.question.typ;
+        let mut builder = ResponseBuilder::new(&mut resp);
+        builder.make_response().clear_flag(HeaderFlag::AuthAnswer);
+        if !qname.is_subdomain(&self.origin) || req.question.class != self.class {
+            builder.rcode(Rcode::Refused).query_edns(edns).done();
+            return resp;
+        }
+
+        builder.set_flag(HeaderFlag::AuthAnswer);
+        let mut name = qname.clone();
+        let mut visited = Vec::new();
+        let mut referral = false;
+        loop {
+            match self.lookup(&name, qtype) {
+                LookupResult::Answer(rrsets) => {
+                    for rrset in rrsets {
+                        builder.add_rrset(SectionType::Answer, rrset);
+                    }
+                    break;
+                }
+                LookupResult::CName(cname) => {
+                    let target = cname_target(&cname).cloned();
+                    builder.add_rrset(SectionType::Answer, cname);
+                    visited.push(name);
+                    let target = match target {
+                        Some(target) => target,
+                        None => break,
+                    };
+                    if !self.should_follow(&target, &visited) {
+                        break;
+                    }
+                    name = target;
+                }
+                LookupResult::DName(dname, cname) => {
+                    let target = cname_target(&cname).cloned();
+                    builder
+                        .add_rrset(SectionType::Answer, dname)
+                        .add_rrset(SectionType::Answer, cname);
+                    visited.push(name);
+                    let target = match target {
+                        Some(target) => target,
+                        None => break,
+                    };
+                    if !self.should_follow(&target, &visited) {
+                        break;
+                    }
+                    name = target;
+                }
+                LookupResult::Delegation(ns) => {
+                    referral = true;
+                    if visited.is_empty() {
+                        builder.clear_flag(HeaderFlag::AuthAnswer);
+                    }
+                    builder.add_rrset(SectionType::Authority, ns);
+                    break;
+                }
+                LookupResult::NXRRset => {
+                    self.add_soa(&mut builder);
+                    break;
+                }
+                LookupResult::NXDomain => {
+                    builder.rcode(Rcode::NXDomain);
+                    self.add_soa(&mut builder);
+                    break;
+                }
+                LookupResult::YXDomain(dname) => {
+                    builder
+                        .rcode(Rcode::YXDomain)
+                        .add_rrset(SectionType::Answer, dname);
+                    break;
+                }
+            }
+        }
+        //address of name servers and mail exchangers which are in zone
+        builder
+            .add_additional(|name, typ| self.additional(name, typ, referral))
+            .query_edns(edns)
+            .done();
+        resp
+    }
+
+    //rrset of name in zone for additional section, data below a zone cut
+    //is glue which is only returned in referral
+    pub(crate) fn additional(&self, name: &Name, typ: RRType, referral: bool) -> Option<RRset> {
+        if !name.is_subdomain(&self.origin) {
+            return None;
+        }
+        match self.lookup(name, typ) {
+            LookupResult::Delegation(_) if referral => {}
+            LookupResult::Delegation(_) | LookupResult::DName(..) | LookupResult::YXDomain(_) => {
+                return None
+            }
+            _ => {}
+        }
+        self.get_rrset(name, typ).cloned()
+    }
+
+    fn should_follow(&self, target: &Name, visited: &[Name]) -> bool {
+        target.is_subdomain(&self.origin)
+            && visited.len() < MAX_CNAME_CHAIN
+            && !visited.iter().any(|name| name.eq(target))
+    }
+
+    fn lookup(&self, name: &Name, qtype: RRType) -> LookupResult {
+        let mut cut = None;
+        let mut chain = NodeChain::new(&self.tree);
+        let result = self.tree.find_node_ext(
+            name,
+            &mut chain,
+            &mut Some(
+                |node: NodePtr<RRsetMap>, _, cut: &mut Option<NodePtr<RRsetMap>>| {
+                    *cut = Some(node);
+                    true
+                },
+            ),
+            &mut cut,
+        );
+
+        if let Some(rrsets) = cut.as_ref().and_then(|node| node.get_value().as_ref()) {
+            if let Some(ns) = rrsets.get(&RRType::NS) {
+                return LookupResult::Delegation(ns.clone());
+            }
+            if let Some(dname) = rrsets.get(&RRType::DNAME) {
+                return match synthesize_cname(name, dname) {
+                    Some(cname) => LookupResult::DName(dname.clone(), cname),
+                    None => LookupResult::YXDomain(dname.clone()),
+                };
+            }
+        }
+
+        match result.flag {
+            FindResultFlag::ExacatMatch => match result.get_value() {
+                Some(rrsets) => {
+                    if qtype != RRType::DS && !name.eq(&self.origin) {
+                        if let Some(ns) = rrsets.get(&RRType::NS) {
+                            return LookupResult::Delegation(ns.clone());
+                        }
+                    }
+                    answer_from_rrsets(rrsets, qtype, name)
+                }
+                None => LookupResult::NXRRset,
+            },
+            FindResultFlag::PartialMatch => {
+                //name is the ancestor of a lower node, or shares labels with
+                //it, both means there is an empty non-terminal without wildcard
+                match chain.last_compared_result.relation {
+                    NameRelation::SuperDomain => return LookupResult::NXRRset,
+                    NameRelation::CommonAncestor => return LookupResult::NXDomain,
+                    _ => {}
+                }
+                let encloser = result.node;
+                if !encloser.is_wildcard() {
+                    return LookupResult::NXDomain;
+                }
+                chain.pop();
+                let wildcard = Name::new("*")
+                    .unwrap()
+                    .concat(&chain.get_absolute_name(encloser.get_name()))
+                    .unwrap();
+                match self.get_rrsets(&wildcard) {
+                    Some(rrsets) => answer_from_rrsets(rrsets, qtype, name),
+                    None => LookupResult::NXDomain,
+                }
+            }
+            FindResultFlag::NotFound => LookupResult::NXDomain,
+        }
+    }
+
+    fn add_soa(&self, builder: &mut ResponseBuilder) {
+        if let Some(soa) = self.soa() {
+            let mut soa = soa.clone();
+            if let RData::SOA(ref rdata) = soa.rdatas[0] {
+                soa.ttl = RRTtl(soa.ttl.0.min(rdata.minimum));
+            }
+            builder.add_rrset(SectionType::Authority, soa);
+        }
+    }
+
+    fn find_exact_node(&self, name: &Name) -> Option<NodePtr<RRsetMap>> {
+        let result = self.tree.find(name);
+        if result.flag == FindResultFlag::ExacatMatch {
+            Some(result.node)
+        } else {
+            None
+        }
+    }
+
+    //wildcard flag is set on the parent of wildcard name
+    fn set_wildcard_flag(&mut self, wildcard: &Name) {
+        let parent = wildcard.parent(1).unwrap();
+        let node = match self.find_exact_node(&parent) {
+            Some(node) => node,
+            None => self.tree.insert(parent, None).0,
+        };
+        node.set_wildcard(true);
+    }
+}
+
+fn is_dnssec_type(typ: RRType) -> bool {
+    matches!(typ, RRType::RRSIG | RRType::NSEC)
+}
+
+fn answer_from_rrsets(rrsets: &RRsetMap, qtype: RRType, name: &Name) -> LookupResult {
+    let with_name = |rrset: &RRset| {
+        let mut rrset = rrset.clone();
+        rrset.name = name.clone();
+        rrset
+    };
+
+    if qtype == RRType::ANY {
+        let mut answers = rrsets.values().map(with_name).collect::<Vec<_>>();
+        answers.sort_by_key(|rrset| rrset.typ.as_u16());
+        LookupResult::Answer(answers)
+    } else if let Some(rrset) = rrsets.get(&qtype) {
+        LookupResult::Answer(vec![with_name(rrset)])
+    } else if let Some(cname) = rrsets.get(&RRType::CNAME) {
+        LookupResult::CName(with_name(cname))
+    } else {
+        LookupResult::NXRRset
+    }
+}
+
+//target of the first rdata, none if the rrset is empty or of other type
+pub(crate) fn cname_target(cname: &RRset) -> Option<&Name> {
+    match cname.rdatas.first() {
+        Some(RData::CName(cname)) => Some(&cname.name),
+        _ => None,
+    }
+}
+
+//replace the dname owner suffix of name with dname target, none if dname
+//has no target or the new name is too long
+pub(crate) fn synthesize_cname(name: &Name, dname: &RRset) -> Option<RRset> {
+    let target = match dname.rdatas.first() {
+        Some(RData::DNAME(dname)) => &dname.target,
+        _ => return None,
+    };
+    let prefix = name.strip_right(dname.name.label_count() - 1);
+    let new_name = prefix.concat(target).ok()?;
+    Some(RRset {
+        name: name.clone(),
+        typ: RRType::CNAME,
+        class: dname.class,
+        ttl: dname.ttl,
+        rdatas: vec![RData::CName(Box::new(CName { name: new_name }))],
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::fixture::build_zone;
+    use crate::zonefile::parse_zone;
+    use std::str::FromStr;
+
+    const ZONE: &str = r#"
+$ORIGIN example.com.
+$TTL 3600
+@           IN SOA   ns1 hostmaster 1 3600 900 604800 300
+            IN NS    ns1
+            IN MX    10 mail
+ns1         IN A     1.1.1.1
+mail        IN A     2.2.2.2
+www         IN CNAME web
+web         IN A     3.3.3.3
+loop1       IN CNAME loop2
+loop2       IN CNAME loop1
+out         IN CNAME www.example.net.
+*.wild      IN TXT   "wildcard"
+*.alias     IN CNAME web
+a.b.c       IN A     4.4.4.4
+sub         IN NS    ns.sub
+            IN NS    ns.example.net.
+            IN DS    1 8 2 abcd
+ns.sub      IN A     5.5.5.5
+occluded    IN MX    10 ns.sub
+old         IN DNAME new.long-target-name.example.net.
+"#;
+
+    fn query(zone: &Zone, name: &str, typ: RRType) -> Response {
+        zone.query(&Request::new(Name::new(name).unwrap(), typ), None)
+    }
+
+    fn answer_types(resp: &Response) -> Vec<RRType> {
+        resp.section(SectionType::Answer)
+            .map_or(Vec::new(), |rrsets| {
+                rrsets.iter().map(|rrset| rrset.typ).collect()
+            })
+    }
+
+    #[test]
+    fn test_add_rrset() {
+        let origin = Name::new("example.com.").unwrap();
+        let rrsets = parse_zone("www.example.com. 300 IN A 1.1.1.1", &origin).unwrap();
+        assert!(Zone::from_rrsets(origin, rrsets).is_err());
+
+        let mut zone = build_zone("example.com.", ZONE);
+        assert!(zone
+            .add_rrset(RRset::from_str("www.example.org. 300 IN A 1.1.1.1").unwrap())
+            .is_err());
+        assert!(zone
+            .add_rrset(RRset::from_str("www.example.com. 300 IN A 1.1.1.1").unwrap())
+            .is_err());
+        zone.add_rrset(RRset::from_str("web.example.com. 300 IN A 3.3.3.4").unwrap())
+            .unwrap();
+        let web = zone
+            .get_rrset(&Name::new("web.example.com.").unwrap(), RRType::A)
+            .unwrap();
+        assert_eq!(web.rdatas.len(), 2);
+        assert_eq!(web.ttl, RRTtl(300));
+
+        let deleted = zone.delete_rrset(&Name::new("web.example.com.").unwrap(), RRType::A);
+        assert_eq!(deleted.unwrap().rdatas.len(), 2);
+        assert_eq!(
+            query(&zone, "web.example.com.", RRType::A).header.rcode,
+            Rcode::NXDomain
+        );
+        assert_eq!(zone.rrsets()[0].typ, RRType::SOA);
+        assert_eq!(zone.clone().rrsets().len(), zone.rrsets().len());
+    }
+
+    #[test]
+    fn test_query() {
+        let zone = build_zone("example.com.", ZONE);
+        let resp = query(&zone, "ns1.example.com.", RRType::A);
+        assert!(resp.header.is_flag_set(HeaderFlag::AuthAnswer));
+        assert!(resp.header.is_flag_set(HeaderFlag::QueryRespone));
+        assert_eq!(resp.header.rcode, Rcode::NoError);
+        assert_eq!(answer_types(&resp), vec![RRType::A]);
+
+        let resp = query(&zone, "example.com.", RRType::ANY);
+        assert_eq!(
+            answer_types(&resp),
+            vec![RRType::NS, RRType::SOA, RRType::MX]
+        );
+
+        //nodata
+        let resp = query(&zone, "ns1.example.com.", RRType::AAAA);
+        assert_eq!(resp.header.rcode, Rcode::NoError);
+        assert_eq!(resp.header.an_count, 0);
+        let soa = &resp.section(SectionType::Authority).unwrap()[0];
+        assert_eq!(soa.typ, RRType::SOA);
+        assert_eq!(soa.ttl, RRTtl(300));
+
+        //empty non-terminal
+        let resp = query(&zone, "b.c.example.com.", RRType::A);
+        assert_eq!(resp.header.rcode, Rcode::NoError);
+        assert_eq!(resp.header.ns_count, 1);
+
+        let resp = query(&zone, "x.c.example.com.", RRType::A);
+        assert_eq!(resp.header.rcode, Rcode::NXDomain);
+        assert_eq!(resp.header.ns_count, 1);
+
+        let resp = query(&zone, "www.example.org.", RRType::A);
+        assert_eq!(resp.header.rcode, Rcode::Refused);
+        assert!(!resp.header.is_flag_set(HeaderFlag::AuthAnswer));
+    }
+
+    #[test]
+    fn test_cname() {
+        let zone = build_zone("example.com.", ZONE);
+        let resp = query(&zone, "www.example.com.", RRType::A);
+        assert_eq!(answer_types(&resp), vec![RRType::CNAME, RRType::A]);
+        assert_eq!(resp.header.an_count, 2);
+
+        let resp = query(&zone, "www.example.com.", RRType::CNAME);
+        assert_eq!(answer_types(&resp), vec![RRType::CNAME]);
+
+        let resp = query(&zone, "loop1.example.com.", RRType::A);
+        assert_eq!(answer_types(&resp), vec![RRType::CNAME, RRType::CNAME]);
+        assert_eq!(resp.header.rcode, Rcode::NoError);
+
+        let resp = query(&zone, "out.example.com.", RRType::A);
+        assert_eq!(answer_types(&resp), vec![RRType::CNAME]);
+        assert_eq!(resp.header.ns_count, 0);
+    }
+
+    #[test]
+    fn test_wildcard() {
+        let zone = build_zone("example.com.", ZONE);
+        let resp = query(&zone, "a.wild.example.com.", RRType::TXT);
+        let answer = &resp.section(SectionType::Answer).unwrap()[0];
+        assert_eq!(answer.name, Name::new("a.wild.example.com.").unwrap());
+        assert_eq!(answer.typ, RRType::TXT);
+
+        let resp = query(&zone, "a.b.alias.example.com.", RRType::A);
+        assert_eq!(answer_types(&resp), vec![RRType::CNAME, RRType::A]);
+        assert_eq!(
+            resp.section(SectionType::Answer).unwrap()[0].name,
+            Name::new("a.b.alias.example.com.").unwrap()
+        );
+
+        let resp = query(&zone, "wild.example.com.", RRType::TXT);
+        assert_eq!(resp.header.rcode, Rcode::NoError);
+        assert_eq!(resp.header.an_count, 0);
+    }
+
+    #[test]
+    fn test_delegation() {
+        let zone = build_zone("example.com.", ZONE);
+        for name in ["sub.example.com.", "www.sub.example.com."] {
+            let resp = query(&zone, name, RRType::A);
+            assert!(!resp.header.is_flag_set(HeaderFlag::AuthAnswer));
+            assert_eq!(resp.header.rcode, Rcode::NoError);
+            assert_eq!(resp.header.an_count, 0);
+            let ns = &resp.section(SectionType::Authority).unwrap()[0];
+            assert_eq!(ns.typ, RRType::NS);
+            assert_eq!(ns.rdatas.len(), 2);
+            let glue = resp.section(SectionType::Additional).unwrap();
+            assert_eq!(glue.len(), 1);
+            assert_eq!(glue[0].name, Name::new("ns.sub.example.com.").unwrap());
+        }
+
+        //glue is only returned in referral
+        let resp = query(&zone, "occluded.example.com.", RRType::MX);
+        assert_eq!(answer_types(&resp), vec![RRType::MX]);
+        assert!(resp.section(SectionType::Additional).is_none());
+
+        let resp = query(&zone, "sub.example.com.", RRType::DS);
+        assert!(resp.header.is_flag_set(HeaderFlag::AuthAnswer));
+        assert_eq!(answer_types(&resp), vec![RRType::DS]);
+    }
+
+    #[test]
+    fn test_dname() {
+        let zone = build_zone("example.com.", ZONE);
+        let resp = query(&zone, "www.old.example.com.", RRType::A);
+        assert_eq!(answer_types(&resp), vec![RRType::DNAME, RRType::CNAME]);
+        let cname = &resp.section(SectionType::Answer).unwrap()[1];
+        assert_eq!(
+            cname.rdatas[0].to_string(),
+            "www.new.long-target-name.example.net."
+        );
+
+        let resp = query(&zone, "old.example.com.", RRType::DNAME);
+        assert_eq!(answer_types(&resp), vec![RRType::DNAME]);
+
+        let long = format!(
+            "{}.{}.old.example.com.",
+            vec!["a".repeat(60); 3].join("."),
+            "b".repeat(50)
+        );
+        let resp = query(&zone, &long, RRType::A);
+        assert_eq!(resp.header.rcode, Rcode::YXDomain);
+    }
+
+    #[test]
+    fn test_class_and_edns() {
+        let zone = build_zone("example.com.", ZONE);
+        let mut req = Request::new(Name::new("ns1.example.com.").unwrap(), RRType::A);
+        req.question.class = RRClass::CH;
+        let resp = zone.query(&req, None);
+        assert_eq!(resp.header.rcode, Rcode::Refused);
+        assert!(resp.get_edns().is_none());
+
+        let edns = Edns {
+            version: 0,
+            extened_rcode: 0,
+            udp_size: 1232,
+            dnssec_aware: false,
+            options: None,
+        };
+        req.question.class = RRClass::IN;
+        let resp = zone.query(&req, Some(&edns));
+        assert_eq!(answer_types(&resp), vec![RRType::A]);
+        assert_eq!(resp.get_edns().unwrap().udp_size, 1232);
+        assert!(query(&zone, "ns1.example.com.", RRType::A)
+            .get_edns()
+            .is_none());
+    }
+}
@@ -0,0 +1,7 @@
+mod memory_zone;
+mod update;
+
+pub use memory_zone::Zone;
+pub(crate) use memory_zone::{cname_target, synthesize_cname};
+pub use update::ChangeSet;
+pub(crate) use update::{group_rrs, serial_gt, soa_serial};
@@ -0,0 +1,531 @@
+use crate::axfr::split_rrset;
+use crate::name::Name;
+use crate::rcode::Rcode;
+use crate::rdata::RData;
+use crate::rr_type::RRType;
+use crate::rrset::RRset;
+use crate::update::{Prerequisite, Update, UpdateMessage};
+use crate::zone::Zone;
+use anyhow::{bail, ensure};
+
+//difference between two versions of a zone, which is also one difference
+//sequence of ixfr, rfc1995 4
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ChangeSet {
+    pub old_soa: RRset,
+    pub deleted: Vec<RRset>,
+    pub new_soa: RRset,
+    pub added: Vec<RRset>,
+}
+
+impl ChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.deleted.is_empty() && self.added.is_empty() && self.old_soa == self.new_soa
+    }
+
+    pub fn old_serial(&self) -> u32 {
+        soa_serial(&self.old_soa)
+    }
+
+    pub fn new_serial(&self) -> u32 {
+        soa_serial(&self.new_soa)
+    }
+
+    //changes of self followed by next, rr added by one and deleted by the
+    //other cancels out
+    pub fn merge(&self, next: &ChangeSet) -> anyhow::Result<ChangeSet> {
+        ensure!(
+            self.new_serial() == next.old_serial(),
+            "changes to {} can't be followed by changes from {}",
+            self.new_serial(),
+            next.old_serial()
+        );
+        let mut deleted = self
+            .deleted
+            .iter()
+            .flat_map(split_rrset)
+            .collect::<Vec<_>>();
+        let mut added = self.added.iter().flat_map(split_rrset).collect::<Vec<_>>();
+        for rr in next.deleted.iter().flat_map(split_rrset) {
+            match added.iter().position(|old| same_rr(old, &rr)) {
+                Some(pos) => {
+                    added.remove(pos);
+                }
+                None => deleted.push(rr),
+            }
+        }
+        for rr in next.added.iter().flat_map(split_rrset) {
+            match deleted.iter().position(|old| same_rr(old, &rr)) {
+                Some(pos) => {
+                    deleted.remove(pos);
+                }
+                None => added.push(rr),
+            }
+        }
+        Ok(ChangeSet {
+            old_soa: self.old_soa.clone(),
+            deleted: group_rrs(deleted),
+            new_soa: next.new_soa.clone(),
+            added: group_rrs(added),
+        })
+    }
+}
+
+impl Zone {
+    //prerequisites are checked before anything is changed, then updates are
+    //applied in place, invalid ones are ignored so the zone is never left
+    //half updated, serial of soa is increased if anything is changed,
+    //rfc2136 3
+    pub fn update(&mut self, msg: &UpdateMessage) -> Result<ChangeSet, Rcode> {
+        if !msg.zone.name.eq(self.origin()) || msg.zone.class != self.class() {
+            return Err(Rcode::NotAuth);
+        }
+        let old_soa = self.soa().cloned().ok_or(Rcode::ServFail)?;
+        self.check_prerequisites(&msg.prerequisites)?;
+        if msg
+            .updates
+            .iter()
+            .any(|update| !update.name().is_subdomain(self.origin()))
+        {
+            return Err(Rcode::NotZone);
+        }
+
+        //rrsets of touched names before the first change to them, which are
+        //compared with the result to get the difference
+        let mut staged: Vec<(&Name, Vec<RRset>)> = Vec::new();
+        for update in &msg.updates {
+            if !staged.iter().any(|(name, _)| name.eq(&update.name())) {
+                let old = self.rrsets_at(update.name()).into_iter().cloned();
+                staged.push((update.name(), old.collect()));
+            }
+            self.apply_update(update);
+        }
+
+        let mut deleted = Vec::new();
+        let mut added = Vec::new();
+        for (name, old) in &staged {
+            let old = old.iter().collect::<Vec<_>>();
+            let new = self.rrsets_at(name);
+            let mut types = old
+                .iter()
+                .chain(new.iter())
+                .map(|rrset| rrset.typ)
+                .collect::<Vec<_>>();
+            types.sort_by_key(|typ| typ.as_u16());
+            types.dedup();
+            for typ in types.into_iter().filter(|&typ| typ != RRType::SOA) {
+                diff_rrset(
+                    find_rrset(&old, typ),
+                    find_rrset(&new, typ),
+                    &mut deleted,
+                    &mut added,
+                );
+            }
+        }
+
+        let mut new_soa = self.soa().cloned().ok_or(Rcode::ServFail)?;
+        if (!deleted.is_empty() || !added.is_empty())
+            && soa_serial(&new_soa) == soa_serial(&old_soa)
+        {
+            if let RData::SOA(ref mut soa) = new_soa.rdatas[0] {
+                soa.serial = soa.serial.wrapping_add(1);
+            }
+            self.delete_rrset(&new_soa.name, RRType::SOA);
+            if self.add_rrset(new_soa.clone()).is_err() {
+                self.restore(staged, old_soa);
+                return Err(Rcode::ServFail);
+            }
+        }
+        Ok(ChangeSet {
+            old_soa,
+            deleted,
+            new_soa,
+            added,
+        })
+    }
+
+    fn restore(&mut self, staged: Vec<(&Name, Vec<RRset>)>, soa: RRset) {
+        for (name, old) in staged {
+            let types = self
+                .rrsets_at(name)
+                .iter()
+                .map(|rrset| rrset.typ)
+                .collect::<Vec<_>>();
+            for typ in types {
+                self.delete_rrset(name, typ);
+            }
+            for rrset in old {
+                let _ = self.add_rrset(rrset);
+            }
+        }
+        self.delete_rrset(&soa.name, RRType::SOA);
+        let _ = self.add_rrset(soa);
+    }
+
+    //apply changes received from ixfr or read from journal, zone serial must
+    //match the old soa and every deleted rr must exist
+    pub fn apply_changeset(&mut self, changes: &ChangeSet) -> anyhow::Result<()> {
+        let serial = self.soa().map(soa_serial);
+        ensure!(
+            serial == Some(changes.old_serial()),
+            "zone serial {:?} doesn't match changes from {}",
+            serial,
+            changes.old_serial()
+        );
+        let mut zone = self.clone();
+        for rrset in &changes.deleted {
+            let mut current = match zone.get_rrset(&rrset.name, rrset.typ) {
+                Some(current) => current.clone(),
+                None => bail!("rrset {} {} to delete doesn't exist", rrset.name, rrset.typ),
+            };
+            for rdata in &rrset.rdatas {
+                let pos = current.rdatas.iter().position(|old| old == rdata);
+                match pos {
+                    Some(pos) => current.rdatas.remove(pos),
+                    None => bail!("rr of {} {} to delete doesn't exist", rrset.name, rrset.typ),
+                };
+            }
+            zone.delete_rrset(&rrset.name, rrset.typ);
+            if !current.rdatas.is_empty() {
+                zone.add_rrset(current)?;
+            }
+        }
+        for rrset in &changes.added {
+            zone.add_rrset(rrset.clone())?;
+        }
+        zone.delete_rrset(&changes.new_soa.name, RRType::SOA);
+        zone.add_rrset(changes.new_soa.clone())?;
+        *self = zone;
+        Ok(())
+    }
+
+    fn check_prerequisites(&self, prerequisites: &[Prerequisite]) -> Result<(), Rcode> {
+        //value dependent prerequisites of the same rrset are compared together
+        let mut values: Vec<RRset> = Vec::new();
+        for prerequisite in prerequisites {
+            if !prerequisite.name().is_subdomain(self.origin()) {
+                return Err(Rcode::NotZone);
+            }
+            match prerequisite {
+                Prerequisite::NameInUse(name) => {
+                    if self.rrsets_at(name).is_empty() {
+                        return Err(Rcode::NXDomain);
+                    }
+                }
+                Prerequisite::NameNotInUse(name) => {
+                    if !self.rrsets_at(name).is_empty() {
+                        return Err(Rcode::YXDomain);
+                    }
+                }
+                Prerequisite::RRsetExists(name, typ) => {
+                    if self.get_rrset(name, *typ).is_none() {
+                        return Err(Rcode::NXRRset);
+                    }
+                }
+                Prerequisite::RRsetNotExists(name, typ) => {
+                    if self.get_rrset(name, *typ).is_some() {
+                        return Err(Rcode::YXRRset);
+                    }
+                }
+                Prerequisite::RRsetExistsWithValue(rrset) => {
+                    match values.iter_mut().find(|value| value.is_same_rrset(rrset)) {
+                        Some(value) => value.rdatas.extend(rrset.rdatas.iter().cloned()),
+                        None => values.push(rrset.clone()),
+                    }
+                }
+            }
+        }
+
+        for value in values {
+            match self.get_rrset(&value.name, value.typ) {
+                Some(rrset) if same_rdatas(rrset, &value) => {}
+                _ => return Err(Rcode::NXRRset),
+            }
+        }
+        Ok(())
+    }
+
+    //invalid changes are ignored silently, rfc2136 3.4.2
+    fn apply_update(&mut self, update: &Update) {
+        let is_apex = update.name().eq(self.origin());
+        match update {
+            Update::Add(rrset) => {
+                let mut rrset = rrset.clone();
+                rrset.class = self.class();
+                if rrset.typ == RRType::SOA {
+                    let current = self.soa().map(soa_serial);
+                    let newer = current.is_some_and(|serial| serial_gt(soa_serial(&rrset), serial));
+                    if !is_apex || rrset.rdatas.len() != 1 || !newer {
+                        return;
+                    }
+                    self.delete_rrset(&rrset.name, RRType::SOA);
+                }
+                //cname conflicts with other data is rejected by zone
+                let _ = self.add_rrset(rrset);
+            }
+            Update::DeleteRRset(name, typ) => {
+                if !is_apex || (*typ != RRType::SOA && *typ != RRType::NS) {
+                    self.delete_rrset(name, *typ);
+                }
+            }
+            Update::DeleteName(name) => {
+                let types = self
+                    .rrsets_at(name)
+                    .iter()
+                    .map(|rrset| rrset.typ)
+                    .collect::<Vec<_>>();
+                for typ in types {
+                    if !is_apex || (typ != RRType::SOA && typ != RRType::NS) {
+                        self.delete_rrset(name, typ);
+                    }
+                }
+            }
+            Update::DeleteRR(rrset) => {
+                if rrset.typ == RRType::SOA {
+                    return;
+                }
+                let mut current = match self.get_rrset(&rrset.name, rrset.typ) {
+                    Some(current) => current.clone(),
+                    None => return,
+                };
+                current.rdatas.retain(|rdata| !rrset.rdatas.contains(rdata));
+                //the last ns of zone apex is kept
+                if current.rdatas.is_empty() && is_apex && rrset.typ == RRType::NS {
+                    return;
+                }
+                self.delete_rrset(&rrset.name, rrset.typ);
+                if !current.rdatas.is_empty() {
+                    let _ = self.add_rrset(current);
+                }
+            }
+        }
+    }
+}
+
+//rrs only in old are deleted and rrs only in new are added, ttl change
+//replaces the whole rrset
+fn diff_rrset(
+    old: Option<&RRset>,
+    new: Option<&RRset>,
+    deleted: &mut Vec<RRset>,
+    added: &mut Vec<RRset>,
+) {
+    let ttl_changed = matches!((old, new), (Some(old), Some(new)) if old.ttl != new.ttl);
+    let only_in = |rrset: Option<&RRset>, other: Option<&RRset>, changes: &mut Vec<RRset>| {
+        if let Some(rrset) = rrset {
+            let mut rrset = rrset.clone();
+            if !ttl_changed {
+                if let Some(other) = other {
+                    rrset.rdatas.retain(|rdata| !other.rdatas.contains(rdata));
+                }
+            }
+            if !rrset.rdatas.is_empty() {
+                changes.push(rrset);
+            }
+        }
+    };
+    only_in(old, new, deleted);
+    only_in(new, old, added);
+}
+
+fn find_rrset<'a>(rrsets: &[&'a RRset], typ: RRType) -> Option<&'a RRset> {
+    rrsets.iter().find(|rrset| rrset.typ == typ).copied()
+}
+
+fn same_rdatas(a: &RRset, b: &RRset) -> bool {
+    a.rdatas.iter().all(|rdata| b.rdatas.contains(rdata))
+        && b.rdatas.iter().all(|rdata| a.rdatas.contains(rdata))
+}
+
+fn same_rr(a: &RRset, b: &RRset) -> bool {
+    a == b && a.ttl == b.ttl && a.class == b.class
+}
+
+//merge rrs of the same rrset
+pub(crate) fn group_rrs(rrs: Vec<RRset>) -> Vec<RRset> {
+    let mut rrsets: Vec<RRset> = Vec::new();
+    for mut rr in rrs {
+        match rrsets
+            .iter_mut()
+            .find(|rrset| rrset.is_same_rrset(&rr) && rrset.ttl == rr.ttl)
+        {
+            Some(rrset) => rrset.rdatas.append(&mut rr.rdatas),
+            None => rrsets.push(rr),
+        }
+    }
+    rrsets
+}
+
+pub(crate) fn soa_serial(soa: &RRset) -> u32 {
+    match soa.rdatas.first() {
+        Some(RData::SOA(soa)) => soa.serial,
+        _ => 0,
+    }
+}
+
+//serial number arithmetic, rfc1982 3.2
+pub(crate) fn serial_gt(a: u32, b: u32) -> bool {
+    a != b && a.wrapping_sub(b) < 0x8000_0000
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::update::UpdateBuilder;
+    use crate::util::fixture::{build_zone, name, rrset, update, ZONE};
+
+    #[test]
+    fn test_prerequisites() {
+        let mut zone = build_zone("example.com.", ZONE);
+        for (rcode, f) in [
+            (
+                Rcode::NXDomain,
+                Box::new(|b: &mut UpdateBuilder| {
+                    b.name_in_use(name("nonexist.example.com."));
+                }) as Box<dyn FnOnce(&mut UpdateBuilder)>,
+            ),
+            (
+                Rcode::YXDomain,
+                Box::new(|b: &mut UpdateBuilder| {
+                    b.name_not_in_use(name("www.example.com."));
+                }),
+            ),
+            (
+                Rcode::NXRRset,
+                Box::new(|b: &mut UpdateBuilder| {
+                    b.rrset_exists(name("www.example.com."), RRType::AAAA);
+                }),
+            ),
+            (
+                Rcode::YXRRset,
+                Box::new(|b: &mut UpdateBuilder| {
+                    b.rrset_not_exists(name("www.example.com."), RRType::A);
+                }),
+            ),
+            (
+                Rcode::NXRRset,
+                Box::new(|b: &mut UpdateBuilder| {
+                    b.rrset_exists_with_value(rrset("www.example.com. 0 IN A 3.3.3.3"));
+                }),
+            ),
+            (
+                Rcode::NotZone,
+                Box::new(|b: &mut UpdateBuilder| {
+                    b.name_in_use(name("www.example.net."));
+                }),
+            ),
+            (
+                Rcode::NotZone,
+                Box::new(|b: &mut UpdateBuilder| {
+                    b.add_rrset(rrset("www.example.net. 300 IN A 1.1.1.1"));
+                }),
+            ),
+        ] {
+            let result = update(&mut zone, |b| {
+                b.add_rrset(rrset("new.example.com. 300 IN A 5.5.5.5"));
+                f(b);
+            });
+            assert_eq!(result, Err(rcode));
+            assert!(zone
+                .get_rrset(&name("new.example.com."), RRType::A)
+                .is_none());
+        }
+
+        let changes = update(&mut zone, |b| {
+            b.name_in_use(name("www.example.com."))
+                .name_not_in_use(name("new.example.com."))
+                .rrset_exists(name("web.example.com."), RRType::CNAME)
+                .rrset_not_exists(name("www.example.com."), RRType::AAAA)
+                .rrset_exists_with_value(rrset("www.example.com. 0 IN A 4.4.4.4"))
+                .rrset_exists_with_value(rrset("www.example.com. 0 IN A 3.3.3.3"));
+        })
+        .unwrap();
+        assert!(changes.is_empty());
+        assert_eq!(changes.new_serial(), 10);
+
+        let mut msg = UpdateMessage::new(name("example.net."));
+        UpdateBuilder::new(&mut msg).done();
+        assert_eq!(zone.update(&msg), Err(Rcode::NotAuth));
+    }
+
+    #[test]
+    fn test_apply_update() {
+        let mut zone = build_zone("example.com.", ZONE);
+        let changes = update(&mut zone, |b| {
+            b.add_rrset(rrset("new.example.com. 300 IN A 5.5.5.5"))
+                .add_rrset(rrset("www.example.com. 3600 IN A 3.3.3.3"))
+                .delete_rr(rrset("www.example.com. 0 IN A 4.4.4.4"))
+                .delete_name(name("ns2.example.com."))
+                .delete_rrset(name("example.com."), RRType::NS)
+                .delete_rr(rrset("example.com. 0 IN NS ns2.example.com."))
+                .add_rrset(rrset("web.example.com. 300 IN A 6.6.6.6"));
+        })
+        .unwrap();
+        assert_eq!(changes.old_serial(), 10);
+        assert_eq!(changes.new_serial(), 11);
+        assert_eq!(
+            changes.deleted,
+            vec![
+                rrset("www.example.com. 3600 IN A 4.4.4.4"),
+                rrset("ns2.example.com. 3600 IN A 2.2.2.2"),
+                rrset("example.com. 3600 IN NS ns2.example.com."),
+            ]
+        );
+        assert_eq!(
+            changes.added,
+            vec![rrset("new.example.com. 300 IN A 5.5.5.5")]
+        );
+        assert_eq!(soa_serial(zone.soa().unwrap()), 11);
+        assert_eq!(
+            zone.get_rrset(&name("example.com."), RRType::NS)
+                .unwrap()
+                .rdatas
+                .len(),
+            1
+        );
+        //cname can't coexist with other data
+        assert!(zone
+            .get_rrset(&name("web.example.com."), RRType::A)
+            .is_none());
+
+        //last ns of apex and soa can't be deleted
+        let changes = update(&mut zone, |b| {
+            b.delete_rr(rrset("example.com. 0 IN NS ns1.example.com."))
+                .delete_name(name("example.com."))
+                .delete_rrset(name("example.com."), RRType::SOA);
+        })
+        .unwrap();
+        assert!(changes.is_empty());
+        assert!(zone.soa().is_some());
+
+        //explicit soa with larger serial is used, smaller one is ignored
+        let changes = update(&mut zone, |b| {
+            b.add_rrset(rrset(
+                "example.com. 3600 IN SOA ns1.example.com. hostmaster.example.com. 5 3600 900 604800 300",
+            ));
+        })
+        .unwrap();
+        assert!(changes.is_empty());
+        let changes = update(&mut zone, |b| {
+            b.add_rrset(rrset(
+                "example.com. 3600 IN SOA ns1.example.com. hostmaster.example.com. 100 3600 900 604800 300",
+            ))
+            .delete_rrset(name("web.example.com."), RRType::CNAME);
+        })
+        .unwrap();
+        assert_eq!(changes.new_serial(), 100);
+        assert_eq!(
+            changes.deleted,
+            vec![rrset("web.example.com. 3600 IN CNAME www.example.com.")]
+        );
+
+        //ttl change replaces the rrset
+        let changes = update(&mut zone, |b| {
+            b.add_rrset(rrset("new.example.com. 600 IN A 5.5.5.5"));
+        })
+        .unwrap();
+        assert_eq!(changes.new_serial(), 101);
+        assert_eq!(changes.deleted[0].ttl.0, 300);
+        assert_eq!(changes.added[0].ttl.0, 600);
+    }
+}
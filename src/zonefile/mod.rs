@@ -0,0 +1,5 @@
+mod reader;
+mod writer;
+
+pub use reader::{parse_zone, read_zone_file};
+pub use writer::ZoneWriter;
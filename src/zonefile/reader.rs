@@ -0,0 +1,528 @@
+use crate::name::Name;
+use crate::rdata::RData;
+use crate::rr_class::RRClass;
+use crate::rr_type::RRType;
+use crate::rrset::{RRTtl, RRset};
+use crate::util::StringBuffer;
+use anyhow::{anyhow, bail, ensure, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const MAX_INCLUDE_DEPTH: usize = 16;
+const MAX_GENERATE_COUNT: u32 = 65536;
+
+//one record or directive, parentheses and comments are removed
+struct Entry {
+    line: usize,
+    indented: bool,
+    text: String,
+}
+
+fn split_entries(content: &str) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut text = String::new();
+    let mut start_line = 0;
+    let mut indented = false;
+    let mut depth = 0;
+    for (i, line) in content.lines().enumerate() {
+        if depth == 0 {
+            start_line = i + 1;
+            indented = line.starts_with([' ', '\t']);
+            text.clear();
+        }
+
+        let mut in_quote = false;
+        let mut escape = false;
+        for c in line.chars() {
+            if escape {
+                escape = false;
+                text.push(c);
+                continue;
+            }
+            match c {
+                '\\' => {
+                    escape = true;
+                    text.push(c);
+                }
+                '"' => {
+                    in_quote = !in_quote;
+                    text.push(c);
+                }
+                ';' if !in_quote => break,
+                '(' if !in_quote => {
+                    depth += 1;
+                    text.push(' ');
+                }
+                ')' if !in_quote => {
+                    ensure!(depth > 0, "line {}: unbalanced parentheses", i + 1);
+                    depth -= 1;
+                    text.push(' ');
+                }
+                _ => text.push(c),
+            }
+        }
+        ensure!(!in_quote, "line {}: quote isn't in pair", i + 1);
+
+        if depth == 0 {
+            if !text.trim().is_empty() {
+                entries.push(Entry {
+                    line: start_line,
+                    indented,
+                    text: text.trim().to_string(),
+                });
+            }
+        } else {
+            text.push(' ');
+        }
+    }
+    ensure!(depth == 0, "line {}: parentheses isn't closed", start_line);
+    Ok(entries)
+}
+
+struct Parser {
+    origin: Name,
+    default_ttl: Option<RRTtl>,
+    last_name: Option<Name>,
+    last_ttl: Option<RRTtl>,
+    last_class: RRClass,
+    rrsets: Vec<RRset>,
+    index: HashMap<(Name, RRType, RRClass), usize>,
+    include_depth: usize,
+}
+
+impl Parser {
+    fn new(origin: Name) -> Self {
+        Parser {
+            origin,
+            default_ttl: None,
+            last_name: None,
+            last_ttl: None,
+            last_class: RRClass::IN,
+            rrsets: Vec::new(),
+            index: HashMap::new(),
+            include_depth: 0,
+        }
+    }
+
+    fn parse(&mut self, content: &str, path: Option<&Path>) -> Result<()> {
+        let location = |line: usize| match path {
+            Some(path) => format!("{} line {}", path.display(), line),
+            None => format!("line {}", line),
+        };
+        for entry in split_entries(content).map_err(|e| match path {
+            Some(path) => anyhow!("{} {}", path.display(), e),
+            None => e,
+        })? {
+            let result = if entry.text.starts_with('$') {
+                self.parse_directive(&entry, path)
+            } else {
+                self.parse_record(&entry.text, entry.indented)
+            };
+            if let Err(e) = result {
+                bail!("{}: {}", location(entry.line), e);
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_directive(&mut self, entry: &Entry, path: Option<&Path>) -> Result<()> {
+        let origin = self.origin.clone();
+        let mut buf = StringBuffer::with_origin(&entry.text, &origin);
+        let directive = buf.read_str().unwrap().to_uppercase();
+        match directive.as_ref() {
+            "$ORIGIN" => {
+                let origin = buf.read_name()?;
+                ensure!(buf.read_str().is_none(), "extra data after origin");
+                self.origin = origin;
+            }
+            "$TTL" => {
+                let ttl = buf.read::<RRTtl>()?;
+                ensure!(buf.read_str().is_none(), "extra data after ttl");
+                self.default_ttl = Some(ttl);
+            }
+            "$INCLUDE" => {
+                let file = buf.read_str().ok_or(anyhow!("include file is missing"))?;
+                let origin = match buf.read_left().map(|s| s.trim()) {
+                    Some(s) if !s.is_empty() => {
+                        let mut buf = StringBuffer::with_origin(s, &origin);
+                        let origin = buf.read_name()?;
+                        ensure!(buf.read_str().is_none(), "extra data after include");
+                        Some(origin)
+                    }
+                    _ => None,
+                };
+                ensure!(
+                    self.include_depth < MAX_INCLUDE_DEPTH,
+                    "include is nested too deep"
+                );
+                let mut file_path = PathBuf::from(file);
+                if file_path.is_relative() {
+                    if let Some(dir) = path.and_then(|path| path.parent()) {
+                        file_path = dir.join(file_path);
+                    }
+                }
+                self.include(&file_path, origin)?;
+            }
+            "$GENERATE" => self.generate(&mut buf)?,
+            _ => bail!("unknown directive {}", directive),
+        }
+        Ok(())
+    }
+
+    //origin and owner name are restored after the included file
+    fn include(&mut self, path: &Path, origin: Option<Name>) -> Result<()> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("read {} failed: {}", path.display(), e))?;
+        let saved_origin = self.origin.clone();
+        let saved_name = self.last_name.clone();
+        if let Some(origin) = origin {
+            self.origin = origin;
+        }
+        self.include_depth += 1;
+        let result = self.parse(&content, Some(path));
+        self.include_depth -= 1;
+        self.origin = saved_origin;
+        self.last_name = saved_name;
+        result
+    }
+
+    //$GENERATE start-stop[/step] lhs [ttl] [class] type rhs
+    fn generate(&mut self, buf: &mut StringBuffer) -> Result<()> {
+        let range = buf.read_str().ok_or(anyhow!("generate range is missing"))?;
+        let (range, step) = match range.find('/') {
+            Some(pos) => (&range[..pos], range[(pos + 1)..].parse::<u32>()?),
+            None => (range, 1),
+        };
+        let pos = range.find('-').ok_or(anyhow!("invalid generate range"))?;
+        let start = range[..pos].parse::<u32>()?;
+        let stop = range[(pos + 1)..].parse::<u32>()?;
+        ensure!(start <= stop && step > 0, "invalid generate range");
+        ensure!(
+            (stop - start) / step < MAX_GENERATE_COUNT,
+            "generate range is too large"
+        );
+
+        let lhs = buf.read_str().ok_or(anyhow!("generate lhs is missing"))?;
+        //only owner and rdata are substituted, ttl class and type are kept
+        let mut fields = Vec::new();
+        loop {
+            let token = buf.read_str().ok_or(anyhow!("generate type is missing"))?;
+            fields.push(token);
+            if RRTtl::from_str(token).is_err() && RRClass::from_str(token).is_err() {
+                break;
+            }
+        }
+        let rhs = buf.read_left().ok_or(anyhow!("generate rhs is missing"))?;
+        let fields = fields.join(" ");
+        for i in (start..=stop).step_by(step as usize) {
+            let record = format!("{} {} {}", substitute(lhs, i)?, fields, substitute(rhs, i)?);
+            self.parse_record(&record, false)?;
+        }
+        Ok(())
+    }
+
+    fn parse_record(&mut self, text: &str, indented: bool) -> Result<()> {
+        let mut buf = StringBuffer::with_origin(text, &self.origin);
+        let name = if indented {
+            self.last_name
+                .clone()
+                .ok_or(anyhow!("no previous owner name"))?
+        } else {
+            buf.read_name()?
+        };
+
+        let mut ttl = None;
+        let mut class = None;
+        let typ = loop {
+            let token = buf.read_str().ok_or(anyhow!("rr type is missing"))?;
+            if ttl.is_none() {
+                if let Ok(t) = RRTtl::from_str(token) {
+                    ttl = Some(t);
+                    continue;
+                }
+            }
+            if class.is_none() {
+                if let Ok(c) = RRClass::from_str(token) {
+                    class = Some(c);
+                    continue;
+                }
+            }
+            break RRType::from_str(token)?;
+        };
+
+        let rdata_str = buf.read_left().map(|s| s.trim()).unwrap_or("");
+        ensure!(!rdata_str.is_empty(), "rdata is missing");
+        let mut rdata_buf = StringBuffer::with_origin(rdata_str, &self.origin);
+        let rdata = RData::from_string_buffer(typ, &mut rdata_buf)?;
+        if let Some(left) = rdata_buf.read_str() {
+            bail!("extra rdata {}", left);
+        }
+
+        let ttl = match ttl.or(self.default_ttl).or(self.last_ttl) {
+            Some(ttl) => ttl,
+            None => match rdata {
+                //without any ttl, use the minimum of soa
+                RData::SOA(ref soa) => RRTtl(soa.minimum),
+                _ => bail!("no ttl specified"),
+            },
+        };
+        if self.default_ttl.is_none() {
+            self.last_ttl = Some(ttl);
+        }
+        let class = class.unwrap_or(self.last_class);
+        self.last_class = class;
+        self.last_name = Some(name.clone());
+        self.add_rdata(name, typ, class, ttl, rdata);
+        Ok(())
+    }
+
+    fn add_rdata(&mut self, name: Name, typ: RRType, class: RRClass, ttl: RRTtl, rdata: RData) {
+        let key = (name, typ, class);
+        if let Some(&i) = self.index.get(&key) {
+            let rrset = &mut self.rrsets[i];
+            if !rrset.rdatas.contains(&rdata) {
+                rrset.rdatas.push(rdata);
+            }
+        } else {
+            self.index.insert(key.clone(), self.rrsets.len());
+            self.rrsets.push(RRset {
+                name: key.0,
+                typ,
+                class,
+                ttl,
+                rdatas: vec![rdata],
+            });
+        }
+    }
+}
+
+//replace $ with the iterator, ${offset,width,base} is supported and \$ is
+//literal dollar sign
+fn substitute(template: &str, value: u32) -> Result<String> {
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'$') => {
+                result.push('$');
+                chars.next();
+            }
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let mut modifier = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => modifier.push(c),
+                        None => bail!("generate modifier isn't closed"),
+                    }
+                }
+                result.push_str(&format_modifier(&modifier, value)?);
+            }
+            '$' => result.push_str(&value.to_string()),
+            _ => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+fn format_modifier(modifier: &str, value: u32) -> Result<String> {
+    let mut fields = modifier.split(',');
+    let offset = match fields.next() {
+        Some(s) if !s.is_empty() => s.parse::<i64>()?,
+        _ => 0,
+    };
+    let width = match fields.next() {
+        Some(s) => s.parse::<usize>()?,
+        None => 0,
+    };
+    let base = fields.next().unwrap_or("d");
+    ensure!(
+        fields.next().is_none(),
+        "invalid generate modifier {}",
+        modifier
+    );
+
+    let value = value as i64 + offset;
+    ensure!(value >= 0, "generated value is negative");
+    Ok(match base {
+        "d" => format!("{:0width$}", value, width = width),
+        "o" => format!("{:0width$o}", value, width = width),
+        "x" => format!("{:0width$x}", value, width = width),
+        "X" => format!("{:0width$X}", value, width = width),
+        _ => bail!("generate base {} isn't supported", base),
+    })
+}
+
+pub fn parse_zone(content: &str, origin: &Name) -> Result<Vec<RRset>> {
+    let mut parser = Parser::new(origin.clone());
+    parser.parse(content, None)?;
+    Ok(parser.rrsets)
+}
+
+pub fn read_zone_file<P: AsRef<Path>>(path: P, origin: &Name) -> Result<Vec<RRset>> {
+    let path = path.as_ref();
+    let content =
+        fs::read_to_string(path).map_err(|e| anyhow!("read {} failed: {}", path.display(), e))?;
+    let mut parser = Parser::new(origin.clone());
+    parser.parse(&content, Some(path))?;
+    Ok(parser.rrsets)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    fn rrset_strs(rrsets: &[RRset]) -> Vec<String> {
+        rrsets
+            .iter()
+            .map(|rrset| rrset.to_string())
+            .collect::<Vec<String>>()
+    }
+
+    #[test]
+    fn test_parse_zone() {
+        let zone = r#"
+$TTL 1h
+@   IN  SOA ns1 hostmaster (
+            2021010101 ; serial
+            3600       ; refresh
+            900 1w
+            300 )
+    IN  NS  ns1
+    NS  ns2.example.net.
+ns1 A   1.1.1.1
+    3600 IN AAAA 2001:db8::1
+www IN 300 A 2.2.2.2
+www A   3.3.3.3
+www A   2.2.2.2
+mx  MX  10 mail
+txt TXT "a;b" "c(d)"
+$ORIGIN sub.example.com.
+a   CNAME www.example.com.
+b   CNAME a
+"#;
+        let origin = Name::new("example.com.").unwrap();
+        let rrsets = parse_zone(zone, &origin).unwrap();
+        assert_eq!(
+            rrset_strs(&rrsets),
+            vec![
+                "example.com.\t3600\tIN\tSOA\tns1.example.com. hostmaster.example.com. 2021010101 3600 900 604800 300\n",
+                "example.com.\t3600\tIN\tNS\tns1.example.com.\nexample.com.\t3600\tIN\tNS\tns2.example.net.\n",
+                "ns1.example.com.\t3600\tIN\tA\t1.1.1.1\n",
+                "ns1.example.com.\t3600\tIN\tAAAA\t2001:db8::1\n",
+                "www.example.com.\t300\tIN\tA\t2.2.2.2\nwww.example.com.\t300\tIN\tA\t3.3.3.3\n",
+                "mx.example.com.\t3600\tIN\tMX\t10 mail.example.com.\n",
+                "txt.example.com.\t3600\tIN\tTXT\t\"a\\;b\" \"c(d)\"\n",
+                "a.sub.example.com.\t3600\tIN\tCNAME\twww.example.com.\n",
+                "b.sub.example.com.\t3600\tIN\tCNAME\ta.sub.example.com.\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ttl_inherit() {
+        let origin = Name::new("example.com.").unwrap();
+        let zone = "a 100 A 1.1.1.1\nb A 2.2.2.2\n$TTL 200\nc A 3.3.3.3\n";
+        let rrsets = parse_zone(zone, &origin).unwrap();
+        let ttls: Vec<u32> = rrsets.iter().map(|rrset| rrset.ttl.0).collect();
+        assert_eq!(ttls, vec![100, 100, 200]);
+
+        let zone = "@ SOA ns1 hostmaster 1 3600 900 604800 60\na A 1.1.1.1\n";
+        let rrsets = parse_zone(zone, &origin).unwrap();
+        assert_eq!(rrsets[0].ttl.0, 60);
+        assert_eq!(rrsets[1].ttl.0, 60);
+
+        assert!(parse_zone("a A 1.1.1.1\n", &origin).is_err());
+    }
+
+    #[test]
+    fn test_generate() {
+        let origin = Name::new("1.168.192.in-addr.arpa.").unwrap();
+        let zone = "$TTL 300\n$GENERATE 1-3 $ PTR host-${10,3,d}.example.com.\n$GENERATE 0-16/8 h${0,2,x} 60 IN A 10.0.0.$\n";
+        let rrsets = parse_zone(zone, &origin).unwrap();
+        assert_eq!(
+            rrset_strs(&rrsets),
+            vec![
+                "1.1.168.192.in-addr.arpa.\t300\tIN\tPTR\thost-011.example.com.\n",
+                "2.1.168.192.in-addr.arpa.\t300\tIN\tPTR\thost-012.example.com.\n",
+                "3.1.168.192.in-addr.arpa.\t300\tIN\tPTR\thost-013.example.com.\n",
+                "h00.1.168.192.in-addr.arpa.\t60\tIN\tA\t10.0.0.0\n",
+                "h08.1.168.192.in-addr.arpa.\t60\tIN\tA\t10.0.0.8\n",
+                "h10.1.168.192.in-addr.arpa.\t60\tIN\tA\t10.0.0.16\n",
+            ]
+        );
+        //iterator isn't substituted in ttl, class or type
+        assert!(parse_zone("$GENERATE 1-2 h$ $ A 10.0.0.$\n", &origin).is_err());
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let origin = Name::new("example.com.").unwrap();
+        let cases = vec![
+            ("$TTL 300\na A 1.1.1.1\nb A 1.1.1\n", "line 3:"),
+            ("$TTL 300\n  A 1.1.1.1\n", "line 2:"),
+            ("$TTL 300\na TXT \"abc\n", "line 2:"),
+            ("$TTL 300\na SOA ns1 hostmaster (\n1 2 3 4 5\n", "line 2:"),
+            ("$TTL 300\n\n\na A 1.1.1.1 2.2.2.2\n", "line 4:"),
+            ("$TTL 300\n$FOO bar\n", "line 2:"),
+        ];
+        for (zone, location) in cases {
+            let err = parse_zone(zone, &origin).unwrap_err().to_string();
+            assert!(err.starts_with(location), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_include() {
+        let dir = env::temp_dir().join(format!("r53-zonefile-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("example.com.zone"),
+            "$TTL 300\n@ NS ns1\n$include hosts.zone sub\nafter A 3.3.3.3\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("hosts.zone"),
+            "www A 1.1.1.1\n  AAAA ::1\nbad A 1\n",
+        )
+        .unwrap();
+
+        let origin = Name::new("example.com.").unwrap();
+        let err = read_zone_file(dir.join("example.com.zone"), &origin)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("example.com.zone line 3:"), "{}", err);
+        assert!(err.contains("hosts.zone line 3:"), "{}", err);
+
+        fs::remove_file(dir.join("hosts.zone")).unwrap();
+        let err = read_zone_file(dir.join("example.com.zone"), &origin)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with(&format!(
+                "{} line 3:",
+                dir.join("example.com.zone").display()
+            )),
+            "{}",
+            err
+        );
+
+        fs::write(dir.join("hosts.zone"), "www A 1.1.1.1\n  AAAA ::1\n").unwrap();
+        let rrsets = read_zone_file(dir.join("example.com.zone"), &origin).unwrap();
+        let names: Vec<String> = rrsets.iter().map(|rrset| rrset.name.to_string()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "example.com.",
+                "www.sub.example.com.",
+                "www.sub.example.com.",
+                "after.example.com.",
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
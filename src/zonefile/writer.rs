@@ -0,0 +1,248 @@
+use crate::name::Name;
+use crate::rdata::RData;
+use crate::rr_type::RRType;
+use crate::rrset::RRset;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub struct ZoneWriter {
+    origin: Name,
+    absolute_name: bool,
+}
+
+impl ZoneWriter {
+    pub fn new(origin: Name) -> Self {
+        ZoneWriter {
+            origin,
+            absolute_name: false,
+        }
+    }
+
+    pub fn absolute_name(mut self, absolute_name: bool) -> Self {
+        self.absolute_name = absolute_name;
+        self
+    }
+
+    //soa comes first, then other rrsets in canonical order of name and type
+    pub fn write<W: Write>(&self, w: &mut W, rrsets: &[RRset]) -> Result<()> {
+        let mut rrsets: Vec<&RRset> = rrsets
+            .iter()
+            .filter(|rrset| !rrset.rdatas.is_empty() && rrset.typ != RRType::OPT)
+            .collect();
+        rrsets.sort_by(|a, b| {
+            (b.typ == RRType::SOA)
+                .cmp(&(a.typ == RRType::SOA))
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| a.typ.cmp(&b.typ))
+        });
+
+        writeln!(w, "$ORIGIN {}", self.origin)?;
+        let default_ttl = default_ttl(&rrsets);
+        if let Some(ttl) = default_ttl {
+            writeln!(w, "$TTL {}", ttl)?;
+        }
+
+        let mut lines = Vec::new();
+        let mut last_name = None;
+        for rrset in rrsets {
+            let owner = if last_name == Some(&rrset.name) {
+                String::new()
+            } else {
+                self.name_to_str(&rrset.name)
+            };
+            last_name = Some(&rrset.name);
+            let mut rdatas = rrset.rdatas.clone();
+            rdatas.sort();
+            for (i, rdata) in rdatas.iter().enumerate() {
+                let ttl = rrset.rr_ttl(rdata);
+                lines.push([
+                    if i == 0 { owner.clone() } else { String::new() },
+                    if default_ttl == Some(ttl.0) {
+                        String::new()
+                    } else {
+                        ttl.to_string()
+                    },
+                    rrset.class.to_string(),
+                    rrset.typ.to_string(),
+                    self.rdata_to_str(rdata),
+                ]);
+            }
+        }
+
+        let mut widths = [0; 4];
+        for line in &lines {
+            for (width, column) in widths.iter_mut().zip(line.iter()) {
+                *width = (*width).max(column.len());
+            }
+        }
+        for line in &lines {
+            for (width, column) in widths.iter().zip(line.iter()) {
+                write!(w, "{:<width$} ", column, width = width)?;
+            }
+            writeln!(w, "{}", line[4])?;
+        }
+        Ok(())
+    }
+
+    pub fn write_to_string(&self, rrsets: &[RRset]) -> Result<String> {
+        let mut buf = Vec::new();
+        self.write(&mut buf, rrsets)?;
+        Ok(String::from_utf8(buf)?)
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P, rrsets: &[RRset]) -> Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write(&mut w, rrsets)?;
+        w.flush()?;
+        Ok(())
+    }
+
+    //names in rdata are relative to origin as well as owner names
+    fn rdata_to_str(&self, rdata: &RData) -> String {
+        match rdata {
+            RData::NS(ns) => self.name_to_str(&ns.name),
+            RData::CName(cname) => self.name_to_str(&cname.name),
+            RData::PTR(ptr) => self.name_to_str(&ptr.name),
+            RData::DNAME(dname) => self.name_to_str(&dname.target),
+            RData::MX(mx) => format!("{} {}", mx.preference, self.name_to_str(&mx.name)),
+            RData::SRV(srv) => format!(
+                "{} {} {} {}",
+                srv.priority,
+                srv.weight,
+                srv.port,
+                self.name_to_str(&srv.target)
+            ),
+            RData::SOA(soa) => {
+                //keep the display of the numbers after mname and rname
+                let rdata = rdata.to_string();
+                let numbers = rdata.splitn(3, ' ').nth(2).unwrap_or("");
+                format!(
+                    "{} {} {}",
+                    self.name_to_str(&soa.mname),
+                    self.name_to_str(&soa.rname),
+                    numbers
+                )
+            }
+            _ => rdata.to_string(),
+        }
+    }
+
+    fn name_to_str(&self, name: &Name) -> String {
+        if self.absolute_name || !name.is_subdomain(&self.origin) {
+            name.to_string()
+        } else if name.eq(&self.origin) {
+            "@".to_string()
+        } else {
+            //only the separator before origin is removed, dot escaped in
+            //the last label is kept
+            let relative = name.strip_right(self.origin.label_count() - 1).to_string();
+            match relative.strip_suffix('.') {
+                Some(label) if label.bytes().rev().take_while(|&c| c == b'\\').count() % 2 == 0 => {
+                    label.to_string()
+                }
+                _ => relative,
+            }
+        }
+    }
+}
+
+//ttl of soa, or the most used one if there is no soa
+fn default_ttl(rrsets: &[&RRset]) -> Option<u32> {
+    if let Some(soa) = rrsets.iter().find(|rrset| rrset.typ == RRType::SOA) {
+        return Some(soa.ttl.0);
+    }
+
+    let mut counts = HashMap::new();
+    for rrset in rrsets {
+        *counts.entry(rrset.ttl.0).or_insert(0) += rrset.rdatas.len();
+    }
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        .map(|(ttl, _)| ttl)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::zonefile::parse_zone;
+
+    #[test]
+    fn test_write_zone() {
+        let zone = r#"
+www.example.com. 300 IN A 2.2.2.2
+www.example.com. 300 IN A 1.1.1.1
+example.com. 3600 IN NS ns1.example.com.
+example.com. 3600 IN MX 10 mail.example.net.
+mail.example.net. 3600 IN A 3.3.3.3
+*.example.com. 3600 IN TXT "wild card"
+example.com. 3600 IN SOA ns1.example.com. hostmaster.example.com. 1 3600 900 604800 300
+ns1.example.com. 3600 IN A 4.4.4.4
+"#;
+        let origin = Name::new("example.com.").unwrap();
+        let rrsets = parse_zone(zone, &origin).unwrap();
+        let output = ZoneWriter::new(origin.clone())
+            .write_to_string(&rrsets)
+            .unwrap();
+        assert_eq!(
+            output,
+            r#"$ORIGIN example.com.
+$TTL 3600
+@                     IN SOA ns1 hostmaster 1 3600 900 604800 300
+                      IN NS  ns1
+                      IN MX  10 mail.example.net.
+*                     IN TXT "wild card"
+ns1                   IN A   4.4.4.4
+www               300 IN A   1.1.1.1
+                  300 IN A   2.2.2.2
+mail.example.net.     IN A   3.3.3.3
+"#
+        );
+        assert_eq!(
+            canonical(parse_zone(&output, &origin).unwrap()),
+            canonical(rrsets.clone())
+        );
+
+        let output = ZoneWriter::new(origin.clone())
+            .absolute_name(true)
+            .write_to_string(&rrsets)
+            .unwrap();
+        assert!(output.contains("\nns1.example.com.      IN A   4.4.4.4\n"));
+        assert!(output.contains(" IN NS  ns1.example.com.\n"));
+        assert_eq!(
+            canonical(parse_zone(&output, &origin).unwrap()),
+            canonical(rrsets)
+        );
+    }
+
+    #[test]
+    fn test_write_escaped_name() {
+        let zone = r#"
+example.com. 3600 IN SOA ns1.example.com. hostmaster.example.com. 1 3600 900 604800 300
+example.com. 3600 IN NS a\..example.com.
+a\..example.com. 3600 IN A 1.1.1.1
+b\\.example.com. 3600 IN A 2.2.2.2
+"#;
+        let origin = Name::new("example.com.").unwrap();
+        let rrsets = parse_zone(zone, &origin).unwrap();
+        let output = ZoneWriter::new(origin.clone())
+            .write_to_string(&rrsets)
+            .unwrap();
+        assert!(output.contains(" IN NS  a\\.\n"));
+        assert_eq!(
+            canonical(parse_zone(&output, &origin).unwrap()),
+            canonical(rrsets)
+        );
+    }
+
+    fn canonical(mut rrsets: Vec<RRset>) -> Vec<RRset> {
+        for rrset in rrsets.iter_mut() {
+            rrset.rdatas.sort();
+        }
+        rrsets.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.typ.cmp(&b.typ)));
+        rrsets
+    }
+}